@@ -0,0 +1,129 @@
+//! Benchmarks finding the median with `quick_select` against sorting the whole list with quicksort
+//! and against `select_nth_unstable` from the standard library, for lists of integers and of
+//! phone numbers as strings, across sizes and distributions, and on the hostile inputs of `qn_1::adversarial`.
+//! Run with `cargo bench`, and open `target/criterion/report/index.html` for the plots.
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use qn_1::{adversarial::Adversary, quick_select};
+use question_2_rust::baseline::quicksort;
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+/// The sizes of the lists to find the median of
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+/// The sizes of the hostile inputs, which double so that quadratic growth shows as four times slower,
+/// and stay small as the worst cases take O(n^2)
+const ADVERSARIAL_SIZES: [usize; 4] = [1_000, 2_000, 4_000, 8_000];
+/// The seed of every generated list, so that each run measures the same inputs
+const SEED: u64 = 42;
+
+/// How the values of a list are drawn
+/// * `Uniform` - every value is drawn uniformly from 0 to 1,000,000
+/// * `Duplicates` - every value is one of 100, so most of them are repeated
+/// * `Sorted` - the values are drawn uniformly, then sorted
+#[derive(Debug, Clone, Copy)]
+enum Distribution {
+    Uniform,
+    Duplicates,
+    Sorted,
+}
+
+impl Distribution {
+    const ALL: [Distribution; 3] = [Distribution::Uniform, Distribution::Duplicates, Distribution::Sorted];
+
+    fn name(self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Duplicates => "duplicates",
+            Distribution::Sorted => "sorted",
+        }
+    }
+
+    /// Generates a list of integers drawn from this distribution
+    fn integers(self, size: usize) -> Vec<i64> {
+        let mut rng = ChaChaRng::seed_from_u64(SEED);
+        let max = match self {
+            Distribution::Duplicates => 100,
+            _ => 1_000_000,
+        };
+        let mut values: Vec<i64> = (0..size).map(|_| rng.gen_range(0..max)).collect();
+        if let Distribution::Sorted = self {
+            values.sort_unstable();
+        }
+        values
+    }
+}
+
+/// Generates a list of 10 digit phone numbers as strings, the way they are compared in `find_median_values`
+fn phone_numbers(size: usize) -> Vec<String> {
+    let mut rng = ChaChaRng::seed_from_u64(SEED);
+    (0..size).map(|_| rng.gen_range(1_000_000_000_i64..10_000_000_000).to_string()).collect()
+}
+
+/// Finds the median with quick select, the way `find_median_values` does for a list of odd length
+fn quick_select_median<T: PartialOrd + Clone>(mut values: Vec<T>) -> Option<T> {
+    let middle = values.len() / 2;
+    quick_select(&mut values, middle)
+}
+
+/// Finds the median by sorting the list with quicksort
+fn quicksort_median<T: PartialOrd + Clone>(mut values: Vec<T>) -> Option<T> {
+    quicksort(&mut values);
+    values.get(values.len() / 2).cloned()
+}
+
+/// Finds the median with `select_nth_unstable` from the standard library
+fn select_nth_unstable_median<T: Ord + Clone>(mut values: Vec<T>) -> Option<T> {
+    if values.is_empty() {
+        return None;
+    }
+    let middle = values.len() / 2;
+    Some(values.select_nth_unstable(middle).1.clone())
+}
+
+/// Measures each way of finding the median on lists of each size. Each one is given its own copy
+/// of the list, which is made outside the measurement.
+fn bench_list<T: Ord + Clone>(c: &mut Criterion, group_name: &str, distribution: &str, lists: &[(usize, Vec<T>)]) {
+    let mut group = c.benchmark_group(format!("{}/{}", group_name, distribution));
+    for (size, values) in lists {
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(BenchmarkId::new("quick_select", size), values, |b, values| {
+            b.iter_batched(|| values.clone(), quick_select_median, BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("quicksort", size), values, |b, values| {
+            b.iter_batched(|| values.clone(), quicksort_median, BatchSize::LargeInput)
+        });
+        group.bench_with_input(BenchmarkId::new("select_nth_unstable", size), values, |b, values| {
+            b.iter_batched(|| values.clone(), select_nth_unstable_median, BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn bench_integers(c: &mut Criterion) {
+    for distribution in Distribution::ALL {
+        let lists: Vec<(usize, Vec<i64>)> = SIZES.iter().map(|size| (*size, distribution.integers(*size))).collect();
+        bench_list(c, "median_integers", distribution.name(), &lists);
+    }
+}
+
+fn bench_adversarial(c: &mut Criterion) {
+    for adversary in Adversary::ALL {
+        let lists: Vec<(usize, Vec<i64>)> = ADVERSARIAL_SIZES.iter().map(|size| (*size, adversary.generate(*size, SEED))).collect();
+        bench_list(c, "median_adversarial", &adversary.to_string(), &lists);
+    }
+}
+
+fn bench_phone_numbers(c: &mut Criterion) {
+    let lists: Vec<(usize, Vec<String>)> = SIZES.iter().map(|size| (*size, phone_numbers(*size))).collect();
+    bench_list(c, "median_phone_numbers", "uniform", &lists);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20).measurement_time(Duration::from_secs(3));
+    targets = bench_integers, bench_adversarial, bench_phone_numbers
+}
+criterion_main!(benches);
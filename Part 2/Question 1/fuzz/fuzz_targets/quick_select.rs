@@ -0,0 +1,45 @@
+//! Fuzzes `quick_select` and `find_median_values` with arbitrary lists and k, checking that they never
+//! panic, only reorder the list, and find the same values as sorting it. Lists of floats, which can hold NaN
+//! and so are not totally ordered, must still not panic and must return one of their values.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use qn_1::{find_median_values, quick_select};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    values: Vec<i64>,
+    floats: Vec<f64>,
+    k: usize,
+}
+
+fuzz_target!(|input: Input| {
+    let Input { values, floats, k } = input;
+    let mut sorted = values.clone();
+    sorted.sort_unstable();
+    let mut selected = values.clone();
+    let result = quick_select(&mut selected, k);
+    assert_eq!(result, sorted.get(k).copied(), "k = {} of {:?}", k, values);
+    selected.sort_unstable();
+    assert_eq!(selected, sorted, "quick select changed the values");
+
+    let middle = sorted.len() / 2;
+    let expected = match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1], Some(sorted[middle]))),
+        _ => Some((sorted[middle], None)),
+    };
+    assert_eq!(find_median_values(&values), expected, "{:?}", values);
+
+    let mut selected = floats.clone();
+    match quick_select(&mut selected, k) {
+        Some(value) => assert!(floats.iter().any(|float| float.to_bits() == value.to_bits()), "{} is not one of {:?}", value, floats),
+        None => assert!(k >= floats.len()),
+    }
+    if let Some((low, high)) = find_median_values(&floats) {
+        assert!(floats.iter().any(|float| float.to_bits() == low.to_bits()));
+        assert!(high.map_or(true, |high| floats.iter().any(|float| float.to_bits() == high.to_bits())));
+    }
+});
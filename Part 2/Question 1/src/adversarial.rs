@@ -0,0 +1,343 @@
+//! Generators of hostile inputs for the selection algorithms, used to show how each of them
+//! behaves at its worst in the tests, the benchmarks and the `bench` binary.
+//! Every generator returns the values `0..n` in some order, apart from `all_equal` and `few_distinct`.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+/// The hostile inputs that can be generated
+/// * `OrganPipe` - rising to the middle, then falling back, such as `0 1 2 3 2 1 0`
+/// * `Sawtooth` - rising over and over, in teeth of about the square root of the length
+/// * `AllEqual` - every value is 0
+/// * `FewDistinct` - every value is drawn from 0 to 9
+/// * `Sorted` - the values in ascending order
+/// * `Reverse` - the values in descending order
+/// * `MedianOf3Killer` - the order that makes a median-of-3 pivot as bad as possible
+/// * `QuickselectKiller` - the order that makes `quick_select` as slow as possible for the median
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adversary {
+    OrganPipe,
+    Sawtooth,
+    AllEqual,
+    FewDistinct,
+    Sorted,
+    Reverse,
+    MedianOf3Killer,
+    QuickselectKiller,
+}
+
+impl Adversary {
+    pub const ALL: [Adversary; 8] = [
+        Adversary::OrganPipe,
+        Adversary::Sawtooth,
+        Adversary::AllEqual,
+        Adversary::FewDistinct,
+        Adversary::Sorted,
+        Adversary::Reverse,
+        Adversary::MedianOf3Killer,
+        Adversary::QuickselectKiller,
+    ];
+
+    /// Generates a hostile input of a given length
+    /// # Arguments
+    /// * `n` - the number of values
+    /// * `seed` - the seed of the random values, which only `FewDistinct` uses
+    /// # Example
+    /// ```rust
+    /// use qn_1::adversarial::Adversary;
+    /// assert_eq!(Adversary::OrganPipe.generate(7, 0), vec![0, 1, 2, 3, 2, 1, 0]);
+    /// assert_eq!(Adversary::Reverse.generate(3, 0), vec![2, 1, 0]);
+    /// ```
+    pub fn generate(self, n: usize, seed: u64) -> Vec<i64> {
+        match self {
+            Adversary::OrganPipe => organ_pipe(n),
+            Adversary::Sawtooth => sawtooth(n, (n as f64).sqrt().ceil().max(1.0) as usize),
+            Adversary::AllEqual => all_equal(n),
+            Adversary::FewDistinct => few_distinct(n, 10, seed),
+            Adversary::Sorted => sorted(n),
+            Adversary::Reverse => reverse_sorted(n),
+            Adversary::MedianOf3Killer => median_of_3_killer(n),
+            Adversary::QuickselectKiller => quickselect_killer(n, n / 2),
+        }
+    }
+}
+
+impl FromStr for Adversary {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "organ-pipe" => Ok(Adversary::OrganPipe),
+            "sawtooth" => Ok(Adversary::Sawtooth),
+            "all-equal" => Ok(Adversary::AllEqual),
+            "few-distinct" => Ok(Adversary::FewDistinct),
+            "sorted" => Ok(Adversary::Sorted),
+            "reverse" => Ok(Adversary::Reverse),
+            "median-of-3-killer" => Ok(Adversary::MedianOf3Killer),
+            "quickselect-killer" => Ok(Adversary::QuickselectKiller),
+            _ => Err("Input must be one of organ-pipe, sawtooth, all-equal, few-distinct, sorted, reverse, median-of-3-killer or quickselect-killer"),
+        }
+    }
+}
+
+impl fmt::Display for Adversary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Adversary::OrganPipe => write!(f, "organ-pipe"),
+            Adversary::Sawtooth => write!(f, "sawtooth"),
+            Adversary::AllEqual => write!(f, "all-equal"),
+            Adversary::FewDistinct => write!(f, "few-distinct"),
+            Adversary::Sorted => write!(f, "sorted"),
+            Adversary::Reverse => write!(f, "reverse"),
+            Adversary::MedianOf3Killer => write!(f, "median-of-3-killer"),
+            Adversary::QuickselectKiller => write!(f, "quickselect-killer"),
+        }
+    }
+}
+
+/// Rises from 0 to the middle, then falls back to 0
+pub fn organ_pipe(n: usize) -> Vec<i64> {
+    (0..n).map(|i| i.min(n - 1 - i) as i64).collect()
+}
+
+/// Rises from 0 to one less than the period, over and over
+pub fn sawtooth(n: usize, period: usize) -> Vec<i64> {
+    (0..n).map(|i| (i % period) as i64).collect()
+}
+
+/// Every value is 0
+pub fn all_equal(n: usize) -> Vec<i64> {
+    vec![0; n]
+}
+
+/// Every value is drawn at random from 0 to one less than the number of distinct values
+pub fn few_distinct(n: usize, distinct: usize, seed: u64) -> Vec<i64> {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    (0..n).map(|_| rng.gen_range(0..distinct as i64)).collect()
+}
+
+/// The values from 0 in ascending order
+pub fn sorted(n: usize) -> Vec<i64> {
+    (0..n as i64).collect()
+}
+
+/// The values from 0 in descending order
+pub fn reverse_sorted(n: usize) -> Vec<i64> {
+    (0..n as i64).rev().collect()
+}
+
+/// Musser's median-of-3 killer, which makes the median of the first, middle and last values
+/// one of the two smallest at every step of a quicksort or quick select that uses it as the pivot.
+/// The sequence is only defined for lengths that are a multiple of 4,
+/// so any remaining values are appended in ascending order.
+/// # Example
+/// ```rust
+/// use qn_1::adversarial::median_of_3_killer;
+/// assert_eq!(median_of_3_killer(8), vec![0, 4, 2, 6, 1, 3, 5, 7]);
+/// ```
+pub fn median_of_3_killer(n: usize) -> Vec<i64> {
+    let k = n / 4 * 2;
+    let mut values = vec![0; n];
+    // the sequence is indexed from 1, as in Musser's paper
+    for i in 1..=k {
+        if i % 2 == 1 {
+            values[i - 1] = i as i64;
+            values[i] = (k + i) as i64;
+        }
+        values[k + i - 1] = 2 * i as i64;
+    }
+    for (i, value) in values.iter_mut().enumerate().skip(2 * k) {
+        *value = i as i64 + 1;
+    }
+    values.iter().map(|value| value - 1).collect()
+}
+
+/// Builds the order that makes `quick_select` compare every remaining value at each step when it
+/// looks for the k-th smallest, so that it takes O(n^2) comparisons.
+/// `quick_select` always takes the middle of what is left as the pivot and splits the rest into
+/// smaller, equal and larger values. This replays those steps on values that have not been given yet,
+/// giving each pivot the smallest or largest value still free, whichever keeps the k-th smallest
+/// on the larger side. Only one value is then removed at each step.
+/// # Arguments
+/// * `n` - the number of values
+/// * `k` - the index of the value that `quick_select` will be asked for, such as `n / 2` for the median
+/// # Example
+/// ```rust
+/// use qn_1::{adversarial::quickselect_killer, quick_select};
+/// let mut values = quickselect_killer(9, 4);
+/// assert_eq!(values, vec![3, 4, 6, 5, 0, 1, 8, 2, 7]);
+/// assert_eq!(quick_select(&mut values, 4), Some(4));
+/// ```
+pub fn quickselect_killer(n: usize, k: usize) -> Vec<i64> {
+    // the positions of the input, moved the same way `quick_select` moves the values at them
+    let mut positions: Vec<usize> = (0..n).collect();
+    let mut values = vec![0; n];
+    let (mut smallest, mut largest) = (0, n as i64 - 1);
+    let (mut start, mut end, mut k) = (0, n, k.min(n.saturating_sub(1)));
+    while end - start > 1 {
+        let part = &mut positions[start..end];
+        let len = part.len();
+        let pivot = part[len / 2];
+        // every other value in this part is still free, so it is larger than a smallest pivot
+        // and smaller than a largest one
+        let pivot_is_smallest = k >= len / 2;
+        if pivot_is_smallest {
+            values[pivot] = smallest;
+            smallest += 1;
+        } else {
+            values[pivot] = largest;
+            largest -= 1;
+        }
+        // the same three way partition as `quick_select`
+        let (mut i, mut j, mut last) = (0, 0, len - 1);
+        while j <= last {
+            if part[j] == pivot {
+                j += 1;
+            } else if pivot_is_smallest {
+                part.swap(j, last);
+                last -= 1;
+            } else {
+                part.swap(i, j);
+                i += 1;
+                j += 1;
+            }
+        }
+        // the pivot is now alone at one end, and the k-th smallest is among the rest
+        if pivot_is_smallest {
+            start += 1;
+            k -= 1;
+        } else {
+            end -= 1;
+        }
+    }
+    if start < end {
+        values[positions[start]] = smallest;
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quick_select;
+    use question_2_rust::baseline::quicksort;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A value that counts every time it is compared
+    #[derive(Debug, Clone, PartialEq)]
+    struct Counted(i64);
+
+    impl PartialOrd for Counted {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            COMPARISONS.with(|count| count.set(count.get() + 1));
+            self.0.partial_cmp(&other.0)
+        }
+    }
+
+    /// This function counts the comparisons a selection algorithm makes on the given values
+    fn count_comparisons(values: &[i64], select: impl FnOnce(&mut [Counted])) -> usize {
+        let mut counted: Vec<Counted> = values.iter().map(|value| Counted(*value)).collect();
+        COMPARISONS.with(|count| count.set(0));
+        select(&mut counted);
+        COMPARISONS.with(|count| count.get())
+    }
+
+    /// This function counts the comparisons `quick_select` makes to find the k-th smallest value
+    fn count_quick_select(values: &[i64], k: usize) -> usize {
+        count_comparisons(values, |values| {
+            quick_select(values, k);
+        })
+    }
+
+    /// This test checks the shape of each generator, and that the ones that reorder `0..n` are permutations of it
+    #[test]
+    fn test_adversarial_shapes() {
+        assert_eq!(organ_pipe(6), vec![0, 1, 2, 2, 1, 0]);
+        assert_eq!(sawtooth(7, 3), vec![0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(all_equal(3), vec![0, 0, 0]);
+        assert!(few_distinct(100, 10, 1).iter().all(|value| (0..10).contains(value)));
+        assert_eq!(median_of_3_killer(10), vec![0, 4, 2, 6, 1, 3, 5, 7, 8, 9]);
+        for n in 0..100 {
+            for values in [median_of_3_killer(n), quickselect_killer(n, n / 2), quickselect_killer(n, 0)] {
+                let mut sorted_values = values.clone();
+                sorted_values.sort_unstable();
+                assert_eq!(sorted_values, sorted(n), "{:?}", values);
+            }
+        }
+    }
+
+    /// This test checks that quick select, quicksort and `select_nth_unstable` all find the right values
+    /// on every hostile input
+    #[test]
+    fn test_selection_on_adversarial_inputs() {
+        for adversary in Adversary::ALL {
+            for n in (0..60).chain([1000, 1001]) {
+                let values = adversary.generate(n, n as u64);
+                let mut expected = values.clone();
+                expected.sort_unstable();
+                let mut sorted_values = values.clone();
+                quicksort(&mut sorted_values);
+                assert_eq!(sorted_values, expected, "{} of {}", adversary, n);
+                for k in [0, n / 3, n / 2, n.saturating_sub(1)].into_iter().filter(|k| *k < n) {
+                    assert_eq!(quick_select(&mut values.clone(), k), Some(expected[k]), "{} of {}, k = {}", adversary, n, k);
+                    assert_eq!(*values.clone().select_nth_unstable(k).1, expected[k], "{} of {}, k = {}", adversary, n, k);
+                }
+            }
+        }
+    }
+
+    /// This test shows the worst case of `quick_select`: the killer makes it compare about n^2 / 2 times
+    /// to find the median, while random, sorted and all equal inputs take a number of comparisons
+    /// that grows linearly. The median-of-3 killer slows it down too, but nowhere near as much.
+    #[test]
+    fn test_quickselect_killer_is_quadratic() {
+        let n = 2000;
+        let killer = count_quick_select(&quickselect_killer(n, n / 2), n / 2);
+        assert!(killer >= n * n / 2, "{} comparisons", killer);
+        for values in [few_distinct(n, n, 42), sorted(n), all_equal(n)] {
+            let comparisons = count_quick_select(&values, n / 2);
+            assert!(comparisons < 10 * n, "{} comparisons", comparisons);
+        }
+        let median_of_3 = count_quick_select(&median_of_3_killer(n), n / 2);
+        assert!(median_of_3 < killer / 10, "{} comparisons", median_of_3);
+        // the killer is built for one k, so it is not as bad for another
+        assert!(count_quick_select(&quickselect_killer(n, n / 2), 0) < killer);
+    }
+
+    /// This test shows the worst case of the quicksort baseline: the killers do not attack its random pivots,
+    /// but its two way partition moves only the pivot when every value is equal, so it compares about n^2 / 2 times
+    #[test]
+    fn test_quicksort_all_equal_is_quadratic() {
+        let n = 2000;
+        let all_equal = count_comparisons(&all_equal(n), quicksort);
+        assert!(all_equal >= n * (n - 1) / 2, "{} comparisons", all_equal);
+        for values in [quickselect_killer(n, n / 2), median_of_3_killer(n), sorted(n)] {
+            let comparisons = count_comparisons(&values, quicksort);
+            assert!(comparisons < n * n / 10, "{} comparisons", comparisons);
+        }
+    }
+
+    /// This test runs the worst cases of `quick_select` and the quicksort baseline on a thread with a
+    /// 64 KiB stack. Each partition of them only removes a few values, so they would overflow the stack
+    /// if they recursed once per partition, as the bench binary did on the killer of 300,000 values.
+    #[test]
+    fn test_worst_cases_fit_in_a_small_stack() {
+        let n = 5000;
+        let thread = std::thread::Builder::new().stack_size(64 * 1024).spawn(move || {
+            let mut killer = quickselect_killer(n, n / 2);
+            assert_eq!(quick_select(&mut killer, n / 2), Some(n as i64 / 2));
+            let mut values = all_equal(n);
+            quicksort(&mut values);
+            assert_eq!(values, all_equal(n));
+        });
+        thread.unwrap().join().unwrap();
+    }
+}
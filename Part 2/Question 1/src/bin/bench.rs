@@ -0,0 +1,406 @@
+use std::fmt;
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitCode};
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use clap::Parser;
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use qn_1::{adversarial::Adversary, quick_select};
+use question_2_rust::{baseline::{naive_sorting_find_kth_nearest, quicksort}, nearest::kth_nearest, PhoneCounts};
+
+/// Times the selection and nearest neighbour algorithms on generated phone numbers for every size
+/// in a range, writing each measurement to a CSV file. A sidecar next to it records the git revision,
+/// the CPU, the seed and every parameter, so that the results and the plots in `analysis.ipynb`
+/// can be reproduced.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// The algorithms to time, separated by commas: `quick_select`, `quicksort` and `select_nth_unstable`
+    /// find the median, while `kth_nearest` and `naive_sorting` find the k nearest numbers to a target
+    #[arg(long, value_delimiter = ',', default_value = "quick_select,quicksort")]
+    algorithms: Vec<Algorithm>,
+    /// The smallest number of phone numbers to time the algorithms on
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    min_size: u64,
+    /// The largest number of phone numbers to time the algorithms on, inclusive
+    #[arg(long, default_value_t = 10_000)]
+    max_size: u64,
+    /// How much the number of phone numbers grows between each size
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    step: u64,
+    /// The number of times each algorithm is timed on each size
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    repetitions: u64,
+    /// How the phone numbers are drawn: `uniform`, `duplicates` (1% as many unique numbers as the largest size),
+    /// `sorted`, `clustered` (one area code and exchange), or one of the hostile inputs of `qn_1::adversarial`:
+    /// `organ-pipe`, `sawtooth`, `all-equal`, `few-distinct`, `reverse`, `median-of-3-killer` or `quickselect-killer`
+    #[arg(long, default_value = "uniform")]
+    distribution: Distribution,
+    /// Compare the phone numbers as strings rather than integers, which only the median algorithms can do
+    #[arg(long)]
+    strings: bool,
+    /// The number of unique nearest numbers for `kth_nearest` and `naive_sorting` to find
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    k: u64,
+    /// The seed the phone numbers and targets are generated from
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// The number of measurements to run at once. More than 1 finishes sooner,
+    /// but the measurements then compete for the CPU and its caches
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: u64,
+    /// The CSV file to write, with the metadata written next to it with a `.meta.json` extension
+    #[arg(short, long, default_value = "bench.csv")]
+    output: PathBuf,
+}
+
+/// The algorithms that can be timed
+/// * `QuickSelect` - `quick_select` for the median
+/// * `Quicksort` - sorting with the quicksort baseline, then taking the middle
+/// * `SelectNthUnstable` - `select_nth_unstable` from the standard library for the median
+/// * `KthNearest` - `kth_nearest` for the k nearest numbers, including counting them
+/// * `NaiveSorting` - the baseline for the k nearest numbers, which sorts them first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(into = "String")]
+enum Algorithm {
+    QuickSelect,
+    Quicksort,
+    SelectNthUnstable,
+    KthNearest,
+    NaiveSorting,
+}
+
+impl Algorithm {
+    /// Whether the algorithm finds the k nearest numbers rather than the median
+    fn is_nearest(self) -> bool {
+        matches!(self, Algorithm::KthNearest | Algorithm::NaiveSorting)
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quick_select" => Ok(Algorithm::QuickSelect),
+            "quicksort" => Ok(Algorithm::Quicksort),
+            "select_nth_unstable" => Ok(Algorithm::SelectNthUnstable),
+            "kth_nearest" => Ok(Algorithm::KthNearest),
+            "naive_sorting" => Ok(Algorithm::NaiveSorting),
+            _ => Err("Algorithm must be one of quick_select, quicksort, select_nth_unstable, kth_nearest or naive_sorting"),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::QuickSelect => write!(f, "quick_select"),
+            Algorithm::Quicksort => write!(f, "quicksort"),
+            Algorithm::SelectNthUnstable => write!(f, "select_nth_unstable"),
+            Algorithm::KthNearest => write!(f, "kth_nearest"),
+            Algorithm::NaiveSorting => write!(f, "naive_sorting"),
+        }
+    }
+}
+
+impl From<Algorithm> for String {
+    fn from(algorithm: Algorithm) -> Self {
+        algorithm.to_string()
+    }
+}
+
+/// How the phone numbers are drawn
+/// * `Uniform` - every 10 digit phone number is equally likely
+/// * `Duplicates` - every phone number is one of 1% as many, so most of them are repeated
+/// * `Sorted` - the phone numbers are drawn uniformly, then sorted
+/// * `Clustered` - every phone number has the same area code and exchange, like `phonescraped`
+/// * `Adversarial` - a hostile input, generated again for every size, as a prefix of one is not as hostile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(into = "String")]
+enum Distribution {
+    Uniform,
+    Duplicates,
+    Sorted,
+    Clustered,
+    Adversarial(Adversary),
+}
+
+impl Distribution {
+    /// Generates the phone numbers, every size being timed on the ones at the start,
+    /// followed by the target of the nearest neighbour algorithms
+    fn generate(self, size: usize, seed: u64) -> (Vec<i64>, i64) {
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        let unique = (size as i64 / 100).max(1);
+        let draw = |rng: &mut ChaChaRng| match self {
+            Distribution::Uniform | Distribution::Sorted => rng.gen_range(1_000_000_000..10_000_000_000),
+            Distribution::Duplicates => 1_000_000_000 + rng.gen_range(0..unique) * (9_000_000_000 / unique),
+            Distribution::Clustered => rng.gen_range(7_327_320_000..7_327_330_000),
+            Distribution::Adversarial(_) => 1_000_000_000 + size as i64 / 2,
+        };
+        if let Distribution::Adversarial(adversary) = self {
+            // every value is given 10 digits, so that they are in the same order as strings
+            let numbers = adversary.generate(size, seed).into_iter().map(|value| 1_000_000_000 + value).collect();
+            return (numbers, draw(&mut rng));
+        }
+        let mut numbers: Vec<i64> = (0..size).map(|_| draw(&mut rng)).collect();
+        let target = draw(&mut rng);
+        if self == Distribution::Sorted {
+            numbers.sort_unstable();
+        }
+        (numbers, target)
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(Distribution::Uniform),
+            "duplicates" => Ok(Distribution::Duplicates),
+            "sorted" => Ok(Distribution::Sorted),
+            "clustered" => Ok(Distribution::Clustered),
+            _ => s.parse().map(Distribution::Adversarial).map_err(|_| {
+                "Distribution must be one of uniform, duplicates, sorted, clustered, organ-pipe, sawtooth, all-equal, \
+                 few-distinct, reverse, median-of-3-killer or quickselect-killer"
+            }),
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Distribution::Uniform => write!(f, "uniform"),
+            Distribution::Duplicates => write!(f, "duplicates"),
+            Distribution::Sorted => write!(f, "sorted"),
+            Distribution::Clustered => write!(f, "clustered"),
+            Distribution::Adversarial(adversary) => write!(f, "{}", adversary),
+        }
+    }
+}
+
+impl From<Distribution> for String {
+    fn from(distribution: Distribution) -> Self {
+        distribution.to_string()
+    }
+}
+
+/// A single timing of an algorithm
+struct Measurement {
+    algorithm: Algorithm,
+    size: usize,
+    repetition: u64,
+    time: Duration,
+}
+
+/// How the benchmark was run, which is written next to the results
+#[derive(Debug, Serialize)]
+struct Metadata<'a> {
+    git_revision: Option<String>,
+    git_dirty: Option<bool>,
+    cpu: Option<String>,
+    logical_cpus: usize,
+    os: &'static str,
+    arch: &'static str,
+    seed: u64,
+    parameters: Parameters<'a>,
+    started: u64,
+    elapsed_seconds: f64,
+    measurements: usize,
+}
+
+/// The parameters of the benchmark, as given on the command line
+#[derive(Debug, Serialize)]
+struct Parameters<'a> {
+    algorithms: &'a [Algorithm],
+    min_size: u64,
+    max_size: u64,
+    step: u64,
+    repetitions: u64,
+    distribution: Distribution,
+    strings: bool,
+    k: u64,
+    threads: u64,
+}
+
+/// This function times every algorithm on every size, then writes the results and their metadata.
+/// # Example
+/// ```bash
+/// cargo run --release --bin bench -- --algorithms quick_select,quicksort --max-size 100000 --strings --output results_phone.csv
+/// > wrote 1000000 measurements to results_phone.csv and results_phone.meta.json
+/// ```
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.min_size > cli.max_size {
+        eprintln!("error: the minimum size {} is larger than the maximum size {}", cli.min_size, cli.max_size);
+        return ExitCode::from(2);
+    }
+    if cli.strings && cli.algorithms.iter().any(|algorithm| algorithm.is_nearest()) {
+        eprintln!("error: --strings can only be used with quick_select, quicksort and select_nth_unstable");
+        return ExitCode::from(2);
+    }
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    let start = Instant::now();
+    let measurements = match run(&cli) {
+        Ok(measurements) => measurements,
+        Err(error) => {
+            eprintln!("error: could not run the benchmark: {}", error);
+            return ExitCode::from(3);
+        }
+    };
+    let metadata = Metadata {
+        git_revision: git(&["rev-parse", "HEAD"]).map(|revision| revision.trim().to_owned()),
+        git_dirty: git(&["status", "--porcelain"]).map(|status| !status.trim().is_empty()),
+        cpu: cpu_model(),
+        logical_cpus: std::thread::available_parallelism().map_or(1, |cpus| cpus.get()),
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        seed: cli.seed,
+        parameters: Parameters {
+            algorithms: &cli.algorithms,
+            min_size: cli.min_size,
+            max_size: cli.max_size,
+            step: cli.step,
+            repetitions: cli.repetitions,
+            distribution: cli.distribution,
+            strings: cli.strings,
+            k: cli.k,
+            threads: cli.threads,
+        },
+        started,
+        elapsed_seconds: start.elapsed().as_secs_f64(),
+        measurements: measurements.len(),
+    };
+    let sidecar = cli.output.with_extension("meta.json");
+    let written = write_results(&cli.output, &cli, &measurements).and_then(|_| write_metadata(&sidecar, &metadata));
+    if let Err(error) = written {
+        eprintln!("error: could not write the results: {}", error);
+        return ExitCode::from(3);
+    }
+    eprintln!("wrote {} measurements to {} and {}", measurements.len(), cli.output.display(), sidecar.display());
+    ExitCode::SUCCESS
+}
+
+/// Times every algorithm on every size the given number of times, on a pool of the given number of threads
+/// # Returns
+/// * `Vec<Measurement>` - the measurements, ordered by size, then algorithm, then repetition
+fn run(cli: &Cli) -> Result<Vec<Measurement>, rayon::ThreadPoolBuildError> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(cli.threads as usize).build()?;
+    let (all_numbers, all_target) = match cli.distribution {
+        Distribution::Adversarial(_) => (Vec::new(), 0),
+        _ => cli.distribution.generate(cli.max_size as usize, cli.seed),
+    };
+    let mut measurements = Vec::new();
+    for size in (cli.min_size..=cli.max_size).step_by(cli.step as usize) {
+        let size = size as usize;
+        let (numbers, target) = match cli.distribution {
+            Distribution::Adversarial(_) => cli.distribution.generate(size, cli.seed),
+            _ => (all_numbers[..size].to_vec(), all_target),
+        };
+        let strings: Vec<String> = match cli.strings {
+            true => numbers.iter().map(|number| number.to_string()).collect(),
+            false => Vec::new(),
+        };
+        let tasks: Vec<(Algorithm, u64)> = cli
+            .algorithms
+            .iter()
+            .flat_map(|algorithm| (0..cli.repetitions).map(move |repetition| (*algorithm, repetition)))
+            .collect();
+        measurements.extend(pool.install(|| {
+            tasks
+                .into_par_iter()
+                .map(|(algorithm, repetition)| {
+                    let time = match (algorithm.is_nearest(), cli.strings) {
+                        (true, _) => time_nearest(algorithm, &numbers, target, cli.k),
+                        (false, true) => time_median(algorithm, &strings),
+                        (false, false) => time_median(algorithm, &numbers),
+                    };
+                    Measurement { algorithm, size, repetition, time }
+                })
+                .collect::<Vec<_>>()
+        }));
+    }
+    Ok(measurements)
+}
+
+/// Times finding the median of a copy of the values, which is made before the timing starts
+fn time_median<T: Ord + Clone>(algorithm: Algorithm, values: &[T]) -> Duration {
+    let mut values = values.to_vec();
+    let middle = values.len() / 2;
+    let start = Instant::now();
+    match algorithm {
+        Algorithm::QuickSelect => {
+            black_box(quick_select(&mut values, middle));
+        }
+        Algorithm::Quicksort => {
+            quicksort(&mut values);
+            black_box(&values[middle]);
+        }
+        _ => {
+            black_box(values.select_nth_unstable(middle));
+        }
+    }
+    start.elapsed()
+}
+
+/// Times finding the k nearest numbers to the target, starting from a copy of the numbers
+/// which is made before the timing starts
+fn time_nearest(algorithm: Algorithm, numbers: &[i64], target: i64, k: u64) -> Duration {
+    let numbers = numbers.to_vec();
+    let start = Instant::now();
+    match algorithm {
+        Algorithm::KthNearest => black_box(kth_nearest(k as i64, target, &PhoneCounts::from_numbers(&numbers))),
+        _ => black_box(naive_sorting_find_kth_nearest(k, target, numbers)),
+    };
+    start.elapsed()
+}
+
+/// Writes one row per measurement, with a header
+fn write_results(path: &Path, cli: &Cli, measurements: &[Measurement]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "algorithm,distribution,n,repetition,nanoseconds")?;
+    for measurement in measurements {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            measurement.algorithm,
+            cli.distribution,
+            measurement.size,
+            measurement.repetition,
+            measurement.time.as_nanos()
+        )?;
+    }
+    writer.flush()
+}
+
+/// Writes the metadata as pretty printed JSON
+fn write_metadata(path: &Path, metadata: &Metadata) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, metadata)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Runs git in the directory the binary was built from, returning its output if it succeeds
+fn git(args: &[&str]) -> Option<String> {
+    let output = process::Command::new("git").arg("-C").arg(env!("CARGO_MANIFEST_DIR")).args(args).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Reads the model of the CPU, which is only known on Linux
+fn cpu_model() -> Option<String> {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .find_map(|line| line.strip_prefix("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_owned())
+}
@@ -0,0 +1,314 @@
+//! Finding the median of a list of phone numbers with quick select, which the binary uses
+//! and the benchmarks compare against sorting.
+
+// only the tests and the benchmarks need the hostile inputs, so the binary is built without them and their dependencies
+#[cfg(any(test, feature = "bench"))]
+pub mod adversarial;
+
+/// This function uses quick select to find the kth smallest element in an array,
+/// indexed from 0.
+/// In this case we are using it to find the median value.
+/// It is adapted from the quick sort algorithm, but instead of recursing on both
+/// sides of the array, it continues on only one side.
+/// It has an average time complexity of O(n), but worst case is O(n^2).
+/// It loops rather than recursing, so even the worst case, such as `adversarial::quickselect_killer`,
+/// only needs a constant amount of stack.
+/// # Arguments
+/// * `arr` - A mutable slice of type T
+/// * `k` - The index of the element to find
+/// # Returns
+/// * `Option<T>` - The kth smallest element in the array
+/// # Examples 
+/// ```
+/// use qn_1::quick_select;
+/// let mut arr = [1, 2, 3, 4, 5];
+/// let k = 2;
+/// let res = quick_select(&mut arr, k);
+/// assert_eq!(res, Some(3));
+/// ```
+pub fn quick_select<T: PartialOrd + Clone>(mut arr: &mut [T], mut k: usize) -> Option<T> {
+    /*
+     * This function uses quick select to find the kth smallest element in an array.
+     * In this case we are using it to find the median value.
+     * It is adapted from the quick sort algorithm, but instead of recursing on both
+     * sides of the array, it continues on only one side.
+     */
+    loop {
+        let len = arr.len();
+        if len == 0 || k >= len {
+            return None;
+        }
+        let pivot_index = len / 2;
+        let pivot_value = arr[pivot_index].clone();
+
+        let mut i = 0; // left side of the array
+        let mut j = 0; // left side of the array (accounts for duplicates)
+        let mut n = len - 1; // right side of the array
+        while j <= n {
+            if arr[j] < pivot_value {
+                arr.swap(i, j);
+                i += 1;
+                j += 1;
+            } else if arr[j] > pivot_value {
+                arr.swap(j, n);
+                n -= 1;
+            } else {
+                j += 1;
+            }
+        }
+        if i <= k && k <= n {
+            // Result is simply the k-th element
+            return Some(arr[k].clone());
+        } else if k < i {
+            // Continue on the left side of the array
+            arr = &mut arr[..i];
+        } else {
+            // Continue on the right side of the array
+            arr = &mut arr[n + 1..];
+            k -= n + 1;
+        }
+    }
+}
+
+/// This function finds the median value(s) of an array.
+/// If the array has an even number of elements, it returns the two middle values.
+/// If the array has an odd number of elements, it returns the middle value.
+/// It uses quick select to find the median value(s).
+/// It has an average time complexity of O(n), but worst case is O(n^2).
+/// # Arguments
+/// * `arr` - A slice of type T
+/// # Returns
+/// * `Option<(T, Option<T>)>` - A tuple of the median value(s)
+/// # Examples
+/// ```
+/// use qn_1::find_median_values;
+/// let arr = [1, 2, 3, 4, 5];
+/// let res = find_median_values(&arr);
+/// assert_eq!(res, Some((3, None)));
+/// ```
+pub fn find_median_values<T: PartialOrd + Clone>(arr: &[T]) -> Option<(T, Option<T>)> {
+    /*
+     * This function finds the median value(s) of an array.
+     * If the array has an even number of elements, it returns the two middle values.
+     * If the array has an odd number of elements, it returns the middle value.
+     * It uses quick select to find the median value(s).
+     * It has an average time complexity of O(n), but worst case is O(n^2).
+     * It is adapted from the quick sort algorithm, but instead of recursing on both
+     * sides of the array, it recurses on only one side.
+     * It returns a tuple of the median value(s).
+     */
+    let mut arr_clone = arr.to_vec();
+    let length = arr.len();
+    // handle the case where the array is empty
+    if length == 0 {
+        return Option::None;
+    }
+    if length % 2 == 0 {
+        // return two values if the array has an even number of elements
+        let median = quick_select(&mut arr_clone, length / 2 - 1);
+        let median2 = quick_select(&mut arr_clone, length / 2);
+        match (median, median2) {
+            (Some(median), Some(median2)) => {
+                Option::Some((median, Some(median2)))
+            },
+            _ => {
+                Option::None
+            }
+        }
+    } else {
+        // otherwise return one value
+        let median = quick_select(&mut arr_clone, length / 2);
+        median.map(|median| (median, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::test_runner::FileFailurePersistence;
+    use question_2_rust::generate::digit_strings;
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+    /// The seed of the random tests, which is fixed so that every run tests the same inputs,
+    /// unless another one is given in the `TEST_SEED` environment variable
+    fn test_seed(default: u64) -> u64 {
+        std::env::var("TEST_SEED").ok().and_then(|seed| seed.parse().ok()).unwrap_or(default)
+    }
+
+    /// This function is used to test quick select by comparing it to the sort function
+    /// on a vector of random numbers.
+    /// It creates a vector of random numbers, then sorts the vector and finds the median value,
+    /// then asserts that the quick select algorithm also finds the median value.
+    #[test]
+    fn test_quick_select() {
+        /* 
+         * This test checks that the quick select algorithm works to find the median
+         * value for an array of numbers. It uses a random number generator to create
+         * a vector of random numbers, then sorts the vector and finds the median value,
+         * then asserts that the quick select algorithm also finds the median value.
+         */
+        // create a vector of random numbers with a max length, max value and seed.
+        let max_length: usize = 100; // max possible length of the vector
+        let max_value: usize = 100; // max possible value of the random numbers
+        let num_tries = 100; // number of times to run the test
+        // initialize the random number generator once, so that every try draws another vector
+        let seed = test_seed(46);
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        for attempt in 0..num_tries {
+            let num_elements = rng.gen_range(1..max_length);
+            let numbers: Vec<usize> = (0..num_elements)
+                .map(|_| rng.gen_range(0..max_value))
+                .collect();
+            // find the median of a clone of the vector
+            let mut numbers_clone = numbers.clone();
+            numbers_clone.sort();
+            let median = if num_elements % 2 == 0 {
+                // if the length of the array is even, return the two middle values
+                Option::Some((numbers_clone[num_elements / 2 - 1], Some(numbers_clone[num_elements / 2])))
+            } else {
+                // otherwise, return the middle value
+                Option::Some((numbers_clone[num_elements / 2], None))
+            };
+            let res = find_median_values(&numbers);
+            match(&median, &res) {
+                // match the median and the result of the quick select algorithm
+                (Some((median, Some(median2))), Some((res, Some(res2)))) => {
+                    assert_eq!(median, res, "seed {}, attempt {}", seed, attempt);
+                    assert_eq!(median2, res2, "seed {}, attempt {}", seed, attempt);
+                },
+                (Some((median, None)), Some((res, None))) => {
+                    assert_eq!(median, res, "seed {}, attempt {}", seed, attempt);
+                },
+                _ => {
+                    panic!("median and res are not the same, seed {}, attempt {}", seed, attempt);
+                }
+            }
+        }
+    }
+    
+    /// This function tests the worst case scenario for the quick select algorithm.
+    /// It creates a vector of numbers from 1 to 10, then calls quick select on the
+    /// vector to find the 0th, 1st, 2nd, 3rd, and 4th element, then finds the median of the
+    /// quick select killer from `adversarial` for every length up to 200.
+    /// It asserts that the quick select algorithm returns the correct values.
+    #[test]
+    fn quick_select_worst_case() {
+        /*
+         *  This test quickly checks some manual inputs to make sure the quick select
+         *  algorithm works in the worst case scenario.
+         */
+        let mut arr = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let res = quick_select(&mut arr, 0);
+        assert_eq!(res, Some(1));
+        let res = quick_select(&mut arr, 1);
+        assert_eq!(res, Some(2));
+        let res = quick_select(&mut arr, 2);
+        assert_eq!(res, Some(3));
+        let res = quick_select(&mut arr, 3);
+        assert_eq!(res, Some(4));
+
+        // the input built to be the worst case of the middle pivot, for every length and the median
+        for len in 1..200 {
+            let mut killer = adversarial::quickselect_killer(len, len / 2);
+            assert_eq!(quick_select(&mut killer, len / 2), Some(len as i64 / 2), "length {}", len);
+        }
+    }
+
+    /// This test is used to test quick select by comparing it to the sort function
+    /// on a vector of random phone numbers.
+    /// It creates a vector of random phone numbers, then sorts the vector and finds the median value,
+    /// then asserts that the quick select algorithm also finds the median value.
+    #[test]
+    fn test_quick_select_phone_numbers() {
+        let max_length: usize = 100;
+        let seed = test_seed(46);
+        let num_tries = 100;
+        // initialize the random number generator once, so that every try draws other phone numbers
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        // create a vector of random phone numbers with a max length, max value and seed.
+        for attempt in 0..num_tries {
+            let num_elements = rng.gen_range(1..max_length);
+            let phone_numbers: Vec<String> = digit_strings(num_elements, 10, rng.gen());
+            // find the median of a clone of the vector
+            let mut phone_numbers_clone = phone_numbers.clone();
+            // clean the phone numbers
+            phone_numbers_clone = phone_numbers_clone
+                .iter()
+                .map(|number| number.chars().rev().take(10).collect::<String>().chars().rev().collect::<String>())
+                .collect();
+            phone_numbers_clone.sort();
+            let median = if num_elements % 2 == 0 {
+                Some((phone_numbers_clone[num_elements / 2 - 1].clone(), Some(phone_numbers_clone[num_elements / 2].clone())))
+            } else {
+                Some((phone_numbers_clone[num_elements / 2].clone(), None))
+            };
+            let res = find_median_values(&phone_numbers);
+            // Check that the median and the result of the quick select algorithm are the same
+            match(&median, &res) {
+                (Some((median, Some(median2))), Some((res, Some(res2)))) => {
+                    assert_eq!(median, res, "seed {}, attempt {}", seed, attempt);
+                    assert_eq!(median2, res2, "seed {}, attempt {}", seed, attempt);
+                },
+                (Some((median, None)), Some((res, None))) => {
+                    assert_eq!(median, res, "seed {}, attempt {}", seed, attempt);
+                },
+                _ => {
+                    panic!("median and res are not the same, seed {}, attempt {}", seed, attempt);
+                }
+            }
+        }
+    }
+
+    /// The values a list is drawn from: any integer, or a few small ones so that most of them are repeated
+    fn values() -> impl Strategy<Value = Vec<i64>> {
+        prop_oneof![
+            prop::collection::vec(any::<i64>(), 1..200),
+            prop::collection::vec(0..5_i64, 1..200),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            failure_persistence: Some(Box::new(FileFailurePersistence::SourceParallel("proptest-regressions"))),
+            ..ProptestConfig::default()
+        })]
+
+        /// Quick select finds the same k-th smallest value as sorting, for every k
+        #[test]
+        fn prop_quick_select_matches_sorting(values in values(), index in any::<prop::sample::Index>()) {
+            let k = index.index(values.len());
+            let mut sorted = values.clone();
+            sorted.sort_unstable();
+            prop_assert_eq!(quick_select(&mut values.clone(), k), Some(sorted[k]));
+            prop_assert_eq!(quick_select(&mut values.clone(), values.len()), None);
+        }
+
+        /// The median is the middle value of the sorted list, or the two middle values if its length is even
+        #[test]
+        fn prop_median_matches_sorting(values in values()) {
+            let mut sorted = values.clone();
+            sorted.sort_unstable();
+            let middle = sorted.len() / 2;
+            let expected = match sorted.len() % 2 {
+                0 => (sorted[middle - 1], Some(sorted[middle])),
+                _ => (sorted[middle], None),
+            };
+            prop_assert_eq!(find_median_values(&values), Some(expected));
+        }
+
+        /// The median of phone numbers compared as strings is the same as sorting the strings
+        #[test]
+        fn prop_median_of_strings_matches_sorting(values in prop::collection::vec("[0-9]{10}", 1..100)) {
+            let mut sorted = values.clone();
+            sorted.sort_unstable();
+            let middle = sorted.len() / 2;
+            let expected = match sorted.len() % 2 {
+                0 => (sorted[middle - 1].clone(), Some(sorted[middle].clone())),
+                _ => (sorted[middle].clone(), None),
+            };
+            prop_assert_eq!(find_median_values(&values), Some(expected));
+        }
+    }
+}
@@ -1,495 +1,285 @@
-use std::env;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::process::ExitCode;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use qn_1::find_median_values;
+use phone_io::binary::{is_binary_file, BinaryDataset};
+use phone_io::output::{write_report, InputStats, OutputFormat, Report};
+use phone_io::compress::{self, decompress};
+use phone_io::records::{describe_rejected, parse_record, read_records, stream_records, Layout};
+use phone_io::table::{Table, TableFormat};
+
+/// The exit code when there is no median, because no valid phone numbers were given
+const EXIT_NO_RESULT: u8 = 1;
+/// The exit code when the arguments are invalid, which is also the code clap exits with
+const EXIT_USAGE: u8 = 2;
+/// The exit code when a file or stdin cannot be read
+const EXIT_IO: u8 = 3;
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  The median was found
+  1  No valid phone numbers were given, so there is no median
+  2  The arguments are invalid, or a phone number is invalid with --strict
+  3  A file or stdin could not be read";
+
+/// Finds the median of a list of phone numbers.
+/// Without a subcommand, the arguments are the same as the `median` subcommand.
+#[derive(Debug, Parser)]
+#[command(version, args_conflicts_with_subcommands = true, after_help = EXIT_CODES_HELP)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    median: Option<MedianArgs>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Find the median phone number, or the two middle phone numbers if there is an even number of them
+    Median(MedianArgs),
+}
+
+#[derive(Debug, Args)]
+struct MedianArgs {
+    /// The phone numbers, in any format such as `123-456-7890` or `+1 (223) 456-7890`
+    #[arg(required_unless_present = "files")]
+    phone_numbers: Vec<String>,
+    /// A file to read phone numbers from, one per line, or `-` to read them from stdin.
+    /// Can be given more than once, and together with phone numbers as arguments.
+    /// Files and stdin compressed with gzip or zstd are decompressed as they are read,
+    /// and binary datasets written by `Question_2_rust convert` are mapped instead of read
+    #[arg(short, long = "file", value_name = "PATH")]
+    files: Vec<String>,
+    /// Scan the files as free text or HTML, such as scraped pages, and use every phone number found in them
+    /// instead of reading one per line
+    #[arg(long, requires = "files")]
+    extract: bool,
+    /// Read the files as exported tables: `csv`, `tsv` or `jsonl`
+    #[arg(long, requires_all = ["files", "column"], conflicts_with = "extract")]
+    format: Option<TableFormat>,
+    /// The column of the tables with the phone numbers, by its name in the header or its position counted from 1.
+    /// For JSON lines, the field, which can be nested with dots such as `contact.phone`
+    #[arg(long, visible_alias = "field", requires = "format")]
+    column: Option<String>,
+    /// The first row of a CSV or TSV table is a row of data rather than a header
+    #[arg(long, requires = "format")]
+    no_header: bool,
+    /// Fail instead of skipping phone numbers that are not 10 digits, or 11 with a country code
+    #[arg(long)]
+    strict: bool,
+    /// How to print the median: `text`, `json`, `csv` or `ndjson`.
+    /// JSON and NDJSON include how many phone numbers were read and rejected,
+    /// and how many times each median appears
+    #[arg(long, default_value = "text")]
+    output: OutputFormat,
+}
 
 /// This function takes a list of phone numbers as command line arguments,
+/// or reads them from files or stdin one per line,
 /// cleans them up, and finds the median value(s).
 /// It uses quick select to find the median value(s).
 /// It has an average time complexity of O(n), but worst case is O(n^2).
+/// Run with `--help` for the options.
 /// # Examples
 /// ```
 /// cargo run "123-456-7890" "(323) 456-7890" "+1 223-456-7890" "1-322-345-7890" "322 555 0000"
 /// > 3223457890
+/// cargo run -- --strict "123-456-7890" "12345"
+/// > error: not a phone number: 12345
+/// cat numbers.txt | cargo run -- --file -
+/// > 3223457890
+/// cargo run -- --file numbers.txt.gz --file numbers.txt.zst
+/// > 3223457890
+/// curl https://example.com/contact | cargo run -- --extract --file -
+/// > 7327325555
+/// cargo run -- --file numbers.bin
+/// > 3223457890
+/// cargo run -- --format csv --column phone --file contacts.csv
+/// > 7327325555
+/// cargo run -- --output json "123-456-7890" "(323) 456-7890" "12345"
+/// > {"input":{"parsed":2,"rejected":1},"medians":[{"number":1234567890,"count":1},{"number":3234567890,"count":1}]}
 /// ```
-fn main() {
-    let args: Vec<String>  = env::args().collect();
-    assert!(args.len() > 1, "Please provide at least one phone number as a command line argument");
-    let phone_numbers: Vec<i64> = args[1..]
-        .iter()
-        .map(|number| {
-            let cleaned: String = number
-                .chars()
-                .filter(|&c| c.is_digit(10)) // filter out non-digits
-                .collect();
-            cleaned
-        })
-        .filter(|cleaned| cleaned.len() >= 10 && cleaned.len() <= 11) // filter out numbers with less than 10 digits
-        .map(|cleaned| cleaned.chars().rev().take(10).collect::<String>().chars().rev().collect::<String>()) // take last 10 digits
-        .map(|cleaned| cleaned.parse::<i64>().unwrap()) // parse to i64
-        .filter(|number| number >= &1000000000) // filter out numbers with less than 10 digits
-        .collect();
-    // use quick select to find the median phone number
-    // quick select has an average time complexity of O(n), but worst case is O(n^2)
-    let res = find_median_values(&phone_numbers);
-    match res {
-        Some((median, Some(median2))) => {
-            println!("{:0<10},{:0<10}", median, median2);
-        },
-        Some((median, None)) => {
-            println!("{:0<10}", median);
-        },
-        _ => {
-            println!("No median");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let args = match (cli.command, cli.median) {
+        (Some(Command::Median(args)), _) | (None, Some(args)) => args,
+        (None, None) => {
+            eprintln!("error: at least one phone number is required\n\nFor more information, try '--help'.");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_NO_RESULT),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::from(error.exit_code())
         }
     }
 }
 
-/// This function uses quick select to find the kth smallest element in an array,
-/// indexed from 0.
-/// In this case we are using it to find the median value.
-/// It is adapted from the quick sort algorithm, but instead of recursing on both
-/// sides of the array, it recurses on only one side.
-/// It has an average time complexity of O(n), but worst case is O(n^2).
-/// # Arguments
-/// * `arr` - A mutable slice of type T
-/// * `k` - The index of the element to find
-/// # Returns
-/// * `Option<T>` - The kth smallest element in the array
-/// # Examples 
-/// ```
-/// let mut arr = [1, 2, 3, 4, 5];
-/// let k = 2;
-/// let res = quick_select(&mut arr, k);
-/// assert_eq!(res, Some(3));
-/// ```
-fn quick_select<T: PartialOrd + Clone>(arr: &mut [T], k: usize) -> Option<T> {
-    /*
-     * This function uses quick select to find the kth smallest element in an array.
-     * In this case we are using it to find the median value.
-     * It is adapted from the quick sort algorithm, but instead of recursing on both
-     * sides of the array, it recurses on only one side.
-     */
-    let len = arr.len();
-    if len == 0 || k >= len {
-        return None;
-    }
-    let pivot_index = len / 2;
-    let pivot_value = arr[pivot_index].clone();
+/// An error that stops the program, which decides its exit code
+/// * `Usage` - the arguments are invalid, or a phone number is invalid with `--strict`
+/// * `Io` - a file or stdin could not be read, or the median could not be written
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(String),
+}
 
-    let mut i = 0; // left side of the array
-    let mut j = 0; // left side of the array (accounts for duplicates)
-    let mut n = len - 1; // right side of the array
-    while j <= n {
-        if arr[j] < pivot_value {
-            arr.swap(i, j);
-            i += 1;
-            j += 1;
-        } else if arr[j] > pivot_value {
-            arr.swap(j, n);
-            n -= 1;
-        } else {
-            j += 1;
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Io(_) => EXIT_IO,
         }
     }
-    if i <= k && k <= n {
-        // Result is simply the k-th element
-        Some(arr[k].clone())
-    } else if k < i {
-        // Recurse on the left side of the array
-        quick_select(&mut arr[..i], k)
-    } else {
-        // Recurse on the right side of the array
-        quick_select(&mut arr[n + 1..], k - n - 1)
-    }
 }
 
-/// This function finds the median value(s) of an array.
-/// If the array has an even number of elements, it returns the two middle values.
-/// If the array has an odd number of elements, it returns the middle value.
-/// It uses quick select to find the median value(s).
-/// It has an average time complexity of O(n), but worst case is O(n^2).
-/// # Arguments
-/// * `arr` - A slice of type T
-/// # Returns
-/// * `Option<(T, Option<T>)>` - A tuple of the median value(s)
-/// # Examples
-/// ```
-/// let arr = [1, 2, 3, 4, 5];
-/// let res = find_median_values(&arr);
-/// assert_eq!(res, Some(3, None));
-/// ```
-fn find_median_values<T: PartialOrd + Clone>(arr: &[T]) -> Option<(T, Option<T>)> {
-    /*
-     * This function finds the median value(s) of an array.
-     * If the array has an even number of elements, it returns the two middle values.
-     * If the array has an odd number of elements, it returns the middle value.
-     * It uses quick select to find the median value(s).
-     * It has an average time complexity of O(n), but worst case is O(n^2).
-     * It is adapted from the quick sort algorithm, but instead of recursing on both
-     * sides of the array, it recurses on only one side.
-     * It returns a tuple of the median value(s).
-     */
-    let mut arr_clone = arr.clone().to_vec();
-    let length = arr.len();
-    // handle the case where the array is empty
-    if length == 0 {
-        return Option::None;
-    }
-    if length % 2 == 0 {
-        // return two values if the array has an even number of elements
-        let median = quick_select(&mut arr_clone, length / 2 - 1);
-        let median2 = quick_select(&mut arr_clone, length / 2);
-        match (median, median2) {
-            (Some(median), Some(median2)) => {
-                Option::Some((median, Some(median2)))
-            },
-            _ => {
-                Option::None
-            }
-        }
-    } else {
-        // otherwise return one value
-        let median = quick_select(&mut arr_clone, length / 2);
-        match median {
-            Some(median) => {
-                Option::Some((median, None))
-            },
-            _ => {
-                Option::None
-            }
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Io(message) => write!(f, "{}", message),
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::Rng;
-    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
-    use std::io::Write;
-    use std::time::{Duration, Instant};
-    use rayon::prelude::*;
-    use rayon::iter::ParallelIterator;
-    use indicatif::{ParallelProgressIterator, ProgressStyle};
-
-    /// This function is used to test quick select by comparing it to the sort function
-    /// on a vector of random numbers.
-    /// It creates a vector of random numbers, then sorts the vector and finds the median value,
-    /// then asserts that the quick select algorithm also finds the median value.
-    #[test]
-    fn test_quick_select() {
-        /* 
-         * This test checks that the quick select algorithm works to find the median
-         * value for an array of numbers. It uses a random number generator to create
-         * a vector of random numbers, then sorts the vector and finds the median value,
-         * then asserts that the quick select algorithm also finds the median value.
-         */
-        // create a vector of random numbers with a max length, max value and seed.
-        let max_length: usize = 100; // max possible length of the vector
-        let max_value: usize = 100; // max possible value of the random numbers
-        let num_tries = 100; // number of times to run the test
-        for _ in 0..num_tries{
-            // intialize the random number generator
-            let seed = rand::thread_rng().gen_range(0..1<<32);
-            let mut rng = ChaChaRng::seed_from_u64(seed);
-            let num_elements = rng.gen_range(1..max_length);
-            let numbers: Vec<usize> = (0..num_elements)
-                .map(|_| rng.gen_range(0..max_value))
-                .collect();
-            // find the median of a clone of the vector
-            let mut numbers_clone = numbers.clone();
-            numbers_clone.sort();
-            let median = if num_elements % 2 == 0 {
-                // if the length of the array is even, return the two middle values
-                Option::Some((numbers_clone[num_elements / 2 - 1], Some(numbers_clone[num_elements / 2])))
-            } else {
-                // otherwise, return the middle value
-                Option::Some((numbers_clone[num_elements / 2], None))
-            };
-            let res = find_median_values(&numbers);
-            match(&median, &res) {
-                // match the median and the result of the quick select algorithm
-                (Some((median, Some(median2))), Some((res, Some(res2)))) => {
-                    assert_eq!(median, res);
-                    assert_eq!(median2, res2);
-                },
-                (Some((median, None)), Some((res, None))) => {
-                    assert_eq!(median, res);
-                },
-                _ => {
-                    panic!("median and res are not the same");
-                }
-            }
+/// Reads the phone numbers given as arguments, in files and on stdin, and prints their median
+/// # Arguments
+/// * `args` - The phone numbers and files to read, and how to print the median
+/// # Returns
+/// * `Result<bool, CliError>` - Whether there is a median
+fn run(args: MedianArgs) -> Result<bool, CliError> {
+    let mut phone_numbers: Vec<i64> = Vec::new();
+    let mut rejected: Vec<String> = Vec::new();
+    for number in args.phone_numbers.iter() {
+        match parse_record(number) {
+            Some(cleaned) => phone_numbers.push(cleaned),
+            None => rejected.push(number.clone()),
         }
     }
-    
-    /// This function tests the worst case scenario for the quick select algorithm.
-    /// It creates a vector of numbers from 1 to 10, then calls quick select on the
-    /// vector to find the 0th, 1st, 2nd, 3rd, and 4th element.
-    /// It asserts that the quick select algorithm returns the correct values.
-    #[test]
-    fn quick_select_worst_case() {
-        /*
-         *  This test quickly checks some manual inputs to make sure the quick select
-         *  algorithm works in the worst case scenario.
-         */
-        let mut arr = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-        let res = quick_select(&mut arr, 0);
-        assert_eq!(res, Some(1));
-        let res = quick_select(&mut arr, 1);
-        assert_eq!(res, Some(2));
-        let res = quick_select(&mut arr, 2);
-        assert_eq!(res, Some(3));
-        let res = quick_select(&mut arr, 3);
-        assert_eq!(res, Some(4));
-
-    }
-
-    /// This function generates a vector of random phone numbers.
-    /// It takes the number of phone numbers to generate, the length of each phone number
-    /// # Arguments
-    /// * `num_numbers` - The number of phone numbers to generate
-    /// * `phone_num_length` - The length of each phone number
-    /// * `seed` - The seed for the random number generator
-    /// # Returns
-    /// * `phone_numbers` - A vector of phone numbers as strings
-    /// # Example
-    /// ```
-    /// let phone_numbers = generate_phone_numbers(10, 10, 42);
-    /// ```
-    fn generate_phone_numbers(num_numbers: usize, phone_num_length: usize, seed: u64) -> Vec<String> {
-        let mut rng = ChaChaRng::seed_from_u64(seed);
-        let phone_numbers: Vec<String> = (0..num_numbers)
-            .map(|_| {
-                let phone_number: String = (0..phone_num_length)
-                    .map(|_| rng.gen_range(0..10).to_string())
-                    .collect();
-                phone_number
-            })
-            .collect();
-        phone_numbers
-    }
-    enum ComparisonType {
-        PhoneNumbers,
-        Integers
-    }
-    /// This test is used to test quick select by comparing it to the sort function
-    /// on a vector of random phone numbers.
-    /// It creates a vector of random phone numbers, then sorts the vector and finds the median value,
-    /// then asserts that the quick select algorithm also finds the median value.
-    #[test]
-    fn test_quick_select_phone_numbers() {
-        let max_length: usize = 100;
-        let seed = rand::thread_rng().gen_range(0..1<<32);
-        let num_tries = 100;
-        // create a vector of random phone numbers with a max length, max value and seed.
-        for _ in 0..num_tries {
-            // initialize the random number generator
-            let mut rng = ChaChaRng::seed_from_u64(seed);
-            let num_elements = rng.gen_range(0..max_length);
-            let phone_numbers: Vec<String> = generate_phone_numbers(num_elements, 10, seed);
-            // find the median of a clone of the vector
-            let mut phone_numbers_clone = phone_numbers.clone();
-            // clean the phone numbers
-            phone_numbers_clone = phone_numbers_clone
-                .iter()
-                .map(|number| number.chars().rev().take(10).collect::<String>().chars().rev().collect::<String>())
-                .collect();
-            phone_numbers_clone.sort();
-            let median = if num_elements % 2 == 0 {
-                Some((phone_numbers_clone[num_elements / 2 - 1].clone(), Some(phone_numbers_clone[num_elements / 2].clone())))
-            } else {
-                Some((phone_numbers_clone[num_elements / 2].clone(), None))
-            };
-            let res = find_median_values(&phone_numbers);
-            // Check that the median and the result of the quick select algorithm are the same
-            match(&median, &res) {
-                (Some((median, Some(median2))), Some((res, Some(res2)))) => {
-                    assert_eq!(median, res);
-                    assert_eq!(median2, res2);
-                },
-                (Some((median, None)), Some((res, None))) => {
-                    assert_eq!(median, res);
-                },
-                _ => {
-                    panic!("median and res are not the same");
-                }
-            }
+    // files are read one phone number per line, unless they are free text or tables
+    let layout = match (args.format, args.column) {
+        (Some(format), Some(column)) => Layout::Table(Table { format, column, header: !args.no_header }),
+        _ if args.extract => Layout::Text,
+        _ => Layout::default(),
+    };
+    let mut datasets: Vec<BinaryDataset> = Vec::new();
+    for path in args.files.iter() {
+        let source = if path == "-" { "stdin" } else { path };
+        // binary datasets are mapped rather than read, whatever the layout of the other files
+        if path != "-" && is_binary_file(path).unwrap_or(false) {
+            let dataset = BinaryDataset::open(path).map_err(|e| CliError::Io(format!("could not read {}: {}", source, e)))?;
+            datasets.push(dataset);
+            continue;
         }
+        // compressed files and stdin are decompressed as they are read
+        let reader = match path.as_str() {
+            "-" => decompress(io::stdin().lock(), None),
+            _ => compress::open(Path::new(path)),
+        };
+        reader
+            .and_then(|reader| read_phone_numbers(reader, source, &layout, &mut phone_numbers, &mut rejected))
+            .map_err(|e| read_error(source, e))?;
     }
-
-    /// This function is for testing the performance of the quick sort algorithm
-    /// to the quick select algorithm.
-    /// # Arguments
-    /// * `arr` - A vector of T that is to be sorted    
-    fn quicksort<T: PartialOrd>(arr: &mut [T]) {
-        if arr.len() < 1 {
-            return;
-        }
-        let pivot = partition(arr);
-        quicksort(&mut arr[..pivot]);
-        quicksort(&mut arr[pivot + 1..]);
+    if args.strict && !rejected.is_empty() {
+        return Err(CliError::Usage(format!("not a phone number: {}", rejected.join(", "))));
     }
-
-    /// This function is used by the quick sort algorithm to partition the vector.
-    /// # Arguments
-    /// * `arr` - A vector of T that is to be sorted
-    /// # Returns
-    /// * `usize` - The index of the pivot element
-    /// # Example
-    /// ```
-    /// let mut arr = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-    /// let pivot = partition(&mut arr);
-    /// ```
-    fn partition<T: PartialOrd>(arr: &mut [T]) -> usize {
-        let len = arr.len();
-        // select a random pivot index
-        let mut rng = rand::thread_rng();
-        let pivot_index = rng.gen_range(0..len);
-        arr.swap(pivot_index, len - 1);
-        let mut store_index = 0;
-        for i in 0..len - 1 {
-            if arr[i] < arr[len - 1] {
-                arr.swap(i, store_index);
-                store_index += 1;
-            }
+    // a binary dataset on its own already holds its median in its header, so it is read in O(1),
+    // while binary datasets given with other phone numbers are added to them
+    let dataset = match datasets.len() {
+        1 if phone_numbers.is_empty() => datasets.pop(),
+        _ => {
+            datasets.iter().for_each(|dataset| phone_numbers.extend(dataset.numbers()));
+            None
         }
-        arr.swap(store_index, len - 1);
-        store_index
-    }
-
-    // This function is used to compare the performance of quick select and quick sort
-    // on a vector of either phone numbers or integers of an array up to a certain length.
-    // It takes a comparison type, the maximum length of the array and a seed for the random number generator.
-    // It returns a vector of tuples containing the length of the array, the time taken to sort the array
-    // # Arguments
-    // * `comparison` - The type of comparison to be made
-    // * `max_length` - The maximum length of the array
-    // * `seed` - The seed for the random number generator
-    // # Returns
-    // * `Vec<(usize, Duration, Duration)>` - A vector of tuples containing the length of the array, the time taken to sort the array
-    // using quick sort and the time taken to find the median value using quick select.
-    // # Example
-    // ```
-    // let comparison = ComparisonType::PhoneNumbers;
-    // let max_length = 100;
-    // let seed = 42;
-    // let results = _compare_quickselect_and_quicksort(comparison, max_length, seed);
-    // ```
-    fn _compare_quickselect_and_quicksort(comparison: ComparisonType, max_length: usize, seed: u64) -> Vec<(usize, Duration, Duration)> {
-        // let max_length: usize = 100000;
-        // let seed: u64 = 42;
-        let style = ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}/{eta_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}")
-            .unwrap()
-            .progress_chars("##-");
-        let results = match comparison {
-            ComparisonType::PhoneNumbers => {
-                // Phone numbers case
-                let phone_numbers = generate_phone_numbers(max_length, 10, seed);
-                let res: Vec<(usize, Duration, Duration)> = (1..=max_length)
-                    .into_par_iter() // parallelize the loop
-                    .progress_count(max_length as u64)
-                    .with_style(style)
-                    .map(|num_elements| {
-                        let phone_numbers_slice = &phone_numbers[..(max_length - num_elements)]; // get a slice of the vector
-                        let timings = compare_quickselect_and_quicksort(phone_numbers_slice);
-                        ((max_length - num_elements + 1), timings.0, timings.1)
-                    }).collect(); // collect the results
-                res.into_iter().rev().collect()
-            },
-            ComparisonType::Integers => {
-                // Integers case
-                let mut rng = ChaChaRng::seed_from_u64(seed);
-                let arr: Vec<i32> = (0..max_length).map(|_| rng.gen_range(0..1000000)).collect();
-                let res: Vec<(usize, Duration, Duration)> = (1..=max_length)
-                    .into_par_iter() // parallelize the loop
-                    .progress_count(max_length as u64)
-                    .with_style(style)
-                    .map(|num_elements| {
-                        let arr_slice = &arr[..(max_length - num_elements)]; // get a slice of the vector
-                        let timings = compare_quickselect_and_quicksort(arr_slice);
-                        ((max_length - num_elements + 1), timings.0, timings.1)
-                    })
-                    .collect();
-                res.into_iter().rev().collect()
-            }
+    };
+    // otherwise, use quick select to find the median phone number
+    // quick select has an average time complexity of O(n), but worst case is O(n^2)
+    let res = match &dataset {
+        Some(dataset) => dataset.median(),
+        None => find_median_values(&phone_numbers),
+    };
+    if args.output != OutputFormat::Text {
+        let parsed = dataset.as_ref().map_or(phone_numbers.len(), |dataset| dataset.total());
+        let input = InputStats { parsed, rejected: rejected.len() };
+        let count = |median: i64| match &dataset {
+            Some(dataset) => dataset.count(median),
+            None => phone_numbers.iter().filter(|number| **number == median).count(),
         };
-        results
+        print_report(args.output, input, res, count)?;
+        return Ok(res.is_some());
+    }
+    match res {
+        Some((median, Some(median2))) => println!("{:0<10},{:0<10}", median, median2),
+        Some((median, None)) => println!("{:0<10}", median),
+        None => println!("No median"),
     }
+    Ok(res.is_some())
+}
 
-    // This function is for testing the performance of the quick sort algorithm
-    // to the quick select algorithm.
-    // # Arguments
-    // * `arr` - A vector of T that is to be sorted
-    // # Returns
-    // * `(Duration, Duration)` - The average time it takes to sort the vector using quicksort and quickselect
-    // # Example
-    // ```
-    // let mut arr = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-    // let timings = compare_quickselect_and_quicksort(&arr);
-    // ```
-    fn compare_quickselect_and_quicksort<T: PartialOrd + Clone>(arr: &[T]) -> (Duration, Duration) {
-        let mut arr_clone = arr.to_vec();
-        let mut sum_quicksort_time = Duration::new(0, 0);
-        let mut sum_quickselect_time = Duration::new(0, 0);
-        for _ in 0..5 { // run the test 5 times and take the average
-            let start = Instant::now();
-            quicksort(&mut arr_clone);
-            let end = Instant::now();
-            sum_quicksort_time += end - start;
-            let start = Instant::now();
-            find_median_values(&arr);
-            let end = Instant::now();
-            sum_quickselect_time += end - start;
-        }
-        let avg_quicksort_time = sum_quicksort_time / 5;
-        let avg_quickselect_time = sum_quickselect_time / 5;
-        (avg_quicksort_time, avg_quickselect_time)
+/// Turns an error reading a file or stdin into a usage error if it is a table without the column in its header,
+/// or an I/O error otherwise
+fn read_error(source: &str, error: io::Error) -> CliError {
+    match error.kind() {
+        io::ErrorKind::InvalidInput => CliError::Usage(format!("could not read {}: {}", source, error)),
+        _ => CliError::Io(format!("could not read {}: {}", source, error)),
     }
+}
 
+/// Writes the median value(s) to stdout in a structured format, with how many times each was given
+/// # Arguments
+/// * `output` - The format to write
+/// * `input` - How many phone numbers were read and rejected
+/// * `res` - The median value(s), if there are any
+/// * `count` - Counts the times a phone number was given
+/// # Errors
+/// This function will return an I/O error if stdout cannot be written
+fn print_report(output: OutputFormat, input: InputStats, res: Option<(i64, Option<i64>)>, count: impl Fn(i64) -> usize) -> Result<(), CliError> {
+    let medians = match res {
+        Some((median, Some(median2))) => vec![median, median2],
+        Some((median, None)) => vec![median],
+        None => Vec::new(),
+    };
+    let results = medians.iter().map(|median| Median { number: *median, count: count(*median) }).collect();
+    let report = Report { input, query: serde_json::Value::Null, name: "medians", kind: "median", results };
+    write_report(io::stdout().lock(), output, &report).map_err(|e| CliError::Io(format!("could not write the median: {}", e)))
+}
 
-    /// This test is used to compare the performance of the quick select algorithm
-    /// to the quick sort algorithm.
-    /// It creates a vector of random phone numbers, then sorts the vector and finds the median value,
-    /// then asserts that the quick select algorithm also finds the median value.
-    /// It then compares the time taken to find the median value using the quick select algorithm
-    /// to the time taken to sort the vector and find the median value.
-    /// It then prints the results to a csv file.
-    #[test]
-    fn compare_quickselect_and_quicksort_phonenumbers() {
-        let max_length: usize = 100000;
-        let seed: u64 = 42;
-        // create a csv file to store the results
-        let mut file = match std::fs::File::create("results_phone.csv") {
-            Ok(file) => file,
-            Err(err) => panic!("couldn't create file: {}", err),
-        };
-        
-        let results = _compare_quickselect_and_quicksort(ComparisonType::PhoneNumbers, max_length, seed);
-        results.iter().for_each(|(length, quicksort_time, quickselect_time)| {
-            let ratio = quicksort_time.as_nanos() as f64 / quickselect_time.as_nanos() as f64;
-            let _ = writeln!(file, "{0}, {1}, {2}, {3}", length, quicksort_time.as_nanos(), quickselect_time.as_nanos(), ratio);
-        });
-    }
+/// A median phone number with the number of times it was given
+#[derive(Debug, Serialize)]
+struct Median {
+    number: i64,
+    count: usize,
+}
 
-    // This test is used to compare the performance of the quick select algorithm
-    // to the quick sort algorithm.
-    // It creates a vector of random integers, then sorts the vector and finds the median value,
-    // then asserts that the quick select algorithm also finds the median value.
-    // It then compares the time taken to find the median value using the quick select algorithm
-    // to the time taken to sort the vector and find the median value.
-    // It then prints the results to a csv file.
-    #[test]
-    fn compare_quickselect_and_quicksort_integers() {
-        let max_length: usize = 100000;
-        let seed: u64 = 42;
-        // create a csv file to store the results
-        let mut file = match std::fs::File::create("results_int.csv") {
-            Ok(file) => file,
-            Err(err) => panic!("couldn't create file: {}", err),
-        };
-        let results = _compare_quickselect_and_quicksort(ComparisonType::Integers, max_length, seed);
-        results.iter().for_each(|(length, quicksort_time, quickselect_time)| {
-            let ratio = quicksort_time.as_nanos() as f64 / quickselect_time.as_nanos() as f64;
-            let _ = writeln!(file, "{0}, {1}, {2}, {3}", length, quicksort_time.as_nanos(), quickselect_time.as_nanos(), ratio);
-        });
+/// This function reads the phone numbers in a file or stdin, one per line, or from free text or HTML,
+/// such as a scraped page, or a table such as a CSV export. Lines are cleaned up as they are read,
+/// so only the phone numbers are kept, and lines that are not valid UTF-8 are still cleaned up,
+/// with a warning on stderr.
+/// # Arguments
+/// * `reader` - Where to read the phone numbers from
+/// * `source` - The name of the file or stdin, used to describe the rejected lines
+/// * `layout` - How the phone numbers are laid out
+/// * `phone_numbers` - The list the phone numbers found are added to
+/// * `rejected` - The list the lines that are not valid phone numbers are added to, by where they were found
+/// # Errors
+/// This function will return an I/O error if the text cannot be read,
+/// with the `InvalidInput` kind if it is a table without the column in its header
+fn read_phone_numbers<R: BufRead>(reader: R, source: &str, layout: &Layout, phone_numbers: &mut Vec<i64>, rejected: &mut Vec<String>) -> io::Result<()> {
+    let records = match layout {
+        Layout::Records(delimiter) => stream_records(reader, *delimiter, |number| phone_numbers.push(number))?,
+        layout => {
+            let records = read_records(reader, layout)?;
+            phone_numbers.extend(&records.numbers);
+            records
+        }
+    };
+    if let Some(lines) = describe_rejected(&records.invalid_utf8) {
+        eprintln!("warning: read lines in {} that are not valid UTF-8, on {}", source, lines);
     }
-}
\ No newline at end of file
+    rejected.extend(records.rejected.iter().map(|line| format!("{} line {}", source, line)));
+    Ok(())
+}
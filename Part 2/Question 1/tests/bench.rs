@@ -0,0 +1,78 @@
+//! Tests that run the benchmark binary on small sizes and check the results and metadata it writes.
+
+use std::process::{Command, Output};
+
+/// This function runs the benchmark binary with the given arguments
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bench")).args(args).output().unwrap()
+}
+
+/// This test checks that every algorithm is timed on every size the given number of times,
+/// and that the metadata records the seed and parameters
+#[test]
+fn test_bench_writes_results_and_metadata() {
+    let output = std::env::temp_dir().join(format!("bench_{}.csv", std::process::id()));
+    let sidecar = output.with_extension("meta.json");
+    let result = run(&[
+        "--algorithms", "quick_select,select_nth_unstable,kth_nearest",
+        "--min-size", "5", "--max-size", "25", "--step", "10", "--repetitions", "2",
+        "--distribution", "clustered", "--seed", "7", "--threads", "2",
+        "--output", output.to_str().unwrap(),
+    ]);
+    assert_eq!(result.status.code(), Some(0), "{}", String::from_utf8_lossy(&result.stderr));
+    let results = std::fs::read_to_string(&output).unwrap();
+    let mut lines = results.lines();
+    assert_eq!(lines.next(), Some("algorithm,distribution,n,repetition,nanoseconds"));
+    let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+    // 3 sizes, 3 algorithms and 2 repetitions, ordered by size, then algorithm, then repetition
+    assert_eq!(rows.len(), 18);
+    assert_eq!(rows[0][..4], ["quick_select", "clustered", "5", "0"]);
+    assert_eq!(rows[5][..4], ["kth_nearest", "clustered", "5", "1"]);
+    assert_eq!(rows[17][..4], ["kth_nearest", "clustered", "25", "1"]);
+    assert!(rows.iter().all(|row| row[4].parse::<u128>().is_ok()));
+    let metadata: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&sidecar).unwrap()).unwrap();
+    assert_eq!(metadata["seed"], 7);
+    assert_eq!(metadata["measurements"], 18);
+    assert_eq!(metadata["parameters"]["algorithms"], serde_json::json!(["quick_select", "select_nth_unstable", "kth_nearest"]));
+    assert_eq!(metadata["parameters"]["distribution"], "clustered");
+    assert_eq!(metadata["parameters"]["threads"], 2);
+    for field in ["git_revision", "cpu", "logical_cpus", "os", "arch", "started", "elapsed_seconds"] {
+        assert!(metadata.get(field).is_some(), "{} is missing", field);
+    }
+    std::fs::remove_file(&output).unwrap();
+    std::fs::remove_file(&sidecar).unwrap();
+}
+
+/// This test checks that invalid combinations of options are usage errors
+#[test]
+fn test_bench_usage_errors() {
+    for args in [
+        vec!["--min-size", "10", "--max-size", "5"],
+        vec!["--strings", "--algorithms", "kth_nearest"],
+        vec!["--algorithms", "bubble_sort"],
+        vec!["--step", "0"],
+    ] {
+        let output = run(&args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error:"), "{:?}", args);
+    }
+}
+
+/// This test checks that the hostile inputs of `qn_1::adversarial` can be timed, as strings too
+#[test]
+fn test_bench_adversarial_distribution() {
+    let output = std::env::temp_dir().join(format!("bench_adversarial_{}.csv", std::process::id()));
+    let result = run(&[
+        "--algorithms", "quick_select,quicksort",
+        "--min-size", "10", "--max-size", "30", "--step", "10", "--repetitions", "1",
+        "--distribution", "quickselect-killer", "--strings",
+        "--output", output.to_str().unwrap(),
+    ]);
+    assert_eq!(result.status.code(), Some(0), "{}", String::from_utf8_lossy(&result.stderr));
+    let results = std::fs::read_to_string(&output).unwrap();
+    let rows: Vec<Vec<&str>> = results.lines().skip(1).map(|line| line.split(',').collect()).collect();
+    assert_eq!(rows.len(), 6);
+    assert!(rows.iter().all(|row| row[1] == "quickselect-killer"));
+    std::fs::remove_file(&output).unwrap();
+    std::fs::remove_file(output.with_extension("meta.json")).unwrap();
+}
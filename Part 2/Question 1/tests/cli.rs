@@ -0,0 +1,237 @@
+//! Tests that run the compiled binary and check its output and exit codes.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use phone_io::{binary::write_binary, PhoneCounts};
+
+/// This function runs the binary with the given arguments
+fn run(args: &[&str]) -> Output {
+    run_with_stdin(args, "")
+}
+
+/// This function runs the binary with the given arguments and stdin
+fn run_with_stdin(args: &[&str], stdin: impl AsRef<[u8]>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_qn_1"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_ref()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// This function writes a file of phone numbers to the temporary directory
+fn temp_file(name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("qn_1_{}_{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// This test checks that the help lists the options and exit codes
+#[test]
+fn test_cli_help() {
+    let output = run(&["--help"]);
+    assert_eq!(output.status.code(), Some(0));
+    for expected in ["median", "--strict", "Exit codes:"] {
+        assert!(stdout(&output).contains(expected), "{}", stdout(&output));
+    }
+}
+
+/// This test checks the median of the example in the README, with and without the subcommand
+#[test]
+fn test_cli_median() {
+    let numbers = ["123-456-7890", "(323) 456-7890", "+1 223-456-7890", "1-322-345-7890", "322 555 0000"];
+    let output = run(&numbers);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3223457890\n");
+    assert_eq!(stderr(&output), "");
+    let subcommand = run(&[&["median"], &numbers[..]].concat());
+    assert_eq!(subcommand.stdout, output.stdout);
+    let output = run(&["1234567890", "2234567890"]);
+    assert_eq!(stdout(&output), "1234567890,2234567890\n");
+}
+
+/// This test checks that invalid phone numbers are skipped, unless the strict option is given
+#[test]
+fn test_cli_invalid_numbers() {
+    let output = run(&["123-456-7890", "12345"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1234567890\n");
+    let output = run(&["--strict", "123-456-7890", "12345", "phone"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "error: not a phone number: 12345, phone\n");
+    assert_eq!(stdout(&output), "");
+}
+
+/// This test checks that phone numbers are read line by line from files and stdin,
+/// together with the ones given as arguments
+#[test]
+fn test_cli_files_and_stdin() {
+    let first = temp_file("first", "123-456-7890\n\n(323) 456-7890\r\n");
+    let second = temp_file("second", "+1 223-456-7890\n");
+    let first = first.to_str().unwrap();
+    let second = second.to_str().unwrap();
+    let output = run(&["--file", first, "-f", second]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "2234567890\n");
+    let output = run_with_stdin(&["-f", "-"], "123-456-7890\n(323) 456-7890\n+1 223-456-7890\n1-322-345-7890\n322 555 0000\n");
+    assert_eq!(stdout(&output), "3223457890\n");
+    let output = run_with_stdin(&["1-322-345-7890", "--file", first, "-f", "-"], "+1 223-456-7890\n");
+    assert_eq!(stdout(&output), "2234567890,3223457890\n");
+    let output = run_with_stdin(&["median", "-f", "-", "--strict"], "123-456-7890\n12345\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), "error: not a phone number: stdin line 2\n");
+    std::fs::remove_file(first).unwrap();
+    std::fs::remove_file(second).unwrap();
+}
+
+/// This test checks that gzip and zstd files and stdin are decompressed as they are read,
+/// whether or not they have the extension of their format
+#[test]
+fn test_cli_compressed() {
+    let contents = "123-456-7890\n(323) 456-7890\n+1 223-456-7890\n";
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(contents.as_bytes()).unwrap();
+    let gzip = gzip.finish().unwrap();
+    let zstd = zstd::encode_all(contents.as_bytes(), 0).unwrap();
+    let gzip_file = temp_file("compressed.gz", &gzip);
+    let zstd_file = temp_file("compressed", &zstd);
+    let output = run(&["--file", gzip_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "2234567890\n");
+    let output = run(&["-f", zstd_file.to_str().unwrap(), "322 555 0000"]);
+    assert_eq!(stdout(&output), "2234567890,3225550000\n");
+    let output = run_with_stdin(&["-f", "-"], &gzip);
+    assert_eq!(stdout(&output), "2234567890\n");
+    let output = run_with_stdin(&["--format", "csv", "--column", "1", "--no-header", "-f", "-"], &zstd);
+    assert_eq!(stdout(&output), "2234567890\n");
+    let broken = temp_file("broken.gz", contents);
+    let output = run(&["-f", broken.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("error: could not read"), "{}", stderr(&output));
+    for path in [gzip_file, zstd_file, broken] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+/// This test checks that the median of a binary dataset is read from its header,
+/// and that binary datasets can be given along with other phone numbers
+#[test]
+fn test_cli_binary() {
+    let mut bytes = Vec::new();
+    let counts = PhoneCounts::from_numbers(&[1234567890, 3234567890, 2234567890, 2234567890]);
+    write_binary(&mut bytes, &counts).unwrap();
+    let binary = temp_file("numbers.bin", &bytes);
+    let output = run(&["--file", binary.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "2234567890,2234567890\n");
+    let output = run(&["--output", "json", "--file", binary.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "{\"input\":{\"parsed\":4,\"rejected\":0},\"medians\":[{\"number\":2234567890,\"count\":2},{\"number\":2234567890,\"count\":2}]}\n");
+    let output = run(&["-f", binary.to_str().unwrap(), "-f", binary.to_str().unwrap(), "322 555 0000"]);
+    assert_eq!(stdout(&output), "2234567890\n");
+    let truncated = temp_file("truncated.bin", &bytes[..bytes.len() - 8]);
+    let output = run(&["-f", truncated.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("error: could not read"), "{}", stderr(&output));
+    for path in [binary, truncated] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+/// This test checks that phone numbers are extracted from free text and HTML
+#[test]
+fn test_cli_extract() {
+    let page = temp_file("page", "<p>Call (732) 732-5555 or +1 732.732.5554</p>\nOrder #7327325553 on 2023-10-19\n");
+    let page = page.to_str().unwrap();
+    let output = run(&["--extract", "--file", page]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325554,7327325555\n");
+    let output = run_with_stdin(&["median", "--extract", "-f", "-", "-f", page], "Fax: 732 732 5556");
+    assert_eq!(stdout(&output), "7327325555\n");
+    let output = run(&["--extract", "7327325555"]);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_file(page).unwrap();
+}
+
+/// This test checks that phone numbers are read from a column of CSV, TSV and JSON lines tables
+#[test]
+fn test_cli_table() {
+    let csv = temp_file("table_csv", "name,phone\n\"Smith, Jo\",(732) 732-5555\nAl,732-732-5554\nKim,123\n");
+    let csv = csv.to_str().unwrap();
+    let output = run(&["--format", "csv", "--column", "phone", "-f", csv]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325554,7327325555\n");
+    let output = run(&["--format", "csv", "--column", "phone", "-f", csv, "--strict"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stderr(&output), format!("error: not a phone number: {} line 4\n", csv));
+    let output = run(&["--format", "csv", "--column", "mobile", "-f", csv]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("there is no column mobile"), "{}", stderr(&output));
+    let output = run_with_stdin(&["--format", "tsv", "--column", "1", "--no-header", "-f", "-"], "7327325553\tJo\n");
+    assert_eq!(stdout(&output), "7327325553\n");
+    let output = run_with_stdin(&["--format", "jsonl", "--field", "contact.phone", "-f", "-"], "{\"contact\":{\"phone\":7327325552}}\n");
+    assert_eq!(stdout(&output), "7327325552\n");
+    let output = run(&["--column", "phone", "-f", csv]);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_file(csv).unwrap();
+}
+
+/// This test checks that the medians are printed as JSON, NDJSON and CSV with their counts,
+/// along with how many phone numbers were read and rejected
+#[test]
+fn test_cli_output() {
+    let output = run(&["--output", "json", "123-456-7890", "(323) 456-7890", "323.456.7890", "12345"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "{\"input\":{\"parsed\":3,\"rejected\":1},\"medians\":[{\"number\":3234567890,\"count\":2}]}\n");
+    let output = run(&["median", "1234567890", "2234567890", "--output", "ndjson"]);
+    assert_eq!(
+        stdout(&output),
+        "{\"type\":\"input\",\"parsed\":2,\"rejected\":0}\n{\"type\":\"median\",\"number\":1234567890,\"count\":1}\n\
+         {\"type\":\"median\",\"number\":2234567890,\"count\":1}\n"
+    );
+    let output = run(&["--output", "csv", "1234567890", "2234567890"]);
+    assert_eq!(stdout(&output), "number,count\n1234567890,1\n2234567890,1\n");
+    let output = run(&["--output", "json", "12345"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "{\"input\":{\"parsed\":0,\"rejected\":1},\"medians\":[]}\n");
+    let output = run(&["--output", "yaml", "1234567890"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// This test checks that a file that cannot be read is an I/O error
+#[test]
+fn test_cli_missing_file() {
+    let output = run(&["-f", "does_not_exist"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("error: could not read does_not_exist"), "{}", stderr(&output));
+}
+
+/// This test checks that there is no median when no valid phone numbers are given
+#[test]
+fn test_cli_no_median() {
+    let output = run(&["12345", "0123456789"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "No median\n");
+}
+
+/// This test checks that missing arguments and unknown options are usage errors
+#[test]
+fn test_cli_usage_errors() {
+    for (args, expected) in [(&[][..], "required arguments were not provided"), (&["--sort", "1234567890"][..], "unexpected argument '--sort'")] {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).contains(expected), "{:?}: {}", args, stderr(&output));
+        assert!(!stderr(&output).contains("panicked"), "{:?}: {}", args, stderr(&output));
+    }
+}
@@ -0,0 +1,79 @@
+//! Benchmarks the k nearest search of the binary, which counts the phone numbers and orders their
+//! distances in a BTreeMap, against sorting every phone number with quicksort first,
+//! across sizes and distributions.
+//! Run with `cargo bench`, and open `target/criterion/report/index.html` for the plots.
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use question_2_rust::{baseline::naive_sorting_find_kth_nearest, nearest::kth_nearest, PhoneCounts};
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+/// The number of phone numbers to search
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+/// The number of unique nearest phone numbers to find
+const K: i64 = 10;
+/// The seed of every generated list, so that each run measures the same inputs
+const SEED: u64 = 42;
+
+/// How the phone numbers of a list are drawn
+/// * `Uniform` - every 10 digit phone number is equally likely
+/// * `Duplicates` - every phone number is one of 1% as many, so most of them are repeated
+/// * `Clustered` - every phone number has the same area code and exchange, like `phonescraped`
+#[derive(Debug, Clone, Copy)]
+enum Distribution {
+    Uniform,
+    Duplicates,
+    Clustered,
+}
+
+impl Distribution {
+    const ALL: [Distribution; 3] = [Distribution::Uniform, Distribution::Duplicates, Distribution::Clustered];
+
+    fn name(self) -> &'static str {
+        match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Duplicates => "duplicates",
+            Distribution::Clustered => "clustered",
+        }
+    }
+
+    /// Generates a list of phone numbers drawn from this distribution, and a target drawn the same way
+    fn generate(self, size: usize) -> (Vec<i64>, i64) {
+        let mut rng = ChaChaRng::seed_from_u64(SEED);
+        let draw = |rng: &mut ChaChaRng| match self {
+            Distribution::Uniform => rng.gen_range(1_000_000_000..10_000_000_000),
+            Distribution::Duplicates => 7_320_000_000 + rng.gen_range(0..(size as i64 / 100).max(1)) * 1_000,
+            Distribution::Clustered => rng.gen_range(7_327_320_000..7_327_330_000),
+        };
+        let numbers = (0..size).map(|_| draw(&mut rng)).collect();
+        (numbers, draw(&mut rng))
+    }
+}
+
+/// Measures both searches on lists of each size. Both start from the list of phone numbers,
+/// so counting them is measured as part of `kth_nearest`, the same way the binary runs it.
+fn bench_kth_nearest(c: &mut Criterion) {
+    for distribution in Distribution::ALL {
+        let mut group = c.benchmark_group(format!("kth_nearest/{}", distribution.name()));
+        for size in SIZES {
+            let (numbers, target) = distribution.generate(size);
+            group.throughput(Throughput::Elements(size as u64));
+            group.bench_with_input(BenchmarkId::new("kth_nearest", size), &numbers, |b, numbers| {
+                b.iter(|| kth_nearest(K, target, &PhoneCounts::from_numbers(numbers)))
+            });
+            group.bench_with_input(BenchmarkId::new("naive_sorting", size), &numbers, |b, numbers| {
+                b.iter_batched(|| numbers.clone(), |numbers| naive_sorting_find_kth_nearest(K as u64, target, numbers), BatchSize::LargeInput)
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20).measurement_time(Duration::from_secs(3));
+    targets = bench_kth_nearest
+}
+criterion_main!(benches);
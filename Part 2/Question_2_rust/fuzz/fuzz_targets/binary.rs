@@ -0,0 +1,43 @@
+//! Fuzzes the binary dataset format. Arbitrary bytes must be rejected or read without panicking,
+//! even when the header and data are corrupt. The same bytes are also read as phone numbers, which must
+//! be written and read back exactly, in the order they first appeared, answering the same k nearest query
+//! as `kth_nearest` with the same numbers in the same order, ties included.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use question_2_rust::binary::{write_binary, BinaryDataset};
+use question_2_rust::{expand_neighbours, nearest::kth_nearest, PhoneCounts};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(dataset) = BinaryDataset::from_bytes(data.to_vec()) {
+        assert_eq!(data.len(), 48 + 24 * dataset.len());
+        let _ = dataset.verify();
+        let _ = (dataset.total(), dataset.median(), dataset.to_counts());
+        let target = dataset.median().map_or(0, |(low, _)| low);
+        let _ = (dataset.count(target), dataset.nearest(target, 3), dataset.within(target, 1000));
+    }
+
+    let Some((k, rest)) = data.split_first() else {
+        return;
+    };
+    // 8 bytes for each phone number, which cannot be negative in the binary format
+    let numbers: Vec<i64> = rest
+        .chunks_exact(8)
+        .map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap()) & i64::MAX)
+        .collect();
+    let counts = PhoneCounts::from_numbers(&numbers);
+    let mut bytes = Vec::new();
+    write_binary(&mut bytes, &counts).unwrap();
+    let dataset = BinaryDataset::from_bytes(bytes).unwrap();
+    dataset.verify().unwrap();
+    assert_eq!(dataset.total(), numbers.len());
+    let mut expected: Vec<(i64, usize)> = counts.iter().collect();
+    expected.sort_unstable();
+    assert_eq!(dataset.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(dataset.to_counts(), counts);
+
+    let target = numbers.first().map_or(0, |number| number ^ 1);
+    let found = expand_neighbours(&dataset.nearest(target, *k as usize));
+    assert_eq!(found, kth_nearest(*k as i64, target, &counts));
+});
@@ -0,0 +1,46 @@
+//! Fuzzes `kth_nearest` and `within_radius` with arbitrary numbers, targets and k, checking that
+//! every number returned comes from the input with all of its duplicates, that at least k unique numbers
+//! are returned if there are that many, and that they are exactly the nearest ones with every tie at the last distance.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use question_2_rust::nearest::{kth_nearest, within_radius};
+use question_2_rust::PhoneCounts;
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    numbers: Vec<i64>,
+    target: i64,
+    k: i64,
+}
+
+fuzz_target!(|input: Input| {
+    let Input { numbers, target, k } = input;
+    let counts = PhoneCounts::from_numbers(&numbers);
+    let result = kth_nearest(k, target, &counts);
+    let found = PhoneCounts::from_numbers(&result);
+    for (number, count) in found.iter() {
+        assert_eq!(count, counts.count(number), "{} is not returned as many times as it appears", number);
+    }
+    if k <= 0 {
+        assert!(result.is_empty());
+        return;
+    }
+    assert!(found.len() >= (k as usize).min(counts.len()), "{} unique numbers for k = {}", found.len(), k);
+    assert!(result.windows(2).all(|pair| pair[0].abs_diff(target) <= pair[1].abs_diff(target)), "{:?}", result);
+
+    let Some(farthest) = result.last().map(|number| number.abs_diff(target)) else {
+        assert!(counts.is_empty());
+        return;
+    };
+    // only as many numbers are returned as needed to reach k, and none are skipped
+    let closer = counts.iter().filter(|(number, _)| number.abs_diff(target) < farthest).count();
+    assert!(closer < k as usize, "{} numbers are closer than the farthest returned", closer);
+    let mut within = within_radius(farthest, target, &counts);
+    let mut sorted = result.clone();
+    within.sort_unstable();
+    sorted.sort_unstable();
+    assert_eq!(sorted, within);
+});
@@ -0,0 +1,46 @@
+//! Fuzzes the file parser with arbitrary bytes, the first of which chooses the delimiter.
+//! It checks that every way of reading a file of records finds the same phone numbers and rejected lines:
+//! streaming its lines, parsing it with the invalid bytes replaced, and counting it in parallel.
+//! It also checks that every phone number extracted from it as free text points into the text.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use question_2_rust::extract::extract_phone_numbers;
+use question_2_rust::ingest::count_records;
+use question_2_rust::records::{parse_records, stream_records, Delimiter};
+use question_2_rust::PhoneCounts;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((first, contents)) = data.split_first() else {
+        return;
+    };
+    let delimiter = [Delimiter::Newline, Delimiter::Comma, Delimiter::Tab][*first as usize % 3];
+    let mut numbers = Vec::new();
+    let streamed = stream_records(contents, delimiter, |number| numbers.push(number)).unwrap();
+    assert!(numbers.iter().all(|number| (1_000_000_000..10_000_000_000).contains(number)), "{:?}", numbers);
+    let lines = contents.split(|byte| *byte == b'\n').count();
+    for line_numbers in [&streamed.rejected, &streamed.invalid_utf8] {
+        assert!(line_numbers.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", line_numbers);
+        assert!(line_numbers.iter().all(|line| (1..=lines).contains(line)), "{:?} of {} lines", line_numbers, lines);
+    }
+
+    let text = String::from_utf8_lossy(contents);
+    let parsed = parse_records(&text, delimiter);
+    assert_eq!(parsed.numbers, numbers);
+    assert_eq!(parsed.rejected, streamed.rejected);
+
+    let (counts, counted) = count_records(contents, delimiter).unwrap();
+    let expected = PhoneCounts::from_numbers(&numbers);
+    assert_eq!(counts.iter().collect::<Vec<_>>(), expected.iter().collect::<Vec<_>>());
+    assert_eq!(counted.rejected, streamed.rejected);
+    assert_eq!(counted.invalid_utf8, streamed.invalid_utf8);
+
+    let mut end = 0;
+    for found in extract_phone_numbers(&text) {
+        assert!(end <= found.start && found.start < found.end && found.end <= text.len(), "{:?}", found);
+        assert!(text.is_char_boundary(found.start) && text.is_char_boundary(found.end), "{:?}", found);
+        assert!((1_000_000_000..10_000_000_000).contains(&found.number), "{:?}", found);
+        end = found.end;
+    }
+});
@@ -0,0 +1,31 @@
+//! Fuzzes `phone_number_sanitizer` with arbitrary bytes, checking that it never panics, that
+//! any number it returns is the last 10 digits of its input, and that the file parser only reads
+//! a line as a phone number when the sanitizer reads it the same way.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use question_2_rust::{parse_phone_numbers, phone_number_sanitizer};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let digits: Vec<u8> = text.bytes().filter(u8::is_ascii_digit).collect();
+    match phone_number_sanitizer(&text) {
+        Ok(number) => {
+            assert!((1_000_000_000..10_000_000_000).contains(&number), "{}", number);
+            assert!(digits.ends_with(number.to_string().as_bytes()), "{} from {:?}", number, text);
+        }
+        // rejected only if there are fewer than 10 digits, or the last 10 start with 0
+        Err(_) => assert!(digits.len() < 10 || digits[digits.len() - 10] == b'0', "{:?}", text),
+    }
+    if !text.contains('\n') {
+        match parse_phone_numbers(&text)[..] {
+            [] => {}
+            [number] => {
+                assert!((10..=11).contains(&digits.len()), "{:?}", text);
+                assert_eq!(phone_number_sanitizer(&text), Ok(number), "{:?}", text);
+            }
+            ref numbers => panic!("one line was read as {:?}", numbers),
+        }
+    }
+});
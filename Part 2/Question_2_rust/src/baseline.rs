@@ -0,0 +1,123 @@
+//! The straightforward implementations that the optimised ones are tested and benchmarked against:
+//! a quicksort with a random pivot, and a k nearest search that sorts every number first.
+//! They are only built for tests and with the `bench` feature, since the binary never uses them.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+/// The seed of the pivots quicksort chooses, so that every run sorts the same list the same way
+const PIVOT_SEED: u64 = 42;
+
+/// This function is a solution that implements the quicksort algorithm
+/// to sort a vector of i64s then returns the kth nearest numbers
+/// to the target number.
+/// Unlike `kth_nearest`, it does not return every number tied at the k-th distance,
+/// so the two are only compared on numbers without such ties.
+/// # Arguments
+/// * `k` - the number of nearest numbers to return
+/// * `target` - the target number
+/// * `numbers` - the vector of numbers to search
+/// # Returns
+/// * `Vec<i64>` - the kth nearest numbers to the target number
+/// # Example
+/// ```rust
+/// use question_2_rust::baseline::naive_sorting_find_kth_nearest;
+/// let numbers = vec![10, 20, 40, 24, 20];
+/// let result = naive_sorting_find_kth_nearest(2, 21, numbers);
+/// assert_eq!(result, vec![20, 20, 24]);
+/// ```
+pub fn naive_sorting_find_kth_nearest(k: u64, target: i64, numbers: Vec<i64>) -> Vec<i64> {
+    let mut sorted_numbers = numbers.clone();
+    quicksort(&mut sorted_numbers);
+    let mut differences = HashMap::new();
+    // store the count of each number
+    for number in sorted_numbers {
+        let distance = number - target;
+        let difference_count = differences.entry(distance).or_insert(0);
+        *difference_count += 1;
+    }
+    let mut differences_sorted = differences.keys().collect::<Vec<&i64>>();
+    differences_sorted.sort_by_key(|a| a.abs()); // sort by absolute distance from target
+    let mut result: Vec<i64>  = Vec::new();
+    let mut last_abs_distance: i64 = *differences.get(differences_sorted[0]).unwrap() as i64;
+    let mut count = 0;
+    for difference in differences_sorted {
+        let num_times = differences.get(difference).unwrap();
+        let actual_number = target + difference;
+        for _ in 0..*num_times {
+            result.push(actual_number);
+        }
+        let abs_distance = difference.abs();
+        count += 1;
+        if abs_distance > last_abs_distance {
+            last_abs_distance = abs_distance;
+            if count >= k {
+                // we have found the kth or k+1th nearest number
+                break;
+            }
+        }
+    }
+    result
+}
+
+/// Sorts a slice in place with quicksort, choosing each pivot at random from a generator with a fixed seed,
+/// so that the time it takes to sort a list is the same on every run.
+/// This is the baseline that selecting the median with quick select is compared against.
+/// # Arguments
+/// * `arr` - A vector of T that is to be sorted
+/// # Example
+/// ```rust
+/// use question_2_rust::baseline::quicksort;
+/// let mut arr = vec![3, 1, 2, 5, 4];
+/// quicksort(&mut arr);
+/// assert_eq!(arr, vec![1, 2, 3, 4, 5]);
+/// ```
+pub fn quicksort<T: PartialOrd>(arr: &mut [T]) {
+    sort_with_pivots(arr, &mut ChaChaRng::seed_from_u64(PIVOT_SEED));
+}
+
+/// Sorts a slice in place with quicksort, drawing each pivot from the given generator
+/// # Arguments
+/// * `arr` - A slice of T that is to be sorted
+/// * `rng` - The generator the pivots are drawn from
+fn sort_with_pivots<T: PartialOrd>(mut arr: &mut [T], rng: &mut ChaChaRng) {
+    // recurse on the smaller side and loop on the larger one, so that the stack stays
+    // O(log n) deep even when every partition is as uneven as it can be, such as for all equal values
+    while !arr.is_empty() {
+        let pivot = partition(arr, rng);
+        let (left, right) = arr.split_at_mut(pivot);
+        let right = &mut right[1..];
+        if left.len() < right.len() {
+            sort_with_pivots(left, rng);
+            arr = right;
+        } else {
+            sort_with_pivots(right, rng);
+            arr = left;
+        }
+    }
+}
+
+/// This function is used by the quick sort algorithm to partition the vector.
+/// # Arguments
+/// * `arr` - A vector of T that is to be sorted
+/// * `rng` - The generator the pivot is drawn from
+/// # Returns
+/// * `usize` - The index of the pivot element
+fn partition<T: PartialOrd>(arr: &mut [T], rng: &mut ChaChaRng) -> usize {
+    let len = arr.len();
+    // select a random pivot index
+    let pivot_index = rng.gen_range(0..len);
+    arr.swap(pivot_index, len - 1);
+    let mut store_index = 0;
+    for i in 0..len - 1 {
+        if arr[i] < arr[len - 1] {
+            arr.swap(i, store_index);
+            store_index += 1;
+        }
+    }
+    arr.swap(store_index, len - 1);
+    store_index
+}
+
@@ -0,0 +1,99 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use question_2_rust::generate::{AreaCodes, Config, Generator, Line, Style};
+
+/// The exit code when the dataset cannot be written, the same as the main binary's
+const EXIT_IO: u8 = 3;
+
+/// Writes a synthetic dataset of phone numbers, one per line like `phonescraped`, to test and benchmark
+/// the binaries with. The same options and seed always write the same file.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// The number of lines to write
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    size: u64,
+    /// The fraction of valid lines that repeat a phone number from an earlier line, from 0 to 1
+    #[arg(long, default_value_t = 0.0, value_parser = parse_fraction)]
+    duplicate_rate: f64,
+    /// How the area codes are drawn: `uniform`, `zipf`, `zipf:<exponent>`, or a comma separated list
+    /// of 3 digit area codes or 6 digit area codes and exchanges, such as `732732` for `phonescraped`
+    #[arg(long, default_value = "uniform")]
+    area_codes: AreaCodes,
+    /// The fraction of lines that are malformed, from 0 to 1
+    #[arg(long, default_value_t = 0.0, value_parser = parse_fraction)]
+    malformed: f64,
+    /// The styles the phone numbers are written in, separated by commas: `plain`, `dashes`, `dots`,
+    /// `spaces`, `parentheses`, `international` and `country-code`
+    #[arg(long, value_delimiter = ',', default_value = "plain")]
+    styles: Vec<Style>,
+    /// The seed the dataset is generated from
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// End every line with a carriage return and a newline, as `phonescraped` does
+    #[arg(long)]
+    crlf: bool,
+    /// The file to write, rather than stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Parses a fraction from 0 to 1
+fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("{} is not a fraction from 0 to 1", s)),
+    }
+}
+
+/// This function writes the dataset, then reports how many lines it wrote if it wrote to a file.
+/// # Example
+/// ```bash
+/// cargo run --release --bin gen -- --size 10000000 --area-codes 732732 --duplicate-rate 0.6 --crlf --output big.txt
+/// > wrote 10000000 lines, 0 of them malformed, to big.txt
+/// ```
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config = Config {
+        duplicate_rate: cli.duplicate_rate,
+        area_codes: cli.area_codes.clone(),
+        malformed: cli.malformed,
+        styles: cli.styles.clone(),
+    };
+    let generator = Generator::new(config, cli.seed).take(cli.size as usize);
+    let ending = if cli.crlf { "\r\n" } else { "\n" };
+    let written = match &cli.output {
+        Some(path) => File::create(path).and_then(|file| write_lines(BufWriter::new(file), generator, ending)),
+        None => write_lines(BufWriter::new(io::stdout().lock()), generator, ending),
+    };
+    match (written, &cli.output) {
+        // a closed pipe, such as from `head`, is not an error
+        (Err(error), None) if error.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        (Err(error), _) => {
+            eprintln!("error: could not write the dataset: {}", error);
+            ExitCode::from(EXIT_IO)
+        }
+        (Ok(malformed), Some(path)) => {
+            eprintln!("wrote {} lines, {} of them malformed, to {}", cli.size, malformed, path.display());
+            ExitCode::SUCCESS
+        }
+        (Ok(_), None) => ExitCode::SUCCESS,
+    }
+}
+
+/// Writes every line with the given line ending
+/// # Returns
+/// * `usize` - the number of malformed lines
+fn write_lines<W: Write>(mut writer: W, lines: impl Iterator<Item = Line>, ending: &str) -> io::Result<usize> {
+    let mut malformed = 0;
+    for line in lines {
+        malformed += line.number.is_none() as usize;
+        write!(writer, "{}{}", line.text, ending)?;
+    }
+    writer.flush()?;
+    Ok(malformed)
+}
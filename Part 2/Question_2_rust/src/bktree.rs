@@ -0,0 +1,298 @@
+use std::collections::BinaryHeap;
+
+use crate::Neighbour;
+use crate::metric::Metric;
+
+/// A single phone number stored in the BK-tree along with the number of
+/// times it appeared and the indices of its children keyed by their distance
+/// to this node
+#[derive(Debug, Clone)]
+struct Node {
+    number: i64,
+    count: usize,
+    children: Vec<(u64, usize)>,
+}
+
+/// A Burkhard-Keller tree that indexes deduplicated phone numbers under a
+/// discrete metric such as the Hamming or Damerau-Levenshtein distance.
+/// Every child of a node is stored under its distance to that node, so the
+/// triangle inequality lets a query skip any subtree whose edge distance is
+/// too far from the distance between the target and the node.
+#[derive(Debug, Clone)]
+pub struct BkTree {
+    metric: Metric,
+    nodes: Vec<Node>,
+}
+
+impl BkTree {
+    /// Creates an empty BK-tree for the given metric
+    /// # Arguments
+    /// * `metric` - the metric used to compare phone numbers
+    pub fn new(metric: Metric) -> Self {
+        BkTree { metric, nodes: Vec::new() }
+    }
+
+    /// Creates a BK-tree from a list of phone numbers, counting duplicates
+    /// # Arguments
+    /// * `metric` - the metric used to compare phone numbers
+    /// * `numbers` - the phone numbers to index
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::{bktree::BkTree, metric::Metric};
+    /// let tree = BkTree::from_numbers(Metric::Hamming, &[7327325555, 7327325555, 1327325555]);
+    /// assert_eq!(tree.len(), 2);
+    /// ```
+    pub fn from_numbers(metric: Metric, numbers: &[i64]) -> Self {
+        let mut tree = BkTree::new(metric);
+        numbers.iter().for_each(|number| tree.insert(*number, 1));
+        tree
+    }
+
+    /// Builds a tree from phone numbers that have already been counted
+    /// # Arguments
+    /// * `metric` - the metric to index the phone numbers by
+    /// * `counts` - each unique phone number with the number of times it appears
+    pub fn from_counts(metric: Metric, counts: impl IntoIterator<Item = (i64, usize)>) -> Self {
+        let mut tree = BkTree::new(metric);
+        counts.into_iter().for_each(|(number, count)| tree.insert(number, count));
+        tree
+    }
+
+    /// The metric used by this tree
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// The number of unique phone numbers in the tree
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree has no phone numbers in it
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Inserts a phone number into the tree, adding to its count if it is already present
+    /// # Arguments
+    /// * `number` - the phone number to insert
+    /// * `count` - the number of times to insert it
+    pub fn insert(&mut self, number: i64, count: usize) {
+        if self.nodes.is_empty() {
+            self.nodes.push(Node { number, count, children: Vec::new() });
+            return;
+        }
+        let mut index = 0;
+        loop {
+            let distance = self.metric.distance(number, self.nodes[index].number);
+            if distance == 0 {
+                self.nodes[index].count += count;
+                return;
+            }
+            match self.nodes[index].children.iter().find(|(edge, _)| *edge == distance) {
+                Some((_, child)) => index = *child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node { number, count, children: Vec::new() });
+                    self.nodes[index].children.push((distance, child));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Finds every phone number within a given distance of the target
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `radius` - the maximum distance from the target, inclusive
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance then by number
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::{bktree::BkTree, metric::Metric};
+    /// let tree = BkTree::from_numbers(Metric::Hamming, &[7327325555, 1327325555, 1111111111]);
+    /// let numbers: Vec<i64> = tree.within(7327325555, 1).iter().map(|n| n.number).collect();
+    /// assert_eq!(numbers, vec![7327325555, 1327325555]);
+    /// ```
+    pub fn within(&self, target: i64, radius: u64) -> Vec<Neighbour> {
+        let mut result = Vec::new();
+        self.search(target, |found| {
+            if found.distance <= radius {
+                result.push(found);
+            }
+            radius
+        });
+        sort_neighbours(&mut result);
+        result
+    }
+
+    /// Finds the k nearest unique phone numbers to the target.
+    /// In the case of a tie at the k-th distance, every number at that distance is returned,
+    /// the same way `kth_nearest` does for the absolute distance.
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `k` - the number of unique phone numbers to find
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance then by number
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::{bktree::BkTree, metric::Metric};
+    /// let tree = BkTree::from_numbers(Metric::Hamming, &[7327325555, 1327325555, 7327325554, 1111111111]);
+    /// let numbers: Vec<i64> = tree.nearest(7327325555, 2).iter().map(|n| n.number).collect();
+    /// assert_eq!(numbers, vec![7327325555, 1327325555, 7327325554]);
+    /// ```
+    pub fn nearest(&self, target: i64, k: usize) -> Vec<Neighbour> {
+        if k == 0 {
+            return Vec::new();
+        }
+        // the k smallest distances found so far, largest on top
+        let mut best: BinaryHeap<u64> = BinaryHeap::new();
+        let mut candidates = Vec::new();
+        self.search(target, |found| {
+            let bound = kth_distance(&best, k);
+            if found.distance <= bound {
+                candidates.push(found);
+                best.push(found.distance);
+                if best.len() > k {
+                    best.pop();
+                }
+            }
+            kth_distance(&best, k)
+        });
+        // the bound only shrinks, so drop anything that was collected before it did
+        let bound = kth_distance(&best, k);
+        candidates.retain(|found| found.distance <= bound);
+        sort_neighbours(&mut candidates);
+        candidates
+    }
+
+    /// Walks the tree, visiting every node that could be within the radius returned by `visit`.
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `visit` - called with each node visited, returning the search radius to use from then on
+    fn search<F: FnMut(Neighbour) -> u64>(&self, target: i64, mut visit: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let distance = self.metric.distance(target, node.number);
+            let radius = visit(Neighbour { distance, number: node.number, count: node.count });
+            // by the triangle inequality, only children whose edge is within the
+            // radius of the distance to this node can hold numbers within the radius
+            for (edge, child) in node.children.iter() {
+                if edge.abs_diff(distance) <= radius {
+                    stack.push(*child);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the largest of the k smallest distances, or `u64::MAX` if fewer than k have been found
+fn kth_distance(best: &BinaryHeap<u64>, k: usize) -> u64 {
+    if best.len() < k {
+        u64::MAX
+    } else {
+        *best.peek().unwrap_or(&u64::MAX)
+    }
+}
+
+/// Sorts neighbours by their distance, then by their phone number
+fn sort_neighbours(neighbours: &mut [Neighbour]) {
+    neighbours.sort_by_key(|found| (found.distance, found.number));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+    /// This function finds every number within the radius by checking all of them
+    fn brute_force_within(metric: Metric, target: i64, radius: u64, numbers: &[i64]) -> Vec<(u64, i64)> {
+        let mut result: Vec<(u64, i64)> = numbers
+            .iter()
+            .map(|number| (metric.distance(target, *number), *number))
+            .filter(|(distance, _)| *distance <= radius)
+            .collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// This function finds the k nearest unique numbers by sorting all of them
+    fn brute_force_nearest(metric: Metric, target: i64, k: usize, numbers: &[i64]) -> Vec<(u64, i64)> {
+        let mut result: Vec<(u64, i64)> = numbers
+            .iter()
+            .map(|number| (metric.distance(target, *number), *number))
+            .collect();
+        result.sort();
+        result.dedup();
+        if let Some((bound, _)) = result.get(k - 1).copied() {
+            result.retain(|(distance, _)| *distance <= bound);
+        }
+        result
+    }
+
+    /// This function generates phone numbers that share most of their digits
+    /// so that the discrete metrics produce plenty of ties
+    fn generate_similar_numbers(num_numbers: usize, seed: u64) -> Vec<i64> {
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        (0..num_numbers)
+            .map(|_| {
+                let mut digits = [7, 3, 2, 7, 3, 2, 5, 5, 5, 5];
+                for _ in 0..rng.gen_range(0..4) {
+                    let index = rng.gen_range(0..digits.len());
+                    digits[index] = rng.gen_range(1..10);
+                }
+                digits.iter().fold(0, |number, digit| number * 10 + *digit as i64)
+            })
+            .collect()
+    }
+
+    /// This test checks that duplicate numbers are counted rather than stored twice
+    #[test]
+    fn test_bktree_counts_duplicates() {
+        let tree = BkTree::from_numbers(Metric::Hamming, &[7327325555, 7327325555, 7327325554]);
+        assert_eq!(tree.len(), 2);
+        let found = tree.within(7327325555, 0);
+        assert_eq!(found, vec![Neighbour { distance: 0, number: 7327325555, count: 2 }]);
+    }
+
+    /// This test checks the BK-tree radius and k-nearest queries against a brute force search
+    /// for every metric
+    #[test]
+    fn test_bktree_against_brute_force() {
+        for metric in [Metric::Absolute, Metric::Hamming, Metric::DamerauLevenshtein] {
+            for seed in 0..20 {
+                let numbers = generate_similar_numbers(500, seed);
+                let tree = BkTree::from_numbers(metric, &numbers);
+                let mut rng = ChaChaRng::seed_from_u64(seed);
+                let target = generate_similar_numbers(1, seed + 1000)[0];
+                let radius = match metric {
+                    Metric::Absolute => rng.gen_range(0..1000000000),
+                    _ => rng.gen_range(0..5),
+                };
+                let k = rng.gen_range(1..50);
+                let within: Vec<(u64, i64)> = tree.within(target, radius).iter().map(|n| (n.distance, n.number)).collect();
+                assert_eq!(within, brute_force_within(metric, target, radius, &numbers), "{} seed {}", metric, seed);
+                let nearest: Vec<(u64, i64)> = tree.nearest(target, k).iter().map(|n| (n.distance, n.number)).collect();
+                assert_eq!(nearest, brute_force_nearest(metric, target, k, &numbers), "{} seed {}", metric, seed);
+            }
+        }
+    }
+
+    /// This test checks the queries on an empty tree and with k larger than the dataset
+    #[test]
+    fn test_bktree_edge_cases() {
+        let tree = BkTree::new(Metric::Hamming);
+        assert!(tree.is_empty());
+        assert!(tree.within(7327325555, 10).is_empty());
+        assert!(tree.nearest(7327325555, 3).is_empty());
+        let tree = BkTree::from_numbers(Metric::Hamming, &[7327325555, 1111111111]);
+        assert!(tree.nearest(7327325555, 0).is_empty());
+        assert_eq!(tree.nearest(7327325555, 10).len(), 2);
+    }
+}
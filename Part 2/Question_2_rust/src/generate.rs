@@ -0,0 +1,638 @@
+//! Generates synthetic datasets of phone numbers like `phonescraped`, at any size, for testing and benchmarks.
+//! The `gen` binary writes them to a file, and the tests of both tasks draw their random phone numbers from here.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+/// How a phone number is written, shown for 7325551234
+/// * `Plain` - `7325551234`
+/// * `Dashes` - `732-555-1234`
+/// * `Dots` - `732.555.1234`
+/// * `Spaces` - `732 555 1234`
+/// * `Parentheses` - `(732) 555-1234`
+/// * `International` - `+1 732-555-1234`
+/// * `CountryCode` - `1-732-555-1234`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Plain,
+    Dashes,
+    Dots,
+    Spaces,
+    Parentheses,
+    International,
+    CountryCode,
+}
+
+impl Style {
+    pub const ALL: [Style; 7] = [
+        Style::Plain,
+        Style::Dashes,
+        Style::Dots,
+        Style::Spaces,
+        Style::Parentheses,
+        Style::International,
+        Style::CountryCode,
+    ];
+
+    /// Whether the style starts with the country code 1
+    pub fn has_country_code(self) -> bool {
+        self.format().prefix != Prefix::None
+    }
+
+    /// The format the style writes phone numbers in, out of every one in `Format::all`
+    pub fn format(self) -> Format {
+        let (prefix, parentheses, area_separator, separator) = match self {
+            Style::Plain => (Prefix::None, false, Separator::None, Separator::None),
+            Style::Dashes => (Prefix::None, false, Separator::Dash, Separator::Dash),
+            Style::Dots => (Prefix::None, false, Separator::Dot, Separator::Dot),
+            Style::Spaces => (Prefix::None, false, Separator::Space, Separator::Space),
+            Style::Parentheses => (Prefix::None, true, Separator::Space, Separator::Dash),
+            Style::International => (Prefix::PlusOne(Separator::Space), false, Separator::Dash, Separator::Dash),
+            Style::CountryCode => (Prefix::One(Separator::Dash), false, Separator::Dash, Separator::Dash),
+        };
+        Format { prefix, parentheses, area_separator, separator }
+    }
+}
+
+impl FromStr for Style {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Style::Plain),
+            "dashes" => Ok(Style::Dashes),
+            "dots" => Ok(Style::Dots),
+            "spaces" => Ok(Style::Spaces),
+            "parentheses" => Ok(Style::Parentheses),
+            "international" => Ok(Style::International),
+            "country-code" => Ok(Style::CountryCode),
+            _ => Err("Style must be one of plain, dashes, dots, spaces, parentheses, international or country-code"),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Style::Plain => write!(f, "plain"),
+            Style::Dashes => write!(f, "dashes"),
+            Style::Dots => write!(f, "dots"),
+            Style::Spaces => write!(f, "spaces"),
+            Style::Parentheses => write!(f, "parentheses"),
+            Style::International => write!(f, "international"),
+            Style::CountryCode => write!(f, "country-code"),
+        }
+    }
+}
+
+/// What is written before the area code
+/// * `None` - nothing
+/// * `One` - the country code `1`, followed by a separator
+/// * `PlusOne` - the country code `+1`, followed by a separator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prefix {
+    None,
+    One(Separator),
+    PlusOne(Separator),
+}
+
+impl Prefix {
+    pub const ALL: [Prefix; 9] = [
+        Prefix::None,
+        Prefix::One(Separator::None),
+        Prefix::One(Separator::Dash),
+        Prefix::One(Separator::Dot),
+        Prefix::One(Separator::Space),
+        Prefix::PlusOne(Separator::None),
+        Prefix::PlusOne(Separator::Dash),
+        Prefix::PlusOne(Separator::Dot),
+        Prefix::PlusOne(Separator::Space),
+    ];
+}
+
+/// What is written between the groups of digits
+/// * `None` - nothing, as in `7325551234`
+/// * `Dash` - `732-555-1234`
+/// * `Dot` - `732.555.1234`
+/// * `Space` - `732 555 1234`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    None,
+    Dash,
+    Dot,
+    Space,
+}
+
+impl Separator {
+    pub const ALL: [Separator; 4] = [Separator::None, Separator::Dash, Separator::Dot, Separator::Space];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Separator::None => "",
+            Separator::Dash => "-",
+            Separator::Dot => ".",
+            Separator::Space => " ",
+        }
+    }
+}
+
+/// A way of writing a phone number, as any combination of a prefix, parentheses around the area code
+/// and the separators between the groups of digits, so that every one can be tested.
+/// Each `Style` is one of them.
+/// * `prefix` - what is written before the area code
+/// * `parentheses` - whether the area code is written in parentheses
+/// * `area_separator` - what is written after the area code
+/// * `separator` - what is written between the exchange and the last 4 digits
+/// # Example
+/// ```rust
+/// use question_2_rust::generate::{Format, Prefix, Separator, Style};
+/// let format = Format { prefix: Prefix::PlusOne(Separator::Dot), parentheses: true, area_separator: Separator::Dot, separator: Separator::Dot };
+/// assert_eq!(format.write(7325551234), "+1.(732).555.1234");
+/// assert_eq!(Style::Parentheses.format().write(7325551234), "(732) 555-1234");
+/// assert_eq!(Format::all().len(), 288);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub prefix: Prefix,
+    pub parentheses: bool,
+    pub area_separator: Separator,
+    pub separator: Separator,
+}
+
+impl Format {
+    /// Every combination of a prefix, parentheses and the separators
+    pub fn all() -> Vec<Format> {
+        let mut formats = Vec::new();
+        for prefix in Prefix::ALL {
+            for parentheses in [false, true] {
+                for area_separator in Separator::ALL {
+                    for separator in Separator::ALL {
+                        formats.push(Format { prefix, parentheses, area_separator, separator });
+                    }
+                }
+            }
+        }
+        formats
+    }
+
+    /// Writes a phone number in this format. Numbers under 10 digits are padded with zeros.
+    pub fn write(self, number: i64) -> String {
+        let (area_code, exchange, line) = (number / 10_000_000, number / 10_000 % 1000, number % 10_000);
+        let prefix = match self.prefix {
+            Prefix::None => String::new(),
+            Prefix::One(separator) => format!("1{}", separator.as_str()),
+            Prefix::PlusOne(separator) => format!("+1{}", separator.as_str()),
+        };
+        let area_code = match self.parentheses {
+            true => format!("({:03})", area_code),
+            false => format!("{:03}", area_code),
+        };
+        format!("{}{}{}{:03}{}{:04}", prefix, area_code, self.area_separator.as_str(), exchange, self.separator.as_str(), line)
+    }
+}
+
+/// How the area codes of the phone numbers are drawn. Area codes and exchanges never start with 0 or 1.
+/// * `Uniform` - every area code from 200 to 999 is equally likely
+/// * `Zipf` - area code 200 is the most likely, and the one ranked r is 1 / r^s as likely, for an exponent s
+/// * `Prefixes` - one of the given 3 digit area codes, or 6 digit area codes and exchanges such as `732732`
+///   which every number of `phonescraped` starts with, each equally likely
+#[derive(Debug, Clone, PartialEq)]
+pub enum AreaCodes {
+    Uniform,
+    Zipf(f64),
+    Prefixes(Vec<i64>),
+}
+
+impl FromStr for AreaCodes {
+    type Err = &'static str;
+
+    /// Parses `uniform`, `zipf`, `zipf:<exponent>`, or a comma separated list of prefixes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERROR: &str = "Area codes must be uniform, zipf, zipf:<exponent>, or a comma separated list of \
+                             3 or 6 digit prefixes that do not start with 0 or 1";
+        match s {
+            "uniform" => Ok(AreaCodes::Uniform),
+            "zipf" => Ok(AreaCodes::Zipf(1.0)),
+            _ => match s.strip_prefix("zipf:") {
+                Some(exponent) => match exponent.parse::<f64>() {
+                    Ok(exponent) if exponent.is_finite() && exponent >= 0.0 => Ok(AreaCodes::Zipf(exponent)),
+                    _ => Err(ERROR),
+                },
+                None => s
+                    .split(',')
+                    .map(|prefix| {
+                        let valid = matches!(prefix.len(), 3 | 6)
+                            && prefix.bytes().all(|byte| byte.is_ascii_digit())
+                            && !prefix.starts_with(['0', '1'])
+                            && (prefix.len() == 3 || !prefix[3..].starts_with(['0', '1']));
+                        valid.then(|| prefix.parse().unwrap()).ok_or(ERROR)
+                    })
+                    .collect::<Result<Vec<i64>, _>>()
+                    .map(AreaCodes::Prefixes),
+            },
+        }
+    }
+}
+
+impl fmt::Display for AreaCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AreaCodes::Uniform => write!(f, "uniform"),
+            AreaCodes::Zipf(exponent) => write!(f, "zipf:{}", exponent),
+            AreaCodes::Prefixes(prefixes) => {
+                let prefixes: Vec<String> = prefixes.iter().map(|prefix| prefix.to_string()).collect();
+                write!(f, "{}", prefixes.join(","))
+            }
+        }
+    }
+}
+
+/// How a malformed line is broken. Every one is rejected by both the file parser and `phone_number_sanitizer`.
+/// * `TooShort` - one to three of the last digits are missing
+/// * `LeadingZero` - the area code starts with 0
+/// * `Letters` - one of the digits is a letter
+/// * `NoDigits` - the line is text with no digits, such as `n/a`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Malformed {
+    TooShort,
+    LeadingZero,
+    Letters,
+    NoDigits,
+}
+
+impl Malformed {
+    pub const ALL: [Malformed; 4] = [Malformed::TooShort, Malformed::LeadingZero, Malformed::Letters, Malformed::NoDigits];
+}
+
+/// The settings of a synthetic dataset
+/// * `duplicate_rate` - the fraction of valid lines that repeat a phone number from an earlier line,
+///   each new phone number so far being equally likely
+/// * `area_codes` - how the area codes are drawn
+/// * `malformed` - the fraction of lines that are malformed
+/// * `styles` - the styles the phone numbers are written in, each equally likely
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub duplicate_rate: f64,
+    pub area_codes: AreaCodes,
+    pub malformed: f64,
+    pub styles: Vec<Style>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config { duplicate_rate: 0.0, area_codes: AreaCodes::Uniform, malformed: 0.0, styles: vec![Style::Plain] }
+    }
+}
+
+/// A line of a synthetic dataset
+/// * `text` - the line, without its line ending
+/// * `number` - the phone number the line should be read as, or None if it is malformed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub text: String,
+    pub number: Option<i64>,
+}
+
+/// Generates the lines of a synthetic dataset from a seed, so that the same settings and seed always give the same lines.
+/// Every new phone number is drawn from its own stream of the random numbers, so a duplicate draws the index
+/// of an earlier one and draws it again from that stream, and a dataset of any size needs no more memory.
+/// # Example
+/// ```rust
+/// use question_2_rust::generate::{AreaCodes, Config, Generator};
+/// let config = Config { duplicate_rate: 0.5, area_codes: AreaCodes::Prefixes(vec![732732]), ..Config::default() };
+/// let lines: Vec<_> = Generator::new(config, 42).take(100).collect();
+/// assert!(lines.iter().all(|line| line.text.starts_with("732732") && line.number.is_some()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    rng: ChaChaRng,
+    config: Config,
+    /// the cumulative weight of each area code from 200, when they are drawn from a Zipf distribution
+    zipf_weights: Vec<f64>,
+    /// the seed of the streams the new phone numbers of valid lines are drawn from, one for each
+    numbers_seed: u64,
+    /// the number of new phone numbers drawn so far, which duplicates are drawn from
+    unique: u64,
+}
+
+impl Generator {
+    /// Creates a generator with the given settings and seed
+    pub fn new(config: Config, seed: u64) -> Self {
+        let zipf_weights = match config.area_codes {
+            AreaCodes::Zipf(exponent) => (1..=800)
+                .scan(0.0, |total, rank| {
+                    *total += 1.0 / (rank as f64).powf(exponent);
+                    Some(*total)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        let numbers_seed = rng.gen();
+        Generator { rng, config, zipf_weights, numbers_seed, unique: 0 }
+    }
+
+    /// Generates the next line, which is malformed with the configured probability
+    pub fn next_line(&mut self) -> Line {
+        if self.config.malformed > 0.0 && self.rng.gen_bool(self.config.malformed.min(1.0)) {
+            let number = draw_number(&mut self.rng, &self.config.area_codes, &self.zipf_weights);
+            let kind = Malformed::ALL[self.rng.gen_range(0..Malformed::ALL.len())];
+            let styles: Vec<Style> = self.config.styles.iter().copied().filter(|style| !style.has_country_code()).collect();
+            let style = match styles.is_empty() {
+                true => Style::Plain,
+                false => styles[self.rng.gen_range(0..styles.len())],
+            };
+            let text = format_phone_number(number, style);
+            return Line { text: malformed_phone_number(&mut self.rng, &text, kind), number: None };
+        }
+        let duplicate = self.unique > 0
+            && self.config.duplicate_rate > 0.0
+            && self.rng.gen_bool(self.config.duplicate_rate.min(1.0));
+        let index = match duplicate {
+            true => self.rng.gen_range(0..self.unique),
+            false => {
+                self.unique += 1;
+                self.unique - 1
+            }
+        };
+        let number = self.nth_unique(index);
+        let style = match self.config.styles.is_empty() {
+            true => Style::Plain,
+            false => self.config.styles[self.rng.gen_range(0..self.config.styles.len())],
+        };
+        Line { text: format_phone_number(number, style), number: Some(number) }
+    }
+
+    /// Draws the new phone number with the given index again from its own stream, so it is the same every time
+    fn nth_unique(&self, index: u64) -> i64 {
+        let mut rng = ChaChaRng::seed_from_u64(self.numbers_seed);
+        rng.set_stream(index);
+        draw_number(&mut rng, &self.config.area_codes, &self.zipf_weights)
+    }
+}
+
+/// Draws a new phone number, which can only be the same as an earlier one by chance
+/// # Arguments
+/// * `rng` - The random number generator
+/// * `area_codes` - How the area codes are drawn
+/// * `zipf_weights` - The cumulative weight of each area code from 200, when they are drawn from a Zipf distribution
+fn draw_number<R: Rng>(rng: &mut R, area_codes: &AreaCodes, zipf_weights: &[f64]) -> i64 {
+    match area_codes {
+        AreaCodes::Uniform => random_phone_number(rng),
+        AreaCodes::Zipf(_) => {
+            let total = zipf_weights[zipf_weights.len() - 1];
+            let weight = rng.gen_range(0.0..total);
+            let rank = zipf_weights.partition_point(|cumulative| *cumulative <= weight).min(799);
+            (200 + rank as i64) * 10_000_000 + rng.gen_range(200..1000) * 10_000 + rng.gen_range(0..10_000)
+        }
+        AreaCodes::Prefixes(prefixes) => {
+            let prefix = prefixes[rng.gen_range(0..prefixes.len())];
+            match prefix >= 1000 {
+                true => prefix * 10_000 + rng.gen_range(0..10_000),
+                false => prefix * 10_000_000 + rng.gen_range(200..1000) * 10_000 + rng.gen_range(0..10_000),
+            }
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        Some(self.next_line())
+    }
+}
+
+/// Draws a phone number with an area code and exchange from 200 to 999, and any last 4 digits
+pub fn random_phone_number<R: Rng>(rng: &mut R) -> i64 {
+    rng.gen_range(200..1000) * 10_000_000 + rng.gen_range(200..1000) * 10_000 + rng.gen_range(0..10_000)
+}
+
+/// Writes a phone number in the given style. Numbers under 10 digits are padded with zeros.
+/// # Example
+/// ```rust
+/// use question_2_rust::generate::{format_phone_number, Style};
+/// assert_eq!(format_phone_number(7325551234, Style::Parentheses), "(732) 555-1234");
+/// assert_eq!(format_phone_number(7325551234, Style::International), "+1 732-555-1234");
+/// ```
+pub fn format_phone_number(number: i64, style: Style) -> String {
+    style.format().write(number)
+}
+
+/// Breaks a written phone number in the given way. It should not have a country code,
+/// as a missing digit could then leave 10 that are read as a phone number.
+pub fn malformed_phone_number<R: Rng>(rng: &mut R, text: &str, kind: Malformed) -> String {
+    let digits: Vec<usize> = text.match_indices(|c: char| c.is_ascii_digit()).map(|(index, _)| index).collect();
+    let mut text = text.to_owned();
+    match kind {
+        Malformed::TooShort => {
+            for &digit in digits.iter().rev().take(rng.gen_range(1..=3)) {
+                text.remove(digit);
+            }
+            text.trim_end_matches(['-', '.', ' ']).to_owned()
+        }
+        Malformed::LeadingZero => {
+            text.replace_range(digits[0]..digits[0] + 1, "0");
+            text
+        }
+        Malformed::Letters => {
+            let index = digits[rng.gen_range(0..digits.len())];
+            let letter = rng.gen_range(b'a'..=b'z') as char;
+            text.replace_range(index..index + 1, &letter.to_string());
+            text
+        }
+        Malformed::NoDigits => ["n/a", "unknown", "none", "call the office", "-"][rng.gen_range(0..5)].to_owned(),
+    }
+}
+
+/// Generates a random valid phone number in a random format, as a tuple of the phone number
+/// and how it is written
+/// # Example
+/// ```rust
+/// use question_2_rust::{generate::valid_phone_number, phone_number_sanitizer};
+/// use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+/// let (number, text) = valid_phone_number(&mut ChaChaRng::seed_from_u64(0));
+/// assert_eq!(phone_number_sanitizer(&text), Ok(number));
+/// ```
+pub fn valid_phone_number<R: Rng>(rng: &mut R) -> (i64, String) {
+    let number = random_phone_number(rng);
+    let formats = Format::all();
+    (number, formats[rng.gen_range(0..formats.len())].write(number))
+}
+
+/// Generates a random malformed phone number, broken in a random way and written in a random format
+/// without a prefix
+/// # Example
+/// ```rust
+/// use question_2_rust::{generate::invalid_phone_number, phone_number_sanitizer};
+/// use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+/// let text = invalid_phone_number(&mut ChaChaRng::seed_from_u64(0));
+/// assert!(phone_number_sanitizer(&text).is_err());
+/// ```
+pub fn invalid_phone_number<R: Rng>(rng: &mut R) -> String {
+    let number = random_phone_number(rng);
+    let kind = Malformed::ALL[rng.gen_range(0..Malformed::ALL.len())];
+    let formats: Vec<Format> = Format::all().into_iter().filter(|format| format.prefix == Prefix::None).collect();
+    let text = formats[rng.gen_range(0..formats.len())].write(number);
+    malformed_phone_number(rng, &text, kind)
+}
+
+/// Generates random valid phone numbers all written in one format
+/// # Arguments
+/// * `format` - how the phone numbers are written
+/// * `count` - the number of phone numbers to generate
+/// * `seed` - the seed of the random phone numbers
+/// # Returns
+/// * `Vec<(i64, String)>` - each phone number with how it is written
+/// # Example
+/// ```rust
+/// use question_2_rust::generate::{valid_phone_numbers, Format, Prefix, Separator};
+/// let format = Format { prefix: Prefix::One(Separator::Dash), parentheses: false, area_separator: Separator::Dash, separator: Separator::Dash };
+/// let numbers = valid_phone_numbers(format, 100, 42);
+/// assert_eq!(numbers, valid_phone_numbers(format, 100, 42));
+/// assert!(numbers.iter().all(|(number, text)| text.starts_with("1-") && text.ends_with(&format!("{:04}", number % 10_000))));
+/// ```
+pub fn valid_phone_numbers(format: Format, count: usize, seed: u64) -> Vec<(i64, String)> {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| {
+            let number = random_phone_number(&mut rng);
+            (number, format.write(number))
+        })
+        .collect()
+}
+
+/// Generates random phone numbers all written in one format and broken in one way
+/// # Arguments
+/// * `kind` - how the phone numbers are broken
+/// * `format` - how the phone numbers are written, which should not have a prefix
+/// * `count` - the number of phone numbers to generate
+/// * `seed` - the seed of the random phone numbers
+/// # Example
+/// ```rust
+/// use question_2_rust::generate::{invalid_phone_numbers, Format, Malformed, Prefix, Separator};
+/// let format = Format { prefix: Prefix::None, parentheses: true, area_separator: Separator::Space, separator: Separator::Space };
+/// let numbers = invalid_phone_numbers(Malformed::LeadingZero, format, 100, 42);
+/// assert!(numbers.iter().all(|text| text.starts_with("(0")));
+/// ```
+pub fn invalid_phone_numbers(kind: Malformed, format: Format, count: usize, seed: u64) -> Vec<String> {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| {
+            let text = format.write(random_phone_number(&mut rng));
+            malformed_phone_number(&mut rng, &text, kind)
+        })
+        .collect()
+}
+
+/// Generates strings of random digits, which can start with 0
+/// # Arguments
+/// * `count` - the number of strings to generate
+/// * `length` - the number of digits in each string
+/// * `seed` - the seed of the random digits
+/// # Example
+/// ```rust
+/// use question_2_rust::generate::digit_strings;
+/// let strings = digit_strings(10, 10, 42);
+/// assert!(strings.iter().all(|string| string.len() == 10));
+/// ```
+pub fn digit_strings(count: usize, length: usize, seed: u64) -> Vec<String> {
+    let mut rng = ChaChaRng::seed_from_u64(seed);
+    (0..count)
+        .map(|_| (0..length).map(|_| rng.gen_range(0..10).to_string()).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::phone_number_sanitizer;
+    use crate::records::parse_record;
+
+    /// This test checks that every valid line is read as its phone number by both the file parser
+    /// and the sanitizer, and that every malformed line is rejected by both
+    #[test]
+    fn test_generated_lines_parse() {
+        let config = Config { duplicate_rate: 0.3, malformed: 0.3, styles: Style::ALL.to_vec(), ..Config::default() };
+        for line in Generator::new(config, 7).take(10_000) {
+            assert_eq!(parse_record(&line.text), line.number, "{}", line.text);
+            assert_eq!(phone_number_sanitizer(&line.text).ok(), line.number, "{}", line.text);
+        }
+        let mut rng = ChaChaRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let (number, text) = valid_phone_number(&mut rng);
+            assert_eq!(parse_record(&text), Some(number), "{}", text);
+            let text = invalid_phone_number(&mut rng);
+            assert_eq!(parse_record(&text), None, "{}", text);
+        }
+    }
+
+    /// This test checks that every format is written differently, that every style is one of them,
+    /// and that the file parser reads phone numbers in any of them and rejects them when they are broken
+    #[test]
+    fn test_every_format_parses() {
+        let formats = Format::all();
+        let examples = ["7325551234", "732-555-1234", "732.555.1234", "732 555 1234", "(732) 555-1234", "+1 732-555-1234", "1-732-555-1234"];
+        for (style, example) in Style::ALL.into_iter().zip(examples) {
+            assert!(formats.contains(&style.format()), "{} is not a format", style);
+            assert_eq!(format_phone_number(7325551234, style), example);
+        }
+        for (index, format) in formats.iter().enumerate() {
+            let text = format.write(7325551234);
+            assert!(formats[..index].iter().all(|other| other.write(7325551234) != text), "{} is written twice", text);
+            for (number, text) in valid_phone_numbers(*format, 100, index as u64) {
+                assert_eq!(parse_record(&text), Some(number), "{}", text);
+            }
+            if format.prefix != Prefix::None {
+                continue;
+            }
+            for kind in Malformed::ALL {
+                for text in invalid_phone_numbers(kind, *format, 100, index as u64) {
+                    assert_eq!(parse_record(&text), None, "{} broken by {:?}", text, kind);
+                }
+            }
+        }
+    }
+
+    /// This test checks that the settings are followed: the fractions of malformed and repeated lines,
+    /// the prefixes and the skew of a Zipf distribution, and that the same seed gives the same lines
+    #[test]
+    fn test_generator_settings() {
+        let config = Config { duplicate_rate: 0.5, malformed: 0.1, ..Config::default() };
+        let lines: Vec<Line> = Generator::new(config.clone(), 1).take(10_000).collect();
+        let malformed = lines.iter().filter(|line| line.number.is_none()).count();
+        assert!((800..1200).contains(&malformed), "{} malformed", malformed);
+        let mut numbers: Vec<i64> = lines.iter().filter_map(|line| line.number).collect();
+        let valid = numbers.len();
+        numbers.sort_unstable();
+        numbers.dedup();
+        assert!((4000..5000).contains(&(valid - numbers.len())), "{} repeated", valid - numbers.len());
+        assert_eq!(Generator::new(config, 1).take(10_000).collect::<Vec<_>>(), lines);
+
+        let prefixes = Config { area_codes: "732732,908".parse().unwrap(), ..Config::default() };
+        for line in Generator::new(prefixes, 2).take(1000) {
+            assert!(line.text.starts_with("732732") || line.text.starts_with("908"), "{}", line.text);
+        }
+        let zipf = Config { area_codes: AreaCodes::Zipf(1.0), ..Config::default() };
+        let area_code_200 = Generator::new(zipf, 3).take(10_000).filter(|line| line.text.starts_with("200")).count();
+        // area code 200 is 1 / H(800), about 13.6%, of a Zipf distribution with an exponent of 1
+        assert!((1200..1500).contains(&area_code_200), "{} from area code 200", area_code_200);
+    }
+
+    /// This test checks the settings that can be parsed from the command line
+    #[test]
+    fn test_generate_from_str() {
+        assert_eq!("zipf:1.5".parse(), Ok(AreaCodes::Zipf(1.5)));
+        assert_eq!("732,908201".parse(), Ok(AreaCodes::Prefixes(vec![732, 908201])));
+        for invalid in ["", "73", "032", "732132", "zipf:-1", "normal"] {
+            assert!(invalid.parse::<AreaCodes>().is_err(), "{}", invalid);
+        }
+        for style in Style::ALL {
+            assert_eq!(style.to_string().parse(), Ok(style));
+        }
+        assert!("hyphens".parse::<Style>().is_err());
+    }
+}
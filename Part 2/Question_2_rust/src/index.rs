@@ -0,0 +1,479 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+use crate::Neighbour;
+
+/// A change made to the index, as recorded in the change log
+/// * `Insert` - adds one occurrence of a phone number
+/// * `Remove` - removes every occurrence of a phone number
+/// * `Decrement` - removes one occurrence of a phone number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Insert(i64),
+    Remove(i64),
+    Decrement(i64),
+}
+
+impl Change {
+    /// Parses a line of the change log, such as `insert 7327325555`
+    /// # Arguments
+    /// * `line` - the line to parse
+    /// # Returns
+    /// * `Option<Change>` - the change, or `None` if the line is malformed
+    fn from_log_line(line: &str) -> Option<Change> {
+        let (operation, number) = line.split_once(' ')?;
+        let number = number.parse::<i64>().ok()?;
+        match operation {
+            "insert" => Some(Change::Insert(number)),
+            "remove" => Some(Change::Remove(number)),
+            "decrement" => Some(Change::Decrement(number)),
+            _ => None,
+        }
+    }
+
+    /// Formats the change as a line of the change log, without the trailing newline
+    fn to_log_line(self) -> String {
+        match self {
+            Change::Insert(number) => format!("insert {}", number),
+            Change::Remove(number) => format!("remove {}", number),
+            Change::Decrement(number) => format!("decrement {}", number),
+        }
+    }
+}
+
+/// A long-lived nearest neighbour index over an ordered map of phone number to count.
+/// Numbers can be inserted, removed and decremented while queries stay correct, and
+/// every change can be appended to a change log so that the index can be rebuilt
+/// from the original dataset after a crash.
+#[derive(Debug, Default)]
+pub struct NearestIndex {
+    counts: BTreeMap<i64, usize>,
+    log: Option<File>,
+}
+
+impl NearestIndex {
+    /// Creates an index from a list of phone numbers, counting duplicates
+    /// # Arguments
+    /// * `numbers` - the phone numbers to index
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::index::NearestIndex;
+    /// let index = NearestIndex::from_numbers(&[7327325555, 7327325555, 7327325554]);
+    /// assert_eq!(index.count(7327325555), 2);
+    /// assert_eq!(index.len(), 2);
+    /// ```
+    pub fn from_numbers(numbers: &[i64]) -> Self {
+        let mut counts = BTreeMap::new();
+        numbers.iter().for_each(|number| *counts.entry(*number).or_insert(0) += 1);
+        NearestIndex { counts, log: None }
+    }
+
+    /// Creates an index from a list of phone numbers, replays the changes in the
+    /// change log on top of it, and then appends every further change to the log.
+    /// The first line of the log records how many phone numbers the index was built from and
+    /// a checksum of them, so that a log is never replayed on top of a dataset that has changed since.
+    /// A torn line at the end of the log, left behind by a crash in the middle of
+    /// a write, is ignored and overwritten.
+    /// # Arguments
+    /// * `numbers` - the phone numbers the index was originally built from
+    /// * `log_path` - the path of the change log, which is created if it does not exist
+    /// # Errors
+    /// This function will return an error if the change log cannot be read or opened for writing,
+    /// with the `InvalidData` kind if it was written for other phone numbers or has a malformed line
+    pub fn open<P: AsRef<Path>>(numbers: &[i64], log_path: P) -> io::Result<Self> {
+        let mut index = NearestIndex::from_numbers(numbers);
+        let header = log_header(numbers);
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut valid_length = 0;
+        if log_path.as_ref().exists() {
+            let mut reader = BufReader::new(File::open(&log_path)?);
+            let mut line = String::new();
+            let mut line_number = 0;
+            while reader.read_line(&mut line)? > 0 {
+                line_number += 1;
+                // only lines that were completely written end with a newline
+                let Some(complete) = line.strip_suffix('\n') else {
+                    break;
+                };
+                let complete = complete.strip_suffix('\r').unwrap_or(complete);
+                if line_number == 1 {
+                    if complete != header {
+                        return Err(invalid(format!(
+                            "the change log was written for other phone numbers, as it starts with {:?} instead of {:?}",
+                            complete, header
+                        )));
+                    }
+                } else {
+                    match Change::from_log_line(complete) {
+                        Some(change) => index.apply(change),
+                        None => return Err(invalid(format!("line {} of the change log is not a change: {:?}", line_number, complete))),
+                    }
+                }
+                valid_length += line.len() as u64;
+                line.clear();
+            }
+        }
+        let mut log = OpenOptions::new().create(true).append(true).open(&log_path)?;
+        log.set_len(valid_length)?;
+        if valid_length == 0 {
+            writeln!(log, "{}", header)?;
+        }
+        log.sync_data()?;
+        index.log = Some(log);
+        Ok(index)
+    }
+
+    /// The number of unique phone numbers in the index
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether the index has no phone numbers in it
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The number of times a phone number appears in the index
+    pub fn count(&self, number: i64) -> usize {
+        self.counts.get(&number).copied().unwrap_or(0)
+    }
+
+    /// The counts of every phone number in ascending order
+    pub fn counts(&self) -> &BTreeMap<i64, usize> {
+        &self.counts
+    }
+
+    /// Adds one occurrence of a phone number
+    /// # Arguments
+    /// * `number` - the phone number to insert
+    /// # Returns
+    /// * `io::Result<usize>` - the new count of the phone number
+    /// # Errors
+    /// This function will return an error if the change cannot be written to the change log
+    pub fn insert(&mut self, number: i64) -> io::Result<usize> {
+        self.record(Change::Insert(number))?;
+        Ok(self.count(number))
+    }
+
+    /// Removes every occurrence of a phone number
+    /// # Arguments
+    /// * `number` - the phone number to remove
+    /// # Returns
+    /// * `io::Result<usize>` - the number of occurrences that were removed
+    /// # Errors
+    /// This function will return an error if the change cannot be written to the change log
+    pub fn remove(&mut self, number: i64) -> io::Result<usize> {
+        let count = self.count(number);
+        if count > 0 {
+            self.record(Change::Remove(number))?;
+        }
+        Ok(count)
+    }
+
+    /// Removes one occurrence of a phone number, removing the number once its count reaches 0
+    /// # Arguments
+    /// * `number` - the phone number to decrement
+    /// # Returns
+    /// * `io::Result<usize>` - the new count of the phone number
+    /// # Errors
+    /// This function will return an error if the change cannot be written to the change log
+    pub fn decrement(&mut self, number: i64) -> io::Result<usize> {
+        if self.count(number) > 0 {
+            self.record(Change::Decrement(number))?;
+        }
+        Ok(self.count(number))
+    }
+
+    /// Finds the k nearest unique phone numbers to the target by walking outwards
+    /// from the target in both directions. In the case of a tie at the k-th distance,
+    /// every number at that distance is returned, the same way `kth_nearest` does.
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `k` - the number of unique phone numbers to find
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance then by number
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::index::NearestIndex;
+    /// let mut index = NearestIndex::from_numbers(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    /// index.remove(4).unwrap();
+    /// let numbers: Vec<i64> = index.nearest(5, 2).iter().map(|n| n.number).collect();
+    /// assert_eq!(numbers, vec![5, 6]);
+    /// ```
+    pub fn nearest(&self, target: i64, k: usize) -> Vec<Neighbour> {
+        let mut below = self.counts.range(..target).rev().peekable();
+        let mut above = self.counts.range(target..).peekable();
+        let mut result: Vec<Neighbour> = Vec::new();
+        loop {
+            // take whichever side is closer, preferring the smaller number on a tie
+            let below_distance = below.peek().map(|(number, _)| number.abs_diff(target));
+            let above_distance = above.peek().map(|(number, _)| number.abs_diff(target));
+            let next = match (below_distance, above_distance) {
+                (Some(b), Some(a)) if b <= a => below.next(),
+                (Some(_), None) => below.next(),
+                (_, Some(_)) => above.next(),
+                (None, None) => None,
+            };
+            let Some((number, count)) = next else {
+                break;
+            };
+            let distance = number.abs_diff(target);
+            if result.len() >= k && result.last().map(|last| last.distance) != Some(distance) {
+                // we have enough numbers, and this one is not tied with the last one
+                break;
+            }
+            result.push(Neighbour { distance, number: *number, count: *count });
+        }
+        result
+    }
+
+    /// Finds every phone number within the radius of the target
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `radius` - the maximum absolute difference from the target, inclusive
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance then by number
+    pub fn within(&self, target: i64, radius: u64) -> Vec<Neighbour> {
+        // the ends can be outside the range of an i64 when the radius is larger than i64::MAX
+        let first = (target as i128 - radius as i128).max(i64::MIN as i128) as i64;
+        let last = (target as i128 + radius as i128).min(i64::MAX as i128) as i64;
+        let mut result: Vec<Neighbour> = self
+            .counts
+            .range(first..=last)
+            .map(|(number, count)| Neighbour { distance: number.abs_diff(target), number: *number, count: *count })
+            .collect();
+        result.sort_by_key(|found| (found.distance, found.number));
+        result
+    }
+
+    /// The total number of phone numbers in the index, including duplicates
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Finds the median value(s) of every phone number in the index, including duplicates,
+    /// with the same contract as `find_median_values`: one value if there is an odd number
+    /// of phone numbers, and the two middle values otherwise.
+    /// Since the counts are already in order, this only walks the running total of the counts.
+    /// # Returns
+    /// * `Option<(i64, Option<i64>)>` - The median value(s), or `None` if the index is empty
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::index::NearestIndex;
+    /// let index = NearestIndex::from_numbers(&[5, 1, 3, 3]);
+    /// assert_eq!(index.median(), Some((3, Some(3))));
+    /// let index = NearestIndex::from_numbers(&[5, 1, 3]);
+    /// assert_eq!(index.median(), Some((3, None)));
+    /// ```
+    pub fn median(&self) -> Option<(i64, Option<i64>)> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+        // the 0-indexed positions of the median value(s) in sorted order
+        let (first, second) = if total % 2 == 0 { (total / 2 - 1, Some(total / 2)) } else { (total / 2, None) };
+        let nth = |position: usize| {
+            let mut seen = 0;
+            self.counts.iter().find_map(|(number, count)| {
+                seen += count;
+                (position < seen).then_some(*number)
+            })
+        };
+        Some((nth(first)?, second.and_then(nth)))
+    }
+
+    /// Writes a change to the change log, if there is one, and then applies it
+    /// # Arguments
+    /// * `change` - the change to make
+    fn record(&mut self, change: Change) -> io::Result<()> {
+        if let Some(log) = self.log.as_mut() {
+            writeln!(log, "{}", change.to_log_line())?;
+            // make sure the change is on disk before it is applied, so that it survives a crash
+            log.sync_data()?;
+        }
+        self.apply(change);
+        Ok(())
+    }
+
+    /// Applies a change to the counts without logging it
+    /// # Arguments
+    /// * `change` - the change to make
+    fn apply(&mut self, change: Change) {
+        match change {
+            Change::Insert(number) => *self.counts.entry(number).or_insert(0) += 1,
+            Change::Remove(number) => {
+                self.counts.remove(&number);
+            }
+            Change::Decrement(number) => {
+                if let Some(count) = self.counts.get_mut(&number) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.counts.remove(&number);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The first line of a change log, with the number of phone numbers the index was built from
+/// and their FNV-1a checksum in the order they were given
+/// # Arguments
+/// * `numbers` - the phone numbers the index was built from
+fn log_header(numbers: &[i64]) -> String {
+    let checksum = numbers.iter().flat_map(|number| number.to_le_bytes()).fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("dataset {} {:016x}", numbers.len(), checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+    use std::collections::HashMap;
+
+    /// This function finds the k nearest unique numbers by sorting all of them
+    fn brute_force_nearest(counts: &HashMap<i64, usize>, target: i64, k: usize) -> Vec<Neighbour> {
+        let mut result: Vec<Neighbour> = counts
+            .iter()
+            .map(|(number, count)| Neighbour { distance: number.abs_diff(target), number: *number, count: *count })
+            .collect();
+        result.sort_by_key(|found| (found.distance, found.number));
+        if k == 0 {
+            return Vec::new();
+        }
+        if let Some(bound) = result.get(k - 1).map(|found| found.distance) {
+            result.retain(|found| found.distance <= bound);
+        }
+        result
+    }
+
+    /// This function creates a unique path in the temporary directory for a change log
+    fn temp_log_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}_{}_{}.log", name, std::process::id(), rand::thread_rng().gen::<u32>()))
+    }
+
+    /// This test applies random inserts, removes and decrements to the index and checks
+    /// that its queries agree with a brute force search after every change
+    #[test]
+    fn test_index_random_changes() {
+        let mut rng = ChaChaRng::seed_from_u64(42);
+        let numbers: Vec<i64> = (0..200).map(|_| rng.gen_range(0..100)).collect();
+        let mut index = NearestIndex::from_numbers(&numbers);
+        let mut expected: HashMap<i64, usize> = HashMap::new();
+        numbers.iter().for_each(|number| *expected.entry(*number).or_insert(0) += 1);
+        for _ in 0..2000 {
+            let number = rng.gen_range(0..100);
+            match rng.gen_range(0..3) {
+                0 => {
+                    *expected.entry(number).or_insert(0) += 1;
+                    assert_eq!(index.insert(number).unwrap(), expected[&number]);
+                }
+                1 => {
+                    let removed = expected.remove(&number).unwrap_or(0);
+                    assert_eq!(index.remove(number).unwrap(), removed);
+                }
+                _ => {
+                    if let Some(count) = expected.get_mut(&number) {
+                        *count -= 1;
+                        if *count == 0 {
+                            expected.remove(&number);
+                        }
+                    }
+                    assert_eq!(index.decrement(number).unwrap(), expected.get(&number).copied().unwrap_or(0));
+                }
+            }
+            let target = rng.gen_range(-10..110);
+            let k = rng.gen_range(0..20);
+            assert_eq!(index.nearest(target, k), brute_force_nearest(&expected, target, k));
+            assert_eq!(index.len(), expected.len());
+        }
+    }
+
+    /// This test checks that the radius query finds every number within the radius
+    #[test]
+    fn test_index_within() {
+        let index = NearestIndex::from_numbers(&[1, 2, 3, 3, 4, 5, 6, 7, 8, 9, 10]);
+        let found: Vec<(i64, usize)> = index.within(3, 1).iter().map(|n| (n.number, n.count)).collect();
+        assert_eq!(found, vec![(3, 2), (2, 1), (4, 1)]);
+        assert!(index.within(20, 5).is_empty());
+        assert_eq!(index.within(i64::MAX, u64::MAX).len(), 10);
+        assert_eq!(NearestIndex::from_numbers(&[i64::MIN, 0, i64::MAX]).within(i64::MIN, u64::MAX).len(), 3);
+    }
+
+    /// This test checks the median against sorting every number
+    #[test]
+    fn test_index_median() {
+        let mut rng = ChaChaRng::seed_from_u64(7);
+        assert_eq!(NearestIndex::default().median(), None);
+        for length in 1..200 {
+            let mut numbers: Vec<i64> = (0..length).map(|_| rng.gen_range(0..50)).collect();
+            let index = NearestIndex::from_numbers(&numbers);
+            numbers.sort();
+            let expected = if length % 2 == 0 {
+                (numbers[length / 2 - 1], Some(numbers[length / 2]))
+            } else {
+                (numbers[length / 2], None)
+            };
+            assert_eq!(index.median(), Some(expected));
+        }
+    }
+
+    /// This test checks that the index can be rebuilt from its change log, ignoring a torn last line
+    #[test]
+    fn test_index_change_log_recovery() {
+        let path = temp_log_path("test_index_change_log_recovery");
+        let numbers = [7327325555, 7327325555, 7327325554];
+        {
+            let mut index = NearestIndex::open(&numbers, &path).unwrap();
+            index.insert(7327325550).unwrap();
+            index.decrement(7327325555).unwrap();
+            index.remove(7327325554).unwrap();
+            // decrementing a missing number is not logged
+            index.decrement(1234567890).unwrap();
+        }
+        // simulate a crash in the middle of writing a change
+        let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(log, "insert 73273").unwrap();
+        drop(log);
+        {
+            let mut index = NearestIndex::open(&numbers, &path).unwrap();
+            assert_eq!(index.counts().iter().map(|(n, c)| (*n, *c)).collect::<Vec<_>>(), vec![(7327325550, 1), (7327325555, 1)]);
+            index.insert(7327325551).unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let changes = "insert 7327325550\ndecrement 7327325555\nremove 7327325554\ninsert 7327325551\n";
+        assert_eq!(contents, format!("{}\n{}", log_header(&numbers), changes));
+        let index = NearestIndex::open(&numbers, &path).unwrap();
+        assert_eq!(index.count(7327325551), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// This test checks that a malformed line in the middle of the change log is an error that leaves
+    /// the changes after it in the log, that lines ending in CRLF are read,
+    /// and that a log is not replayed on top of other phone numbers
+    #[test]
+    fn test_index_change_log_errors() {
+        let path = temp_log_path("test_index_change_log_errors");
+        let numbers = [7327325555, 7327325554];
+        let header = log_header(&numbers);
+        let contents = format!("{}\r\ninsert 7327325550\r\ninsret 7327325551\ninsert 7327325552\n", header);
+        std::fs::write(&path, &contents).unwrap();
+        let error = NearestIndex::open(&numbers, &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 3"), "{}", error);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), contents);
+
+        std::fs::write(&path, contents.replace("insret", "insert")).unwrap();
+        let index = NearestIndex::open(&numbers, &path).unwrap();
+        assert_eq!(index.len(), 5);
+        drop(index);
+        let error = NearestIndex::open(&numbers[..1], &path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
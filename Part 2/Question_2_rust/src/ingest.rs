@@ -0,0 +1,179 @@
+//! Parallel ingestion of large files of records.
+//! The file is read in blocks that end on a line boundary, and each block is split into
+//! one chunk per thread. Every chunk is parsed straight from its bytes into its own counts,
+//! which are then merged in the order of the chunks, so the result is the same as reading
+//! the file one line at a time.
+
+use std::io::{self, BufRead, Read};
+
+use rayon::prelude::*;
+
+use crate::records::{parse_record_bytes, Delimiter, Records};
+use crate::PhoneCounts;
+
+/// The number of bytes read from a file before its lines are parsed
+const BLOCK_SIZE: usize = 16 << 20;
+
+/// The counts and skipped lines of one chunk of a file, with line numbers counted from the
+/// start of the chunk
+#[derive(Debug, Default)]
+struct Chunk {
+    counts: PhoneCounts,
+    lines: usize,
+    rejected: Vec<usize>,
+    invalid_utf8: Vec<usize>,
+}
+
+/// Reads and counts every phone number in a file of records, parsing chunks of it in parallel
+/// on the current rayon thread pool. This gives the same counts and the same rejected lines
+/// as `stream_records`, and lines that are not valid UTF-8 are still parsed for their digits.
+/// # Arguments
+/// * `reader` - Where to read the records from, such as a file opened with `compress::open`
+/// * `delimiter` - The character that separates the records
+/// # Returns
+/// * `(PhoneCounts, Records)` - The counts of the phone numbers, in the order they first appear,
+///   and the lines that were rejected or are not valid UTF-8. The `numbers` of the records are left empty
+/// # Errors
+/// This function will return an error if the reader cannot be read
+/// # Example
+/// ```rust
+/// use question_2_rust::{ingest::count_records, records::Delimiter};
+/// let (counts, records) = count_records(&b"7327325555\n12345\n(732) 732-5554\n7327325555\n"[..], Delimiter::Newline).unwrap();
+/// assert_eq!(counts.iter().collect::<Vec<_>>(), vec![(7327325555, 2), (7327325554, 1)]);
+/// assert_eq!(records.rejected, vec![2]);
+/// ```
+pub fn count_records<R: BufRead>(reader: R, delimiter: Delimiter) -> io::Result<(PhoneCounts, Records)> {
+    count_blocks(reader, delimiter, BLOCK_SIZE)
+}
+
+/// Reads a file in blocks of about `block_size` bytes, each ending on a line boundary,
+/// and counts the phone numbers of each block in parallel
+fn count_blocks<R: BufRead>(mut reader: R, delimiter: Delimiter, block_size: usize) -> io::Result<(PhoneCounts, Records)> {
+    let mut counts = PhoneCounts::default();
+    let mut records = Records::default();
+    let mut lines = 0;
+    let mut buffer = Vec::with_capacity(block_size);
+    loop {
+        let read = (&mut reader).take(block_size as u64).read_to_end(&mut buffer)?;
+        let end = match buffer.iter().rposition(|byte| *byte == b'\n') {
+            _ if read == 0 => buffer.len(),
+            Some(newline) => newline + 1,
+            // a line longer than a block, so keep reading until it ends
+            None => continue,
+        };
+        let chunks = split_lines(&buffer[..end], rayon::current_num_threads());
+        let parsed: Vec<Chunk> = chunks.par_iter().map(|chunk| parse_chunk(chunk, delimiter)).collect();
+        for chunk in parsed {
+            counts.merge(chunk.counts);
+            records.rejected.extend(chunk.rejected.iter().map(|line| line + lines));
+            records.invalid_utf8.extend(chunk.invalid_utf8.iter().map(|line| line + lines));
+            lines += chunk.lines;
+        }
+        buffer.drain(..end);
+        if read == 0 {
+            return Ok((counts, records));
+        }
+    }
+}
+
+/// Splits bytes into about `parts` chunks of about the same size, each ending on a line boundary
+fn split_lines(bytes: &[u8], parts: usize) -> Vec<&[u8]> {
+    let size = bytes.len().div_ceil(parts.max(1)).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = match bytes[(start + size).min(bytes.len())..].iter().position(|byte| *byte == b'\n') {
+            Some(newline) => (start + size).min(bytes.len()) + newline + 1,
+            None => bytes.len(),
+        };
+        chunks.push(&bytes[start..end]);
+        start = end;
+    }
+    chunks
+}
+
+/// Parses every record of a chunk straight from its bytes into its own counts
+fn parse_chunk(bytes: &[u8], delimiter: Delimiter) -> Chunk {
+    let mut chunk = Chunk::default();
+    let separator = delimiter.separator().map(|separator| separator as u8);
+    for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+        chunk.lines += 1;
+        if std::str::from_utf8(line).is_err() {
+            chunk.invalid_utf8.push(chunk.lines);
+        }
+        let mut rejected = false;
+        let fields = line.split(|byte| Some(*byte) == separator);
+        for field in fields.filter(|field| !is_blank(field)) {
+            match parse_record_bytes(field) {
+                Some(number) => chunk.counts.add(number),
+                None => rejected = true,
+            }
+        }
+        if rejected {
+            chunk.rejected.push(chunk.lines);
+        }
+    }
+    chunk
+}
+
+/// Whether a field is empty or only whitespace, the same way as `str::trim`.
+/// A field that is not valid UTF-8 is never blank
+fn is_blank(field: &[u8]) -> bool {
+    std::str::from_utf8(field).is_ok_and(|field| field.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::stream_records;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    /// This function counts records one line at a time, which the parallel counts should match
+    fn count_sequentially(contents: &[u8], delimiter: Delimiter) -> (PhoneCounts, Records) {
+        let mut counts = PhoneCounts::default();
+        let records = stream_records(contents, delimiter, |number| counts.add(number)).unwrap();
+        (counts, records)
+    }
+
+    /// This test checks that the parallel counts match reading one line at a time on random files
+    /// with blank lines, bad records, invalid UTF-8 and no final newline, for blocks small enough
+    /// that lines are split across them
+    #[test]
+    fn test_count_records_against_stream() {
+        let pieces: [&[u8]; 10] = [b"7327325555", b"+1 (732) 732-5554", b"12345", b"", b"  ", b"\xff732.732.5553", b"phone", b"\xc2\xa0", b"1-732-732-5552 ", b"\r"];
+        let mut rng = ChaChaRng::seed_from_u64(40);
+        for delimiter in [Delimiter::Newline, Delimiter::Comma, Delimiter::Tab] {
+            for _ in 0..50 {
+                let mut contents = Vec::new();
+                for _ in 0..rng.gen_range(0..200) {
+                    contents.extend_from_slice(pieces[rng.gen_range(0..pieces.len())]);
+                    contents.push([b'\n', b',', b'\t'][rng.gen_range(0..3)]);
+                }
+                if rng.gen_bool(0.5) {
+                    contents.pop();
+                }
+                let expected = count_sequentially(&contents, delimiter);
+                for block_size in [1, 7, 64, BLOCK_SIZE] {
+                    let (counts, records) = count_blocks(&contents[..], delimiter, block_size).unwrap();
+                    assert_eq!(counts, expected.0);
+                    assert_eq!(counts.iter().collect::<Vec<_>>(), expected.0.iter().collect::<Vec<_>>());
+                    assert_eq!(records.rejected, expected.1.rejected);
+                    assert_eq!(records.invalid_utf8, expected.1.invalid_utf8);
+                }
+            }
+        }
+    }
+
+    /// This test checks that chunks always end on a line boundary and cover every byte
+    #[test]
+    fn test_split_lines() {
+        let bytes = b"a\nbb\nccc\n\ndddd";
+        for parts in 1..8 {
+            let chunks = split_lines(bytes, parts);
+            assert_eq!(chunks.concat(), bytes);
+            assert!(chunks[..chunks.len() - 1].iter().all(|chunk| chunk.ends_with(b"\n")));
+        }
+        assert!(split_lines(b"", 4).is_empty());
+    }
+}
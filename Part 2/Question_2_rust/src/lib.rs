@@ -0,0 +1,236 @@
+//! Nearest neighbour search over scraped phone numbers.
+//! The binary finds the k nearest numbers by their absolute difference, while
+//! the modules here provide the alternative metrics and indexes it can search with.
+
+use std::collections::HashMap;
+
+#[cfg(any(test, feature = "bench"))]
+pub mod baseline;
+pub mod bktree;
+pub mod generate;
+pub mod index;
+pub mod ingest;
+pub mod metric;
+pub mod nearest;
+pub mod pattern;
+pub mod serve;
+pub mod succinct;
+
+// reading and writing phone numbers is shared with Task 1, so it lives in its own crate
+pub use phone_io::{binary, compress, extract, output, records, table};
+pub use phone_io::{Neighbour, NumberHasher, PhoneCounts};
+
+/// Repeats each phone number found by a query as many times as it appeared,
+/// which is how the results are printed
+/// # Arguments
+/// * `neighbours` - the phone numbers found by a query
+/// # Returns
+/// * `Vec<i64>` - the phone numbers, with duplicates
+/// # Example
+/// ```rust
+/// use question_2_rust::{expand_neighbours, Neighbour};
+/// let neighbours = [Neighbour { distance: 0, number: 5, count: 2 }, Neighbour { distance: 1, number: 4, count: 1 }];
+/// assert_eq!(expand_neighbours(&neighbours), vec![5, 5, 4]);
+/// ```
+pub fn expand_neighbours(neighbours: &[Neighbour]) -> Vec<i64> {
+    neighbours
+        .iter()
+        .flat_map(|neighbour| vec![neighbour.number; neighbour.count])
+        .collect()
+}
+
+/// Cleans a phone number string and returns a 64 bit integer
+/// # Arguments
+/// * `phone_number` - The phone number to clean
+/// # Errors
+/// This function will return an error if the phone number is less than 10 digits long
+/// or if the phone number contains invalid characters
+/// # Example
+/// ```rust
+/// use question_2_rust::phone_number_sanitizer;
+/// let result = phone_number_sanitizer(&String::from("1234567890"));
+/// assert_eq!(result, Ok(1234567890));
+/// ```
+pub fn phone_number_sanitizer(phone_number: &str) -> Result<i64, &'static str> {
+    // keep the last 10 digits as they are read, rather than collecting them into a string
+    let mut digits = 0;
+    let mut number: i64 = 0;
+    for byte in phone_number.bytes().filter(|byte| byte.is_ascii_digit()) {
+        digits += 1;
+        number = (number * 10 + (byte - b'0') as i64) % 10_000_000_000;
+    }
+    if digits == 0 {
+        Err("Phone number has invalid characters")
+    } else if number < 1000000000 {
+        Err("Phone number must be at least 10 digits long")
+    } else {
+        Ok(number)
+    }
+}
+
+/// Parses every line in the contents of a file as one phone number,
+/// skipping any that are too short or too long to be a phone number
+/// # Arguments
+/// * `contents` - The contents of the file
+/// # Returns
+/// * `Vec<i64>` - The sanitized phone numbers, in the order they appear
+/// # Example
+/// ```rust
+/// use question_2_rust::parse_phone_numbers;
+/// let numbers = parse_phone_numbers("7327325555\n+1 (732) 732-5554\n12345\n");
+/// assert_eq!(numbers, vec![7327325555, 7327325554]);
+/// ```
+pub fn parse_phone_numbers(contents: &str) -> Vec<i64> {
+    records::parse_records(contents, records::Delimiter::Newline).numbers
+}
+
+/// Counts the number of times each phone number appears, which is the
+/// deduplicated store that every search is run against
+/// # Arguments
+/// * `numbers` - the list of phone numbers
+/// # Returns
+/// * `HashMap<i64, usize>` - each unique phone number with the number of times it appeared
+/// # Example
+/// ```rust
+/// use question_2_rust::count_numbers;
+/// let counts = count_numbers(&[7327325555, 7327325555, 7327325554]);
+/// assert_eq!(counts[&7327325555], 2);
+/// assert_eq!(counts[&7327325554], 1);
+/// ```
+pub fn count_numbers(numbers: &[i64]) -> HashMap<i64, usize> {
+    let mut numbers_counter = HashMap::new();
+    numbers.iter().for_each(|number| {
+        let count = numbers_counter.entry(*number).or_insert(0);
+        *count += 1;
+    });
+    numbers_counter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::test_runner::FileFailurePersistence;
+    use crate::generate::{invalid_phone_numbers, valid_phone_numbers, Format, Malformed, Prefix};
+
+    const NO_DIGITS: Result<i64, &str> = Err("Phone number has invalid characters");
+    const TOO_SHORT: Result<i64, &str> = Err("Phone number must be at least 10 digits long");
+
+    /// Inputs that are easy to get wrong, with what the sanitizer and the file parser read them as.
+    /// The sanitizer keeps the last 10 digits of anything, while the file parser only drops a country code
+    /// from 11 digits and rejects a line with more.
+    const TRICKY_INPUTS: [(&str, Result<i64, &str>, Option<i64>); 32] = [
+        ("", NO_DIGITS, None),
+        ("   ", NO_DIGITS, None),
+        ("n/a", NO_DIGITS, None),
+        ("(---) ---.----", NO_DIGITS, None),
+        // full width and Arabic-Indic digits are not ASCII digits
+        ("７３２５５５１２３４", NO_DIGITS, None),
+        ("٧٣٢٥٥٥١٢٣٤", NO_DIGITS, None),
+        ("732555123", TOO_SHORT, None),
+        ("732-555-123", TOO_SHORT, None),
+        ("732555l234", TOO_SHORT, None),
+        ("0325551234", TOO_SHORT, None),
+        ("(032) 555-1234", TOO_SHORT, None),
+        ("0000000000", TOO_SHORT, None),
+        ("10325551234", TOO_SHORT, None),
+        ("1000000000", Ok(1000000000), Some(1000000000)),
+        ("9999999999", Ok(9999999999), Some(9999999999)),
+        ("7325551234", Ok(7325551234), Some(7325551234)),
+        ("17325551234", Ok(7325551234), Some(7325551234)),
+        ("+1(732)555-1234", Ok(7325551234), Some(7325551234)),
+        ("1 (732) 555 1234", Ok(7325551234), Some(7325551234)),
+        ("(732)5551234", Ok(7325551234), Some(7325551234)),
+        ("  732 - 555 - 1234.  ", Ok(7325551234), Some(7325551234)),
+        ("-7325551234", Ok(7325551234), Some(7325551234)),
+        ("7325551234\r", Ok(7325551234), Some(7325551234)),
+        ("tel:7325551234;", Ok(7325551234), Some(7325551234)),
+        // any eleventh digit is dropped as if it were a country code
+        ("27325551234", Ok(7325551234), Some(7325551234)),
+        // a number exported from a spreadsheet keeps its trailing 0
+        ("7325551234.0", Ok(3255512340), Some(3255512340)),
+        // a 9 digit number after the country code 1 becomes a 10 digit number starting with 1
+        ("1-732-555-123", Ok(1732555123), Some(1732555123)),
+        // only the last 10 of 12 or more digits are kept by the sanitizer
+        ("+44 20 7946 0958", Ok(2079460958), None),
+        ("732-555-1234 ext. 56", Ok(2555123456), None),
+        ("732-555-1234, 908-555-1234", Ok(9085551234), None),
+        ("001 732 555 1234", Ok(7325551234), None),
+        ("99999999999999999999", Ok(9999999999), None),
+    ];
+
+    /// This test will generate 100 random phone numbers in every combination of prefix, separator
+    /// and parentheses, and ensure that they are all sanitized correctly. It also checks that they are
+    /// different numbers, as they were once all the same, and that another seed gives other numbers.
+    /// # Example
+    /// ```bash
+    /// cargo test --release -- test_sanitizer_valid_numbers
+    /// ```
+    #[test]
+    fn test_sanitizer_valid_numbers() {
+        for (seed, format) in Format::all().into_iter().enumerate() {
+            let numbers = valid_phone_numbers(format, 100, seed as u64);
+            for (phone_number, original) in &numbers {
+                assert_eq!(phone_number_sanitizer(original), Ok(*phone_number), "{} in {:?}", original, format);
+            }
+            let mut unique: Vec<i64> = numbers.iter().map(|(number, _)| *number).collect();
+            unique.sort_unstable();
+            unique.dedup();
+            assert!(unique.len() > 90, "only {} different numbers in {:?}", unique.len(), format);
+            assert_ne!(valid_phone_numbers(format, 100, seed as u64 + 100), numbers);
+        }
+    }
+
+    /// This test will generate 100 incorrect random phone numbers for every way they are broken
+    /// and every format without a prefix, and ensure that they are all not sanitized
+    /// # Example
+    /// ```bash
+    /// cargo test --release -- test_sanitizer_invalid_numbers
+    /// ```
+    #[test]
+    fn test_sanitizer_invalid_numbers() {
+        let formats = Format::all().into_iter().filter(|format| format.prefix == Prefix::None);
+        for (seed, format) in formats.enumerate() {
+            for kind in Malformed::ALL {
+                for phone_number in invalid_phone_numbers(kind, format, 100, seed as u64) {
+                    if phone_number_sanitizer(&phone_number).is_ok() {
+                        panic!("Invalid phone number {} was sanitized, broken by {:?} in {:?}", phone_number, kind, format);
+                    }
+                }
+            }
+        }
+    }
+
+    /// This test checks the sanitizer and the file parser against a table of inputs that are easy to get wrong
+    /// # Example
+    /// ```bash
+    /// cargo test --release -- test_sanitizer_tricky_inputs
+    /// ```
+    #[test]
+    fn test_sanitizer_tricky_inputs() {
+        for (input, sanitized, parsed) in TRICKY_INPUTS {
+            assert_eq!(phone_number_sanitizer(input), sanitized, "sanitizing {:?}", input);
+            assert_eq!(parse_phone_numbers(input).first().copied(), parsed, "parsing {:?}", input);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            failure_persistence: Some(Box::new(FileFailurePersistence::SourceParallel("proptest-regressions"))),
+            ..ProptestConfig::default()
+        })]
+
+        /// The sanitizer reads back any 10 digit phone number written in any format,
+        /// with any text around it that has no digits
+        #[test]
+        fn prop_sanitizer_round_trips(
+            number in 1_000_000_000..10_000_000_000_i64,
+            format in prop::sample::select(Format::all()),
+            before in "[a-zA-Z:#() ]{0,6}",
+            after in "[a-zA-Z.,;() ]{0,6}",
+        ) {
+            let formatted = format!("{}{}{}", before, format.write(number), after);
+            prop_assert_eq!(phone_number_sanitizer(&formatted), Ok(number), "{}", formatted);
+        }
+    }
+}
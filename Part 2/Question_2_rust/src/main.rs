@@ -1,477 +1,653 @@
-use std::{env, fs};
-use std::collections::{HashMap, BTreeMap};
+use std::fmt;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, Read, Write};
+use std::process::ExitCode;
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+use question_2_rust::{expand_neighbours, phone_number_sanitizer, Neighbour, PhoneCounts, binary::{is_binary_file, write_binary, BinaryDataset}, bktree::BkTree, compress, index::NearestIndex, ingest::count_records, metric::Metric, nearest::{kth_nearest, within_radius}, output::{write_report, InputStats, OutputFormat, Report}, pattern::{Pattern, PatternIndex}, extract::{extract_phone_numbers, PhoneMatch}, records::{describe_rejected, read_records, stream_records, Delimiter, Layout, Records}, serve::{serve, Service}, succinct::{SuccinctIndex, SuccinctIndexBuilder}, table::{Table, TableFormat}};
+
+/// The exit code when the query found no phone numbers
+const EXIT_NO_RESULT: u8 = 1;
+/// The exit code when the arguments are invalid, which is also the code clap exits with
+const EXIT_USAGE: u8 = 2;
+/// The exit code when a file cannot be read or written
+const EXIT_IO: u8 = 3;
+
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  The query found at least one phone number
+  1  The query found no phone numbers
+  2  The arguments are invalid
+  3  A file could not be read or written";
+
+/// Finds the k nearest phone numbers to a target number in a file of scraped phone numbers.
+/// Without a subcommand, the arguments are the same as the `nearest` subcommand.
+#[derive(Debug, Parser)]
+#[command(version, args_conflicts_with_subcommands = true, after_help = EXIT_CODES_HELP)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    nearest: Option<NearestArgs>,
+    /// What separates the phone numbers in the file: `newline`, `comma` or `tab`.
+    /// Each one is parsed as a whole, so it can contain spaces
+    #[arg(long, global = true, default_value = "newline")]
+    delimiter: Delimiter,
+    /// Scan the file as free text or HTML, such as a scraped page, and use every phone number found in it
+    #[arg(long, global = true, conflicts_with = "delimiter")]
+    extract: bool,
+    /// Read the file as an exported table: `csv`, `tsv` or `jsonl`.
+    /// The nearest and radius results are printed with the rest of the row each number was found in
+    #[arg(long, global = true, conflicts_with_all = ["delimiter", "extract"], requires = "column")]
+    format: Option<TableFormat>,
+    /// The column of the table with the phone numbers, by its name in the header or its position counted from 1.
+    /// For JSON lines, the field, which can be nested with dots such as `contact.phone`
+    #[arg(long, visible_alias = "field", global = true, requires = "format")]
+    column: Option<String>,
+    /// The first row of a CSV or TSV table is a row of data rather than a header
+    #[arg(long, global = true, requires = "format")]
+    no_header: bool,
+    /// How to print the results: `text`, `json`, `csv` or `ndjson`.
+    /// JSON and NDJSON include how many records were read and rejected,
+    /// and the distance and count of each number found
+    #[arg(long, global = true, default_value = "text")]
+    output: OutputFormat,
+    /// The number of threads to read files of records with, which defaults to the number of CPUs
+    #[arg(long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Find the k nearest phone numbers to the target number
+    Nearest(NearestArgs),
+    /// Find every phone number within a distance of the target number
+    Radius(RadiusArgs),
+    /// Find every phone number matching a pattern of digits and `?` wildcards, or starting with it,
+    /// printed with the number of times it appears
+    Pattern(PatternArgs),
+    /// Keep the phone numbers in an index and read commands from stdin, one per line:
+    /// `insert <number>`, `remove <number>`, `decrement <number>`, `nearest <target> <k>` and `radius <target> <r>`
+    Interactive(InteractiveArgs),
+    /// Answer k-nearest, radius and median queries as JSON over HTTP on a local address,
+    /// reloading the file whenever it changes
+    Serve(ServeArgs),
+    /// Find every phone number in a file of free text or HTML, such as a scraped page,
+    /// printed with its byte offset in the file and the text it was found in
+    Extract(ExtractArgs),
+    /// Convert a file of phone numbers to a binary dataset, which is queried in place without
+    /// being parsed, or convert a binary dataset back to text with one phone number per line
+    Convert(ConvertArgs),
+}
+
+#[derive(Debug, Args)]
+struct NearestArgs {
+    /// The file of phone numbers to search
+    filename: String,
+    /// The target number to find the nearest numbers to
+    #[arg(value_parser = phone_number_sanitizer)]
+    target_number: i64,
+    /// The number of unique nearest numbers to return, more if there is a tie
+    #[arg(default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    k: u64,
+    /// The distance metric to use: `absolute`, `hamming` or `damerau-levenshtein`
+    #[arg(long, default_value = "absolute")]
+    metric: Metric,
+    /// Answer absolute distance queries from an Elias-Fano compressed index, built from the phone numbers
+    /// sorted in runs on disk instead of counted in a hash map, which needs less memory unless most of them are repeated
+    #[arg(long)]
+    succinct: bool,
+}
+
+#[derive(Debug, Args)]
+struct RadiusArgs {
+    /// The file of phone numbers to search
+    filename: String,
+    /// The target number to find the numbers around
+    #[arg(value_parser = phone_number_sanitizer)]
+    target_number: i64,
+    /// The maximum distance from the target number, inclusive
+    radius: u64,
+    /// The distance metric to use: `absolute`, `hamming` or `damerau-levenshtein`
+    #[arg(long, default_value = "absolute")]
+    metric: Metric,
+    /// Answer absolute distance queries from an Elias-Fano compressed index, built from the phone numbers
+    /// sorted in runs on disk instead of counted in a hash map, which needs less memory unless most of them are repeated
+    #[arg(long)]
+    succinct: bool,
+}
+
+#[derive(Debug, Args)]
+struct PatternArgs {
+    /// The file of phone numbers to search
+    filename: String,
+    /// The pattern to match, such as `732-73?-55??` or `732732`
+    pattern: Pattern,
+}
+
+#[derive(Debug, Args)]
+struct InteractiveArgs {
+    /// The file of phone numbers to start the index with
+    filename: String,
+    /// The change log the index is rebuilt from on start and appends its changes to
+    #[arg(long)]
+    log: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct ServeArgs {
+    /// The file of phone numbers to serve
+    filename: String,
+    /// The loopback address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    address: String,
+}
+
+#[derive(Debug, Args)]
+struct ExtractArgs {
+    /// The file of free text or HTML to search
+    filename: String,
+}
+
+#[derive(Debug, Args)]
+struct ConvertArgs {
+    /// The file to convert, which is converted to text if it is a binary dataset and to a binary dataset otherwise
+    input: String,
+    /// The file to write
+    destination: String,
+}
+
+/// An error that stops the program, which decides its exit code
+/// * `Usage` - the arguments are invalid
+/// * `Io` - a file could not be read or written
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Io(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Usage(_) => EXIT_USAGE,
+            CliError::Io(_) => EXIT_IO,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
 
 /// This function is the entry point of the program.
-/// It takes in a file name, a target number, and a k value.
+/// It takes in a file name, a target number, and an optional k value, which defaults to 1.
 /// It will then read the file and find the k nearest numbers to the target number.
 /// In the case of a tie between the last 2 numbers, it will print both numbers.
-/// # Arguments
-/// * `filename` - The name of the file to read
-/// * `target_number` - The target number to find the k nearest numbers to
-/// * `k` - The number of nearest numbers to return
+/// Run with `--help` for the other subcommands and options.
 /// # Example
 /// ```bash
 /// cargo run --release -- phonescraped 1234567890 3
 /// > 1234567890
 /// > 1234567891
 /// > 1234567889
+/// cargo run --release -- radius phonescraped 7327325550 1 --metric hamming
+/// > 7327325551
+/// > 7327325552
+/// > 7327325553
+/// > 7327325554
+/// > 7327325555
+/// cargo run --release -- pattern phonescraped 732-73?-555?
+/// > 7327325551 1
+/// > 7327325552 3
+/// > 7327325553 1
+/// > 7327325554 1
+/// > 7327325555 5
+/// cargo run --release -- convert phonescraped phonescraped.bin
+/// cargo run --release -- phonescraped.bin 7327325555 1
+/// > 7327325555
+/// > 7327325555
+/// > 7327325555
+/// > 7327325555
+/// > 7327325555
+/// cargo run --release -- interactive phonescraped --log changes.log
+/// < insert 7327325556
+/// > 1
+/// < nearest 7327325556 2
+/// > 7327325556 7327325555 7327325555 7327325555 7327325555 7327325555
+/// cargo run --release -- serve phonescraped --address 127.0.0.1:8080
+/// curl "http://127.0.0.1:8080/nearest?target=7327325555&k=1"
+/// > {"target":7327325555,"k":1,"neighbours":[{"distance":0,"number":7327325555,"count":5}]}
 /// ```
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    assert!(args.len() == 4, "Usage: cargo run --release -- <filename> <target_number> <k>");
-    let filename = &args[1];
-    let target_number = phone_number_sanitizer(&args[2]).unwrap();
-    let k = &args[3].parse::<i64>().unwrap();
-
-    if *k <= 0 {
-        panic!("k must be greater than 0");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let command = match (cli.command, cli.nearest) {
+        (Some(command), _) => command,
+        (None, Some(nearest)) => Command::Nearest(nearest),
+        (None, None) => {
+            eprintln!("error: a file and target number are required\n\nFor more information, try '--help'.");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    if let Some(threads) = cli.threads {
+        // this can only fail if the pool was already built, which it has not been yet
+        rayon::ThreadPoolBuilder::new().num_threads(threads as usize).build_global().unwrap_or_default();
+    }
+    let layout = match (cli.format, cli.column) {
+        (Some(format), Some(column)) => Layout::Table(Table { format, column, header: !cli.no_header }),
+        _ if cli.extract => Layout::Text,
+        _ => Layout::Records(cli.delimiter),
+    };
+    match run(command, layout, cli.output) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_NO_RESULT),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::from(error.exit_code())
+        }
     }
-
-    let contents = fs::read_to_string(filename).expect("Something went wrong reading the file");
-    let phone_numbers: Vec<i64> = contents
-        .split_whitespace()
-        .map(|number| { // clean the phone number string
-            let cleaned: String = number
-                .chars()
-                .filter(|c| c.is_digit(10)) // filter out non-digits
-                .collect();
-            cleaned
-        })
-        .filter(|cleaned| cleaned.len() >= 10 && cleaned.len() <= 11) // filter out numbers that are too short or too long length wise
-        .map(|cleaned| cleaned.chars().rev().take(10).collect::<String>().chars().rev().collect::<String>().parse().unwrap()) // take the last 10 characters and parse it into an integer
-        .filter(|number| number >= &1000000000) // filter out numbers that are less than 10 digits long
-        .collect();
-    let result = kth_nearest(*k, target_number, phone_numbers);
-    result.iter().for_each(|number| println!("{}", number));
 }
 
-
-/// Cleans a phone number string and returns a 64 bit integer
+/// Runs a subcommand, printing its results
 /// # Arguments
-/// * `phone_number` - The phone number to clean
-/// # Errors
-/// This function will return an error if the phone number is less than 10 digits long
-/// or if the phone number contains invalid characters
-/// # Example
-/// ```rust
-/// let result = phone_number_sanitizer(&String::from("1234567890"));
-/// assert_eq!(result, Ok(1234567890));
-/// ```
-fn phone_number_sanitizer(phone_number: &String) -> Result<i64, &'static str> {
-    let cleaned = phone_number
-        .chars()
-        .filter(|c| c.is_digit(10)) // filter out non-digits
-        .collect::<String>()
-        .chars()
-        .rev() // reverse the string and take the last 10 characters
-        .take(10)
-        .collect::<String>()
-        .chars()
-        .rev() // reverse it back again
-        .collect::<String>() // collect it back into a string
-        .parse::<i64>(); // parse it into an integer
-    match cleaned {
-        Ok(number) => {
-            if number < 1000000000 {
-                Err("Phone number must be at least 10 digits long")
+/// * `command` - The subcommand to run
+/// * `layout` - How the phone numbers are laid out in the file
+/// * `output` - How to print the results
+/// # Returns
+/// * `Result<bool, CliError>` - Whether any phone numbers were found
+fn run(command: Command, layout: Layout, output: OutputFormat) -> Result<bool, CliError> {
+    match command {
+        Command::Nearest(args) => {
+            if args.metric == Metric::Absolute {
+                if let Some(dataset) = open_binary(&args.filename)? {
+                    // a binary dataset is already sorted, so it is searched in place
+                    let result = expand_neighbours(&dataset.nearest(args.target_number, args.k as usize));
+                    let query = json!({ "target": args.target_number, "k": args.k, "metric": args.metric.to_string() });
+                    return print_neighbours(output, binary_input(&dataset), query, args.metric, args.target_number, &result, HashMap::new());
+                }
+            }
+            let query = json!({ "target": args.target_number, "k": args.k, "metric": args.metric.to_string() });
+            if args.metric == Metric::Absolute && args.succinct {
+                let SuccinctDataset { index, rows, input } = read_succinct(&args.filename, &layout)?;
+                let result = expand_neighbours(&index.nearest(args.target_number, args.k as usize));
+                return print_neighbours(output, input, query, args.metric, args.target_number, &result, rows);
+            }
+            let Dataset { counts, rows, input } = read_counts(&args.filename, &layout)?;
+            let result = match args.metric {
+                Metric::Absolute => kth_nearest(args.k as i64, args.target_number, &counts),
+                metric => {
+                    // the discrete metrics are searched with a BK-tree
+                    let tree = BkTree::from_counts(metric, counts.iter());
+                    expand_neighbours(&tree.nearest(args.target_number, args.k as usize))
+                }
+            };
+            print_neighbours(output, input, query, args.metric, args.target_number, &result, rows)
+        }
+        Command::Radius(args) => {
+            if args.metric == Metric::Absolute {
+                if let Some(dataset) = open_binary(&args.filename)? {
+                    let result = expand_neighbours(&dataset.within(args.target_number, args.radius));
+                    let query = json!({ "target": args.target_number, "radius": args.radius, "metric": args.metric.to_string() });
+                    return print_neighbours(output, binary_input(&dataset), query, args.metric, args.target_number, &result, HashMap::new());
+                }
+            }
+            let query = json!({ "target": args.target_number, "radius": args.radius, "metric": args.metric.to_string() });
+            if args.metric == Metric::Absolute && args.succinct {
+                let SuccinctDataset { index, rows, input } = read_succinct(&args.filename, &layout)?;
+                let result = expand_neighbours(&index.within(args.target_number, args.radius));
+                return print_neighbours(output, input, query, args.metric, args.target_number, &result, rows);
+            }
+            let Dataset { counts, rows, input } = read_counts(&args.filename, &layout)?;
+            let result = match args.metric {
+                Metric::Absolute => within_radius(args.radius, args.target_number, &counts),
+                metric => {
+                    let tree = BkTree::from_counts(metric, counts.iter());
+                    expand_neighbours(&tree.within(args.target_number, args.radius))
+                }
+            };
+            print_neighbours(output, input, query, args.metric, args.target_number, &result, rows)
+        }
+        Command::Pattern(args) => {
+            let Dataset { counts, input, .. } = read_counts(&args.filename, &layout)?;
+            let index = PatternIndex::from_counts(counts.counts());
+            let matches = index.find(&args.pattern);
+            if output == OutputFormat::Text {
+                matches.iter().for_each(|(number, count)| println!("{} {}", number, count));
             } else {
-                Ok(number)
+                let results = matches.iter().map(|(number, count)| PatternMatch { number: *number, count: *count }).collect();
+                let query = json!({ "pattern": args.pattern.to_string() });
+                print_report(output, Report { input, query, name: "matches", kind: "match", results })?;
             }
-        },
-        Err(_) => Err("Phone number has invalid characters")
-    }
-}
-
-/// Returns the k nearest numbers to the target number
-/// while accounting for duplicates
-/// # Arguments
-/// * `k` - The number of nearest numbers to return
-/// * `target` - The target number
-/// * `numbers` - The list of numbers to search
-/// # Example
-/// ```rust
-/// let numbers = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-/// let result = kth_nearest(3, 5, numbers);
-/// assert_eq!(result, vec![4, 5, 6]);
-/// ```
-fn kth_nearest(k: i64, target: i64, numbers: Vec<i64>) -> Vec<i64> {
-    // count the number of times each number appears in the list
-    let mut numbers_counter = HashMap::new();
-    numbers.iter().for_each(|number| {
-        let count = numbers_counter.entry(number).or_insert(0);
-        *count += 1;
-    });
-    // calculate the differences between each number and the target number,
-    // and store the numbers that have the same difference in a BTreeMap
-    let mut differences : BTreeMap<i64, Vec<i64>> = BTreeMap::new();
-    for (number, _) in numbers_counter.iter() {
-        let abs_distance = (*number - target).abs();
-        let difference_count = differences.entry(abs_distance).or_insert(Vec::new());
-        difference_count.push(*number.clone());
-    }
-    let mut result: Vec<i64> = Vec::new();
-    let mut count = 0;
-    for (_, numbers) in differences.iter().take(k as usize) { // take only k keys from the BTreeMap
-        let mut numbers_to_add: Vec<i64> = Vec::new();
-        for number in numbers {
-            count += 1;
-            let num_times = numbers_counter.get(number).unwrap();
-            numbers_to_add.append(&mut vec![*number; *num_times as usize]);
+            Ok(!matches.is_empty())
         }
-        result.append(&mut numbers_to_add);
-        if count >= k as usize {
-            // we have enough numbers, so we can stop
-            break;
+        Command::Interactive(args) => {
+            let phone_numbers = read_phone_numbers(&args.filename, &layout)?.numbers;
+            let index = match args.log {
+                Some(log) => NearestIndex::open(&phone_numbers, &log)
+                    .map_err(|e| CliError::Io(format!("could not open the change log {}: {}", log, e)))?,
+                None => NearestIndex::from_numbers(&phone_numbers),
+            };
+            run_interactive(index).map_err(|e| CliError::Io(format!("could not read stdin: {}", e)))?;
+            Ok(true)
         }
-    }
-    result
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::Rng;
-    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
-    use std::io::Write;
-    use std::time::{Duration, Instant};
-    use rayon::prelude::*;
-    use rayon::iter::ParallelIterator;
-    use indicatif::{ParallelProgressIterator, ProgressStyle, ProgressIterator};
-
-    /// This test will generate 100 random phone numbers and
-    /// ensure that they are all sanitized correctly
-    /// # Example
-    /// ```bash
-    /// cargo test --release -- test_sanitizer_valid_numbers
-    /// ```
-    #[test]
-    fn test_sanitizer_valid_numbers() {
-        for _ in 0..100 {
-            let (phone_number, _) = valid_phone_number_generator();
-            let result = phone_number_sanitizer(&phone_number.to_string());
-            assert_eq!(result, Ok(phone_number));
+        Command::Serve(args) => {
+            let service = Service::load(&args.filename, layout).map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidInput => CliError::Usage(format!("could not read {}: {}", args.filename, e)),
+                _ => CliError::Io(format!("could not read {}: {}", args.filename, e)),
+            })?;
+            serve(&service, &args.address).map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidInput => CliError::Usage(format!("invalid address {}: {}", args.address, e)),
+                _ => CliError::Io(format!("could not serve on {}: {}", args.address, e)),
+            })?;
+            Ok(true)
         }
-    }
-
-    /// This test will generate 100 incorrect random phone numbers and
-    /// ensure that they are all not sanitized
-    /// # Example
-    /// ```bash
-    /// cargo test --release -- test_sanitizer_invalid_numbers
-    /// ```
-    #[test]
-    fn test_sanitizer_invalid_numbers() {
-        for _ in 0..100 {
-            let phone_number = invalid_phone_number_generator();
-            let result = phone_number_sanitizer(&phone_number);
-            match result {
-                Ok(_) => panic!("Invalid phone number was sanitized"),
-                Err(_) => {}
+        Command::Extract(args) => {
+            let mut contents = String::new();
+            compress::open(&args.filename)
+                .and_then(|mut reader| reader.read_to_string(&mut contents))
+                .map_err(|e| CliError::Io(format!("could not read {}: {}", args.filename, e)))?;
+            let found = extract_phone_numbers(&contents);
+            if output == OutputFormat::Text {
+                found.iter().for_each(|m| println!("{}\t{}\t{}", m.start, m.number, &contents[m.start..m.end]));
+            } else {
+                let results = found.iter().map(|m| ExtractedNumber { found: *m, text: &contents[m.start..m.end] }).collect();
+                let input = InputStats { parsed: found.len(), rejected: 0 };
+                print_report(output, Report { input, query: serde_json::Value::Null, name: "matches", kind: "match", results })?;
+            }
+            Ok(!found.is_empty())
+        }
+        Command::Convert(args) => {
+            let write_error = |e: io::Error| CliError::Io(format!("could not write {}: {}", args.destination, e));
+            match open_binary(&args.input)? {
+                Some(dataset) => {
+                    dataset.verify().map_err(|e| read_error(&args.input, e))?;
+                    File::create(&args.destination).and_then(|file| dataset.write_text(file)).map_err(write_error)?;
+                }
+                None => {
+                    let Dataset { counts, .. } = read_counts(&args.input, &layout)?;
+                    File::create(&args.destination).and_then(|file| write_binary(file, &counts)).map_err(write_error)?;
+                }
             }
+            Ok(true)
         }
     }
+}
 
-    /// This function generates a random valid phone number
-    /// as a tuple of (i64, String) where the i64 is the
-    /// sanitized phone number and the String is the original
-    /// phone number
-    /// # Example
-    /// ```rust
-    /// let (phone_number, original) = valid_phone_number_generator();
-    /// assert!(phone_number = phone_number_sanitizer(&original).unwrap());
-    /// ```
-    fn valid_phone_number_generator() -> (i64, String) {
-        let mut rng = ChaChaRng::seed_from_u64(0);
-        // generate the 3 parts of a US phone number
-        let first_part = rng.gen_range(100..1000);
-        let second_part = rng.gen_range(0..1000);
-        let third_part = rng.gen_range(0..10000);
-        let prefix = match rng.gen_bool(0.5) {
-            // US prefix
-            true => "+1".to_owned(),
-            false => "".to_owned()
-        };
-        // include dashses or spaces or none:
-        let separator = match rng.gen_range(0..3) {
-            0 => "-".to_owned(),
-            1 => " ".to_owned(),
-            _ => "".to_owned()
-        };
-        let phone_number = format!("{}{:0>3}{}{:0>3}{}{:0>4}", prefix, first_part, separator, second_part, separator, third_part);
-        let phone_number_int = format!("{}{:0>3}{:0>3}{:0>4}", prefix, first_part, second_part, third_part).parse::<i64>().unwrap();
-        (phone_number_int, phone_number)
+/// Maps a file if it is a binary dataset, which is told apart from text by its first bytes
+/// # Arguments
+/// * `filename` - The name of the file to map
+/// # Returns
+/// * `Result<Option<BinaryDataset>, CliError>` - The dataset, or None if the file is not a binary dataset
+/// # Errors
+/// This function will return an I/O error if the file cannot be read or is a corrupt binary dataset
+fn open_binary(filename: &str) -> Result<Option<BinaryDataset>, CliError> {
+    match is_binary_file(filename) {
+        Ok(true) => BinaryDataset::open(filename).map(Some).map_err(|e| read_error(filename, e)),
+        // a file that cannot be read is reported by whatever reads it as text
+        Ok(false) | Err(_) => Ok(None),
     }
+}
 
-    /// This function generates a random invalid phone number
-    /// as a String
-    /// # Example
-    /// ```rust
-    /// let phone_number = invalid_phone_number_generator();
-    /// assert!(phone_number_sanitizer(&phone_number).is_err());
-    /// ```
-    fn invalid_phone_number_generator() -> String {
-        let mut rng = ChaChaRng::seed_from_u64(0);
-        // generate the 3 parts of a US phone number
-        let first_part = rng.gen_range(0..100); // invalid first part
-        let second_part = rng.gen_range(0..1000);
-        let third_part = rng.gen_range(0..10000);
-        let prefix = match rng.gen_bool(0.5) {
-            // add US prefix at random
-            true => "+1".to_owned(),
-            false => "".to_owned()
-        };
-        let mut number = format!("{}{:0>3}{:0>3}{:0>4}", prefix, first_part, second_part, third_part);
-        // replace a random character with a non-digit at random
-        match rng.gen_bool(0.5) {
-            true => {
-                rng.gen_range(0..number.len());
-                let index = rng.gen_range(0..number.len());
-                number.replace_range(index..index+1, "a");
-            },
-            false => {}
-        }
-        number
-    }
+/// How many records a binary dataset holds, none of which were rejected when it was written
+fn binary_input(dataset: &BinaryDataset) -> InputStats {
+    InputStats { parsed: dataset.total(), rejected: 0 }
+}
 
-    /// This test will test the kth-nearest function
-    /// on a predefined set of numbers
-    #[test]
-    fn test_kth_nearest_pre_defined() {
-        let numbers = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-        let result = kth_nearest(4, 5, numbers);
-        assert_eq!(result, vec![3, 4, 5, 6, 7]);
+/// Reads and sanitizes every phone number in a file, which can be compressed with gzip or zstd
+/// or be a binary dataset, warning on stderr about the lines that are not phone numbers
+/// # Arguments
+/// * `filename` - The name of the file to read
+/// * `layout` - How the phone numbers are laid out in the file
+/// # Errors
+/// This function will return an I/O error if the file cannot be read,
+/// or a usage error if it is a table without the column in its header
+fn read_phone_numbers(filename: &str, layout: &Layout) -> Result<Records, CliError> {
+    if let Some(dataset) = open_binary(filename)? {
+        return Ok(Records { numbers: dataset.numbers(), ..Records::default() });
     }
+    let records = compress::open(filename).and_then(|reader| read_records(reader, layout)).map_err(|e| read_error(filename, e))?;
+    warn_skipped(filename, &records);
+    Ok(records)
+}
 
-    /// This function is a solution that implements the quicksort algorithm
-    /// to sort a vector of i64s then returns the kth nearest numbers
-    /// to the target number
-    /// # Arguments
-    /// * `k` - the number of nearest numbers to return
-    /// * `target` - the target number
-    /// * `numbers` - the vector of numbers to search
-    /// # Returns
-    /// * `Vec<i64>` - the kth nearest numbers to the target number
-    /// # Example
-    /// ```rust
-    /// let numbers = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-    /// let result = kth_nearest(4, 5, numbers);
-    /// assert_eq!(result, vec![3, 4, 5, 6, 7]);
-    /// ```
-    fn naive_sorting_find_kth_nearest(k: u64, target: i64, numbers: Vec<i64>) -> Vec<i64> {
-        let mut sorted_numbers = numbers.clone();
-        quicksort(&mut sorted_numbers);
-        let mut differences = HashMap::new();
-        // store the count of each number
-        for number in sorted_numbers {
-            let distance = number - target;
-            let difference_count = differences.entry(distance).or_insert(0);
-            *difference_count += 1;
+/// The phone numbers of a file counted for a query
+/// * `counts` - the number of times each phone number appears
+/// * `rows` - the rows of each phone number, as grouped by `rows_by_number`
+/// * `input` - how many records were read and rejected
+struct Dataset {
+    counts: PhoneCounts,
+    rows: HashMap<i64, VecDeque<String>>,
+    input: InputStats,
+}
+
+/// Reads and counts every phone number in a file, which can be compressed with gzip or zstd
+/// or be a binary dataset, warning on stderr about the lines that are not phone numbers.
+/// A file of records is read in blocks whose lines are counted in parallel, so the phone numbers
+/// are never collected into a list, while tables and free text are read first.
+/// # Arguments
+/// * `filename` - The name of the file to read
+/// * `layout` - How the phone numbers are laid out in the file
+/// # Errors
+/// This function will return an I/O error if the file cannot be read,
+/// or a usage error if it is a table without the column in its header
+fn read_counts(filename: &str, layout: &Layout) -> Result<Dataset, CliError> {
+    if let Some(dataset) = open_binary(filename)? {
+        return Ok(Dataset { counts: dataset.to_counts(), rows: HashMap::new(), input: binary_input(&dataset) });
+    }
+    match layout {
+        Layout::Records(delimiter) => {
+            let (counts, records) = compress::open(filename)
+                .and_then(|reader| count_records(reader, *delimiter))
+                .map_err(|e| read_error(filename, e))?;
+            warn_skipped(filename, &records);
+            let input = InputStats { parsed: counts.total(), rejected: records.rejected.len() };
+            Ok(Dataset { counts, rows: HashMap::new(), input })
         }
-        let mut differences_sorted = differences.keys().collect::<Vec<&i64>>();
-        differences_sorted.sort_by(|a, b| a.abs().cmp(&b.abs())); // sort by absolute distance from target
-        let mut result: Vec<i64>  = Vec::new();
-        let mut last_abs_distance: i64 = *differences.get(differences_sorted[0]).unwrap() as i64;
-        let mut count = 0;
-        for difference in differences_sorted {
-            let num_times = differences.get(difference).unwrap();
-            let actual_number = target + difference;
-            for _ in 0..*num_times {
-                result.push(actual_number);
-            }
-            let abs_distance = difference.abs();
-            count += 1;
-            if abs_distance > last_abs_distance {
-                last_abs_distance = abs_distance;
-                if count >= k {
-                    // we have found the kth or k+1th nearest number
-                    break;
-                }
-            }
+        _ => {
+            let Records { numbers, rows, rejected, .. } = read_phone_numbers(filename, layout)?;
+            let input = InputStats { parsed: numbers.len(), rejected: rejected.len() };
+            Ok(Dataset { counts: PhoneCounts::from_numbers(&numbers), rows: rows_by_number(&numbers, rows), input })
         }
-        return result;
     }
+}
 
-    /// This function is for testing the performance of the quick sort algorithm
-    /// to the quick select algorithm.
-    /// # Arguments
-    /// * `arr` - A vector of T that is to be sorted    
-    fn quicksort<T: PartialOrd>(arr: &mut [T]) {
-        if arr.len() < 1 {
-            return;
-        }
-        let pivot = partition(arr);
-        quicksort(&mut arr[..pivot]);
-        quicksort(&mut arr[pivot + 1..]);
-    }
+/// The phone numbers of a file indexed for an absolute distance query with `--succinct`
+/// * `index` - the compressed index of the phone numbers and their counts
+/// * `rows` - the rows of each phone number, as grouped by `rows_by_number`
+/// * `input` - how many records were read and rejected
+struct SuccinctDataset {
+    index: SuccinctIndex,
+    rows: HashMap<i64, VecDeque<String>>,
+    input: InputStats,
+}
 
-    /// This function is used by the quick sort algorithm to partition the vector.
-    /// # Arguments
-    /// * `arr` - A vector of T that is to be sorted
-    /// # Returns
-    /// * `usize` - The index of the pivot element
-    /// # Example
-    /// ```
-    /// let mut arr = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
-    /// let pivot = partition(&mut arr);
-    /// ```
-    fn partition<T: PartialOrd>(arr: &mut [T]) -> usize {
-        let len = arr.len();
-        // let pivot_index = len / 2;
-        // select a random pivot index
-        let mut rng = rand::thread_rng();
-        let pivot_index = rng.gen_range(0..len);
-        arr.swap(pivot_index, len - 1);
-        let mut store_index = 0;
-        for i in 0..len - 1 {
-            if arr[i] < arr[len - 1] {
-                arr.swap(i, store_index);
-                store_index += 1;
-            }
+/// Reads every phone number in a text file into a compressed index, handing each number to the builder
+/// as a file of records is streamed, so they are neither collected into a list nor counted in a hash map
+/// as `read_counts` does. A binary dataset is already sorted and is searched in place before this is called.
+/// # Arguments
+/// * `filename` - The name of the file to read
+/// * `layout` - How the phone numbers are laid out in the file
+/// # Errors
+/// This function will return an I/O error if the file cannot be read or the numbers cannot be sorted,
+/// or a usage error if it is a table without the column in its header
+fn read_succinct(filename: &str, layout: &Layout) -> Result<SuccinctDataset, CliError> {
+    let mut builder = SuccinctIndexBuilder::default();
+    let (rows, input) = match layout {
+        Layout::Records(delimiter) => {
+            let mut parsed = 0;
+            let records = compress::open(filename)
+                .and_then(|reader| stream_records(reader, *delimiter, |number| {
+                    parsed += 1;
+                    builder.push(number);
+                }))
+                .map_err(|e| read_error(filename, e))?;
+            warn_skipped(filename, &records);
+            (HashMap::new(), InputStats { parsed, rejected: records.rejected.len() })
+        }
+        _ => {
+            let Records { numbers, rows, rejected, .. } = read_phone_numbers(filename, layout)?;
+            numbers.iter().for_each(|number| builder.push(*number));
+            let input = InputStats { parsed: numbers.len(), rejected: rejected.len() };
+            (rows_by_number(&numbers, rows), input)
         }
-        arr.swap(store_index, len - 1);
-        store_index
+    };
+    let index = builder.finish().map_err(|e| CliError::Io(format!("could not sort the phone numbers of {}: {}", filename, e)))?;
+    Ok(SuccinctDataset { index, rows, input })
+}
+
+/// Turns an error reading a file into a usage error if it is a table without the column in its header,
+/// or an I/O error otherwise
+fn read_error(filename: &str, error: io::Error) -> CliError {
+    match error.kind() {
+        io::ErrorKind::InvalidInput => CliError::Usage(format!("could not read {}: {}", filename, error)),
+        _ => CliError::Io(format!("could not read {}: {}", filename, error)),
     }
+}
 
-    /// This function is used to test the kth-nearest function
-    /// on a random set of 10 digit numbers.
-    /// The test will run 1000 times and will print the progress
-    /// of the test.
-    #[test]
-    fn test_kth_nearest_random() {
-        (0..1000).progress_count(1000).for_each(|_| {
-            let max_length = 10000;
-            let min_number = 1000000000;
-            let max_number = 1999999999;
-            let seed = rand::thread_rng().gen_range(0..1<<32);
-            let mut rng = ChaChaRng::seed_from_u64(seed);
-            let numbers: Vec<i64> = (0..max_length).map(|_| rng.gen_range(0..max_number)).collect();
-            let target = rng.gen_range(min_number..=max_number);
-            let k = rng.gen_range(1..=max_length);
-            let mut our_result = kth_nearest(k, target, numbers.clone());
-            let mut naive_result = naive_sorting_find_kth_nearest(k as u64, target, numbers);
-            our_result.sort();
-            naive_result.sort();
-            assert_eq!(our_result, naive_result);
-        });
+/// Warns on stderr about the lines of a file that are not phone numbers or not valid UTF-8
+fn warn_skipped(filename: &str, records: &Records) {
+    if let Some(lines) = describe_rejected(&records.invalid_utf8) {
+        eprintln!("warning: read lines in {} that are not valid UTF-8, on {}", filename, lines);
+    }
+    if let Some(lines) = describe_rejected(&records.rejected) {
+        eprintln!("warning: skipped records in {} that are not phone numbers, on {}", filename, lines);
     }
+}
+
+/// Groups the rows of a table by their phone number, in the order they appear
+/// # Arguments
+/// * `numbers` - The phone numbers, as read by `read_phone_numbers`
+/// * `rows` - The rest of the row of each phone number, which is empty if the file is not a table
+fn rows_by_number(numbers: &[i64], rows: Vec<String>) -> HashMap<i64, VecDeque<String>> {
+    let mut grouped: HashMap<i64, VecDeque<String>> = HashMap::new();
+    numbers.iter().zip(rows).for_each(|(number, row)| grouped.entry(*number).or_default().push_back(row));
+    grouped
+}
 
-    /// This function generates a vector of random 10 digit numbers.
-    /// # Arguments
-    /// * `num_numbers` - the number of numbers to generate
-    /// * `min_value` - the minimum value of the numbers
-    /// * `max_value` - the maximum value of the numbers
-    /// * `seed` - the seed for the random number generator
-    /// # Returns
-    /// * `Vec<i64>` - a vector of random 10 digit numbers
-    /// # Example
-    /// ```rust
-    /// let numbers = generate_valid_phone_numbers(100, 1000000000, 1999999999, 0);
-    /// ```
-    fn generate_valid_phone_numbers(num_numbers: usize, min_value: u64, max_value: u64, seed: u64) -> Vec<i64> {
-        let mut rng = ChaChaRng::seed_from_u64(seed);
-        let mut numbers: Vec<i64> = Vec::new();
-        for _ in 0..num_numbers {
-            let number = rng.gen_range(min_value..=max_value);
-            numbers.push(number as i64);
+/// Prints each number on its own line. When the file is a table, each time a number is printed
+/// it is followed by a tab and the next row it was found in.
+/// # Arguments
+/// * `numbers` - The numbers to print
+/// * `rows` - The rows of each number, as grouped by `rows_by_number`
+/// # Returns
+/// * `bool` - Whether there were any numbers to print
+fn print_numbers(numbers: &[i64], mut rows: HashMap<i64, VecDeque<String>>) -> bool {
+    for number in numbers {
+        match rows.get_mut(number).and_then(|rows| rows.pop_front()) {
+            Some(row) => println!("{}\t{}", number, row),
+            None => println!("{}", number),
         }
-        numbers
     }
+    !numbers.is_empty()
+}
+
+/// A phone number found by a nearest or radius query, with the rows of the table it was found in
+#[derive(Debug, Serialize)]
+struct NeighbourOutput {
+    #[serde(flatten)]
+    neighbour: Neighbour,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rows: Vec<String>,
+}
+
+/// A phone number matching a pattern, with the number of times it appears
+#[derive(Debug, Serialize)]
+struct PatternMatch {
+    number: i64,
+    count: usize,
+}
 
-    /// This function is used to test the kth-nearest function against
-    /// the naive sorting algorithm.
-    /// # Arguments
-    /// * `max_length` - the maximum length of the vector to test
-    /// * `seed` - the seed for the random number generator
-    /// # Returns
-    /// * `Vec<(usize, Duration, Duration)>` - a vector of tuples containing the length of the vector,
-    /// the time it took to run the naive sorting algorithm, and the time it took to run the btreemap implementation
-    /// # Example
-    /// ```rust
-    /// let results = _compare_naive_sort_and_btreemap(100, 0);
-    /// ```
-    fn _compare_naive_sort_and_btreemap(max_length: usize, seed: u64) -> Vec<(usize, Duration, Duration)> {
-        let style = ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}")
-            .unwrap()
-            .progress_chars("##-");
-        let results = (1..=max_length)
-            .into_par_iter()
-            .progress_count(max_length as u64)
-            .with_style(style)
-            .map(|length| {
-                let numbers = generate_valid_phone_numbers(length, 1000000000, 9999999999, seed);
-                let mut rng = ChaChaRng::seed_from_u64(seed);
-                let target = rng.gen_range(1000000000..=9999999999); // generate valid phone number target
-                let k = rng.gen_range(1..=length);
-                let durations = compare_naive_sort_and_btreemap(5, numbers, target, k as u64);
-                (length, durations.0, durations.1)
-            }).collect();
-        results
+/// A phone number extracted from free text, with the text it was found in
+#[derive(Debug, Serialize)]
+struct ExtractedNumber<'a> {
+    #[serde(flatten)]
+    found: PhoneMatch,
+    text: &'a str,
+}
+
+/// Prints the results of a nearest or radius query. As text, each number is printed the way
+/// `print_numbers` does. Otherwise, repeated numbers are grouped with their count and distance
+/// to the target.
+/// # Arguments
+/// * `output` - How to print the results
+/// * `input` - How many records were read and rejected
+/// * `query` - The parameters of the query
+/// * `metric` - The metric the distances are measured with
+/// * `target` - The target number
+/// * `numbers` - The numbers found, with repeated numbers next to each other
+/// * `rows` - The rows of each number, as grouped by `rows_by_number`
+/// # Returns
+/// * `Result<bool, CliError>` - Whether any numbers were found
+fn print_neighbours(output: OutputFormat, input: InputStats, query: serde_json::Value, metric: Metric, target: i64, numbers: &[i64], mut rows: HashMap<i64, VecDeque<String>>) -> Result<bool, CliError> {
+    if output == OutputFormat::Text {
+        return Ok(print_numbers(numbers, rows));
     }
+    let results: Vec<NeighbourOutput> = numbers
+        .chunk_by(|a, b| a == b)
+        .map(|group| NeighbourOutput {
+            neighbour: Neighbour { distance: metric.distance(target, group[0]), number: group[0], count: group.len() },
+            rows: rows.remove(&group[0]).map(Vec::from).unwrap_or_default(),
+        })
+        .collect();
+    print_report(output, Report { input, query, name: "neighbours", kind: "neighbour", results })?;
+    Ok(!numbers.is_empty())
+}
 
-    /// This function is used to test the kth-nearest function against
-    /// the naive sorting algorithm.
-    /// # Arguments
-    /// * `num_times` - the number of times to run the test and take the average for
-    /// * `numbers` - the vector of numbers to test
-    /// * `target` - the target number
-    /// * `k` - the kth nearest number to find
-    /// # Returns
-    /// * `(Duration, Duration)` - a tuple containing the time it took to run the naive sorting algorithm,
-    /// and the time it took to run the btreemap implementation
-    /// # Example
-    /// ```rust
-    /// let durations = compare_naive_sort_and_btreemap(5, numbers, target, k as u64);
-    /// ```
-    fn compare_naive_sort_and_btreemap(num_times: u32, numbers: Vec<i64>, target: i64, k: u64) -> (Duration, Duration) {
-        let mut sum_naive_duration = Duration::new(0, 0);
-        let mut sum_btreemap_duration = Duration::new(0, 0);
-        for _ in 0..num_times {
-            let numbers_clone_naive = numbers.clone();
-            let numbers_clone_btreemap = numbers.clone();
-            let naive_start = Instant::now();
-            let mut naive_result = naive_sorting_find_kth_nearest(k, target, numbers_clone_naive);
-            let naive_end = Instant::now();
-            let naive_duration = naive_end.duration_since(naive_start);
-            sum_naive_duration += naive_duration;
-            let btreemap_start = Instant::now();
-            let mut btreemap_result = kth_nearest(k as i64, target, numbers_clone_btreemap);
-            let btreemap_end = Instant::now();
-            let btreemap_duration = btreemap_end.duration_since(btreemap_start);
-            sum_btreemap_duration += btreemap_duration;
-            naive_result.sort();
-            btreemap_result.sort();
-            // assert_eq!(naive_result, btreemap_result);
+/// Writes a report to stdout in a structured format
+/// # Errors
+/// This function will return an I/O error if stdout cannot be written
+fn print_report<T: Serialize>(output: OutputFormat, report: Report<T>) -> Result<(), CliError> {
+    let mut stdout = io::stdout().lock();
+    write_report(&mut stdout, output, &report)
+        .and_then(|_| stdout.flush())
+        .map_err(|e| CliError::Io(format!("could not write the results: {}", e)))
+}
+
+/// Reads commands from stdin and runs them against the index, printing one line per command.
+/// Changes print the new count of the number, or the number of occurrences removed for `remove`,
+/// and queries print the numbers found separated by spaces.
+/// # Arguments
+/// * `index` - The index to run the commands against
+/// # Errors
+/// This function will return an error if stdin cannot be read
+fn run_interactive(mut index: NearestIndex) -> io::Result<()> {
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match run_command(&mut index, &words) {
+            Ok(output) => println!("{}", output),
+            Err(message) => println!("error: {}", message),
         }
-        let avg_naive_duration = sum_naive_duration / num_times;
-        let avg_btreemap_duration = sum_btreemap_duration / num_times;
-        (avg_naive_duration, avg_btreemap_duration)
     }
+    Ok(())
+}
 
-    /// This test compares the naive sorting implementation with the btreemap implementation.
-    /// It generates a csv file with the results.
-    /// The csv file can be opened with a spreadsheet program like LibreOffice Calc.
-    /// The first column is the length of the input array.
-    /// The second column is the average duration of the naive sorting implementation in nanoseconds.
-    /// The third column is the average duration of the btreemap implementation in nanoseconds.
-    /// # Example
-    /// ```ignore
-    /// $ cargo test --release -- --nocapture compare_naive_and_our_implementation
-    /// ```
-    #[test]
-    fn compare_naive_and_our_implementation() {
-        let max_length = 50000;
-        let seed = 42;
-        let mut file = match std::fs::File::create("results.csv") {
-            Ok(file) => file,
-            Err(e) => panic!("Error creating file: {}", e),
-        };
-        
-        let results = _compare_naive_sort_and_btreemap(max_length, seed);
-        results.iter().for_each(|(length, naive_duration, btreemap_duration)| {
-            let naive_duration_millis = naive_duration.as_nanos();
-            let btreemap_duration_millis = btreemap_duration.as_nanos();
-            let _ = writeln!(file, "{},{},{}", length, naive_duration_millis, btreemap_duration_millis);
-            // println!("{},{},{}", length, naive_duration_millis, btreemap_duration_millis)
-        });
+/// Runs a single interactive command against the index
+/// # Arguments
+/// * `index` - The index to run the command against
+/// * `words` - The command and its arguments
+/// # Returns
+/// * `Result<String, String>` - The line to print, or an error message
+fn run_command(index: &mut NearestIndex, words: &[&str]) -> Result<String, String> {
+    let number = |position: usize| -> Result<i64, String> {
+        let word = words.get(position).ok_or("missing phone number")?;
+        Ok(phone_number_sanitizer(word)?)
+    };
+    let amount = |position: usize| -> Result<u64, String> {
+        let word = words.get(position).ok_or("missing k or radius")?;
+        word.parse::<u64>().map_err(|e| e.to_string())
+    };
+    let format_numbers = |numbers: Vec<i64>| numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" ");
+    match words.first().copied() {
+        Some("insert") => index.insert(number(1)?).map(|count| count.to_string()).map_err(|e| e.to_string()),
+        Some("remove") => index.remove(number(1)?).map(|count| count.to_string()).map_err(|e| e.to_string()),
+        Some("decrement") => index.decrement(number(1)?).map(|count| count.to_string()).map_err(|e| e.to_string()),
+        Some("nearest") => Ok(format_numbers(expand_neighbours(&index.nearest(number(1)?, amount(2)? as usize)))),
+        Some("radius") => Ok(format_numbers(expand_neighbours(&index.within(number(1)?, amount(2)?)))),
+        _ => Err("commands are insert, remove, decrement, nearest and radius".to_owned()),
     }
-}
\ No newline at end of file
+}
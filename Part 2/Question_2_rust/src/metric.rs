@@ -0,0 +1,194 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The number of digits in a sanitized phone number
+pub const DIGITS: usize = 10;
+
+/// The distance metrics that can be used to compare two phone numbers.
+/// * `Absolute` - the numeric absolute difference between the two numbers
+/// * `Hamming` - the number of positions at which the 10 digits differ
+/// * `DamerauLevenshtein` - the minimum number of insertions, deletions, substitutions
+///   and transpositions of adjacent digits needed to turn one number into the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Absolute,
+    Hamming,
+    DamerauLevenshtein,
+}
+
+impl Metric {
+    /// Calculates the distance between two phone numbers under this metric
+    /// # Arguments
+    /// * `a` - the first phone number
+    /// * `b` - the second phone number
+    /// # Returns
+    /// * `u64` - the distance between the two numbers
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::metric::Metric;
+    /// assert_eq!(Metric::Absolute.distance(7327325555, 7327325556), 1);
+    /// assert_eq!(Metric::Hamming.distance(7327325555, 1327325555), 1);
+    /// assert_eq!(Metric::DamerauLevenshtein.distance(7327325555, 3727325555), 1);
+    /// ```
+    pub fn distance(&self, a: i64, b: i64) -> u64 {
+        match self {
+            Metric::Absolute => a.abs_diff(b),
+            Metric::Hamming => hamming_distance(&to_digits(a), &to_digits(b)),
+            Metric::DamerauLevenshtein => damerau_levenshtein_distance(&to_digits(a), &to_digits(b)),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "absolute" => Ok(Metric::Absolute),
+            "hamming" => Ok(Metric::Hamming),
+            "damerau-levenshtein" | "damerau" => Ok(Metric::DamerauLevenshtein),
+            _ => Err("Metric must be one of absolute, hamming or damerau-levenshtein"),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Metric::Absolute => write!(f, "absolute"),
+            Metric::Hamming => write!(f, "hamming"),
+            Metric::DamerauLevenshtein => write!(f, "damerau-levenshtein"),
+        }
+    }
+}
+
+/// Splits a phone number into its last 10 digits, padding with leading zeroes
+/// # Arguments
+/// * `number` - the phone number to split
+/// # Returns
+/// * `[u8; DIGITS]` - the digits of the number, most significant first
+/// # Example
+/// ```rust
+/// use question_2_rust::metric::to_digits;
+/// assert_eq!(to_digits(1234567890), [1, 2, 3, 4, 5, 6, 7, 8, 9, 0]);
+/// ```
+pub fn to_digits(number: i64) -> [u8; DIGITS] {
+    let mut digits = [0; DIGITS];
+    let mut remaining = number.unsigned_abs();
+    for digit in digits.iter_mut().rev() {
+        *digit = (remaining % 10) as u8;
+        remaining /= 10;
+    }
+    digits
+}
+
+/// Counts the number of positions at which two digit strings differ
+/// # Arguments
+/// * `a` - the digits of the first number
+/// * `b` - the digits of the second number
+/// # Returns
+/// * `u64` - the Hamming distance between the two digit strings
+fn hamming_distance(a: &[u8; DIGITS], b: &[u8; DIGITS]) -> u64 {
+    a.iter().zip(b.iter()).filter(|(x, y)| x != y).count() as u64
+}
+
+/// Calculates the (unrestricted) Damerau-Levenshtein distance between two digit strings.
+/// Unlike the optimal string alignment variant, this satisfies the triangle inequality,
+/// which the BK-tree relies on to prune its search.
+/// # Arguments
+/// * `a` - the digits of the first number
+/// * `b` - the digits of the second number
+/// # Returns
+/// * `u64` - the Damerau-Levenshtein distance between the two digit strings
+fn damerau_levenshtein_distance(a: &[u8; DIGITS], b: &[u8; DIGITS]) -> u64 {
+    let max_distance = 2 * DIGITS;
+    // the last row each digit was seen in
+    let mut last_row = [0; 10];
+    // the table is offset by one so that row and column 0 can hold the maximum distance
+    let mut table = [[0; DIGITS + 2]; DIGITS + 2];
+    table[0][0] = max_distance;
+    for i in 0..=DIGITS {
+        table[i + 1][0] = max_distance;
+        table[i + 1][1] = i;
+        table[0][i + 1] = max_distance;
+        table[1][i + 1] = i;
+    }
+    for i in 1..=DIGITS {
+        // the last column in this row where the digits matched
+        let mut last_match_column = 0;
+        for j in 1..=DIGITS {
+            let k = last_row[b[j - 1] as usize];
+            let l = last_match_column;
+            let cost = if a[i - 1] == b[j - 1] {
+                last_match_column = j;
+                0
+            } else {
+                1
+            };
+            let substitution = table[i][j] + cost;
+            let insertion = table[i + 1][j] + 1;
+            let deletion = table[i][j + 1] + 1;
+            let transposition = table[k][l] + (i - k - 1) + 1 + (j - l - 1);
+            table[i + 1][j + 1] = substitution.min(insertion).min(deletion).min(transposition);
+        }
+        last_row[a[i - 1] as usize] = i;
+    }
+    table[DIGITS + 1][DIGITS + 1] as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// This test checks the distances between a number and a few common typos of it
+    #[test]
+    fn test_metric_typos() {
+        let number = 7327325555;
+        // one digit off at the end
+        assert_eq!(Metric::Absolute.distance(number, 7327325556), 1);
+        assert_eq!(Metric::Hamming.distance(number, 7327325556), 1);
+        assert_eq!(Metric::DamerauLevenshtein.distance(number, 7327325556), 1);
+        // one digit off in the area code
+        assert_eq!(Metric::Absolute.distance(number, 1327325555), 6000000000);
+        assert_eq!(Metric::Hamming.distance(number, 1327325555), 1);
+        assert_eq!(Metric::DamerauLevenshtein.distance(number, 1327325555), 1);
+        // two adjacent digits swapped
+        assert_eq!(Metric::Hamming.distance(number, 3727325555), 2);
+        assert_eq!(Metric::DamerauLevenshtein.distance(number, 3727325555), 1);
+        // a digit dropped and another one appended
+        assert_eq!(Metric::Hamming.distance(number, 3273255551), 7);
+        assert_eq!(Metric::DamerauLevenshtein.distance(number, 3273255551), 2);
+    }
+
+    /// This test checks that the Damerau-Levenshtein distance is symmetric and
+    /// satisfies the triangle inequality for a range of numbers
+    #[test]
+    fn test_damerau_levenshtein_is_metric() {
+        let numbers = [
+            1234567890, 2134567890, 1234567809, 9876543210, 1111111111,
+            1211111111, 2143658709, 1234567890 / 10, 7327325555, 3727325555,
+        ];
+        for a in numbers {
+            assert_eq!(Metric::DamerauLevenshtein.distance(a, a), 0);
+            for b in numbers {
+                let ab = Metric::DamerauLevenshtein.distance(a, b);
+                assert_eq!(ab, Metric::DamerauLevenshtein.distance(b, a));
+                for c in numbers {
+                    let ac = Metric::DamerauLevenshtein.distance(a, c);
+                    let cb = Metric::DamerauLevenshtein.distance(c, b);
+                    assert!(ab <= ac + cb, "{} {} {}", a, b, c);
+                }
+            }
+        }
+    }
+
+    /// This test checks that metrics can be parsed from their names
+    #[test]
+    fn test_metric_from_str() {
+        for metric in [Metric::Absolute, Metric::Hamming, Metric::DamerauLevenshtein] {
+            assert_eq!(metric.to_string().parse::<Metric>(), Ok(metric));
+        }
+        assert_eq!("damerau".parse::<Metric>(), Ok(Metric::DamerauLevenshtein));
+        assert!("euclidean".parse::<Metric>().is_err());
+    }
+}
@@ -0,0 +1,204 @@
+//! The k nearest and radius queries by absolute difference that the binary answers by default,
+//! searching every unique phone number the same way as `task2.py`.
+
+use std::collections::BTreeMap;
+
+use crate::PhoneCounts;
+
+/// Returns the k nearest numbers to the target number
+/// while accounting for duplicates.
+/// The numbers are ordered by their distance to the target, and numbers at the same distance
+/// are ordered by where they first appear in the list, the same way as `task2.py`.
+/// Nothing is returned if k is not positive.
+/// # Arguments
+/// * `k` - The number of nearest numbers to return
+/// * `target` - The target number
+/// * `counts` - The numbers to search, counted in the order they first appear
+/// # Example
+/// ```rust
+/// use question_2_rust::{nearest::kth_nearest, PhoneCounts};
+/// let counts = PhoneCounts::from_numbers(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+/// let result = kth_nearest(3, 5, &counts);
+/// assert_eq!(result, vec![5, 4, 6]);
+/// ```
+pub fn kth_nearest(k: i64, target: i64, counts: &PhoneCounts) -> Vec<i64> {
+    // calculate the differences between each number and the target number,
+    // and store the numbers that have the same difference in a BTreeMap
+    let mut differences : BTreeMap<u64, Vec<i64>> = BTreeMap::new();
+    // go through the unique numbers in the order they first appear in the list
+    for (number, _) in counts.iter() {
+        // the difference of two numbers far apart does not fit in an i64, but always fits in a u64
+        let abs_distance = number.abs_diff(target);
+        let difference_count = differences.entry(abs_distance).or_default();
+        difference_count.push(number);
+    }
+    let mut result: Vec<i64> = Vec::new();
+    if k <= 0 {
+        return result;
+    }
+    let mut count = 0;
+    for (_, numbers) in differences.iter().take(k as usize) { // take only k keys from the BTreeMap
+        let mut numbers_to_add: Vec<i64> = Vec::new();
+        for number in numbers {
+            count += 1;
+            let num_times = counts.count(*number);
+            numbers_to_add.append(&mut vec![*number; num_times]);
+        }
+        result.append(&mut numbers_to_add);
+        if count >= k as usize {
+            // we have enough numbers, so we can stop
+            break;
+        }
+    }
+    result
+}
+
+/// Returns every number within the radius of the target number
+/// while accounting for duplicates, sorted by distance then by number
+/// # Arguments
+/// * `radius` - The maximum absolute difference from the target, inclusive
+/// * `target` - The target number
+/// * `counts` - The numbers to search, counted
+/// # Example
+/// ```rust
+/// use question_2_rust::{nearest::within_radius, PhoneCounts};
+/// let counts = PhoneCounts::from_numbers(&[1, 2, 3, 3, 4, 5, 6, 7, 8, 9, 10]);
+/// let result = within_radius(1, 3, &counts);
+/// assert_eq!(result, vec![3, 3, 2, 4]);
+/// ```
+pub fn within_radius(radius: u64, target: i64, counts: &PhoneCounts) -> Vec<i64> {
+    let mut in_range: Vec<(i64, usize)> = counts.iter().filter(|(number, _)| number.abs_diff(target) <= radius).collect();
+    in_range.sort_by_key(|(number, _)| (number.abs_diff(target), *number));
+    in_range
+        .iter()
+        .flat_map(|(number, count)| vec![*number; *count])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::baseline::naive_sorting_find_kth_nearest;
+    use indicatif::ProgressIterator;
+    use proptest::prelude::*;
+    use proptest::test_runner::FileFailurePersistence;
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+    /// This test will test the kth-nearest function
+    /// on a predefined set of numbers
+    #[test]
+    fn test_kth_nearest_pre_defined() {
+        let numbers = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let result = kth_nearest(4, 5, &PhoneCounts::from_numbers(&numbers));
+        assert_eq!(result, vec![5, 4, 6, 3, 7]);
+    }
+
+    /// This test checks that numbers at the same distance are returned in the order
+    /// they first appear, with every duplicate, the same way as `task2.py`
+    #[test]
+    fn test_kth_nearest_tie_order() {
+        let numbers = vec![11, 1, 9, 11, 1, 10, 9];
+        assert_eq!(kth_nearest(1, 10, &PhoneCounts::from_numbers(&numbers)), vec![10]);
+        assert_eq!(kth_nearest(2, 10, &PhoneCounts::from_numbers(&numbers)), vec![10, 11, 11, 9, 9]);
+        assert_eq!(kth_nearest(3, 10, &PhoneCounts::from_numbers(&numbers)), vec![10, 11, 11, 9, 9]);
+        assert_eq!(kth_nearest(4, 10, &PhoneCounts::from_numbers(&numbers)), vec![10, 11, 11, 9, 9, 1, 1]);
+        assert_eq!(kth_nearest(0, 10, &PhoneCounts::from_numbers(&numbers)), Vec::<i64>::new());
+        assert_eq!(kth_nearest(-1, 10, &PhoneCounts::from_numbers(&numbers)), Vec::<i64>::new());
+        // numbers so far apart that their difference does not fit in an i64
+        assert_eq!(kth_nearest(1, i64::MAX, &PhoneCounts::from_numbers(&[i64::MIN, 0])), vec![0]);
+    }
+
+    /// This test will test the within-radius function
+    /// on a predefined set of numbers with duplicates
+    #[test]
+    fn test_within_radius_pre_defined() {
+        let numbers = vec![1, 2, 3, 3, 4, 5, 6, 7, 8, 9, 10];
+        assert_eq!(within_radius(1, 3, &PhoneCounts::from_numbers(&numbers)), vec![3, 3, 2, 4]);
+        assert_eq!(within_radius(0, 11, &PhoneCounts::from_numbers(&numbers)), Vec::<i64>::new());
+        assert_eq!(within_radius(u64::MAX, 1, &PhoneCounts::from_numbers(&numbers)).len(), 11);
+        assert_eq!(within_radius(u64::MAX, i64::MIN, &PhoneCounts::from_numbers(&[i64::MAX])), vec![i64::MAX]);
+    }
+
+    /// The seed of the random tests, which is fixed so that every run tests the same inputs,
+    /// unless another one is given in the `TEST_SEED` environment variable
+    fn test_seed(default: u64) -> u64 {
+        std::env::var("TEST_SEED").ok().and_then(|seed| seed.parse().ok()).unwrap_or(default)
+    }
+
+    /// This function is used to test the kth-nearest function
+    /// on a random set of 10 digit numbers.
+    /// The test will run 1000 times and will print the progress
+    /// of the test.
+    #[test]
+    fn test_kth_nearest_random() {
+        let seed = test_seed(46);
+        let mut rng = ChaChaRng::seed_from_u64(seed);
+        (0..1000).progress_count(1000).for_each(|attempt| {
+            let max_length = 10000;
+            let min_number = 1000000000;
+            let max_number = 1999999999;
+            let numbers: Vec<i64> = (0..max_length).map(|_| rng.gen_range(0..max_number)).collect();
+            let target = rng.gen_range(min_number..=max_number);
+            let k = rng.gen_range(1..=max_length);
+            let mut our_result = kth_nearest(k, target, &PhoneCounts::from_numbers(&numbers));
+            let mut naive_result = naive_sorting_find_kth_nearest(k as u64, target, numbers);
+            our_result.sort();
+            naive_result.sort();
+            assert_eq!(our_result, naive_result, "seed {}, attempt {}", seed, attempt);
+        });
+    }
+
+    /// The k nearest numbers under the tie policy of `kth_nearest`, found without a BTreeMap:
+    /// the unique numbers are sorted by distance keeping the order they first appear in,
+    /// then whole distances are taken until there are at least k of them, and each is repeated by its count
+    fn kth_nearest_by_sorting(k: usize, target: i64, numbers: &[i64]) -> Vec<i64> {
+        let mut unique: Vec<i64> = Vec::new();
+        for number in numbers {
+            if !unique.contains(number) {
+                unique.push(*number);
+            }
+        }
+        unique.sort_by_key(|number| number.abs_diff(target));
+        let mut taken = k.min(unique.len());
+        while taken > 0 && taken < unique.len() && unique[taken].abs_diff(target) == unique[taken - 1].abs_diff(target) {
+            taken += 1;
+        }
+        unique[..taken]
+            .iter()
+            .flat_map(|number| numbers.iter().filter(move |other| *other == number))
+            .copied()
+            .collect()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            failure_persistence: Some(Box::new(FileFailurePersistence::SourceParallel("proptest-regressions"))),
+            ..ProptestConfig::default()
+        })]
+
+        /// `kth_nearest` returns exactly the k nearest unique numbers with every one of their duplicates,
+        /// along with every other number at the same distance as the k-th, in the order they first appear.
+        /// Numbers are drawn from a small range around the target so that there are many ties and duplicates.
+        #[test]
+        fn prop_kth_nearest_matches_sorting(
+            numbers in prop::collection::vec(7_327_325_540..7_327_325_560_i64, 0..60),
+            target in 7_327_325_540..7_327_325_560_i64,
+            k in 1..70_usize,
+        ) {
+            let result = kth_nearest(k as i64, target, &PhoneCounts::from_numbers(&numbers));
+            prop_assert_eq!(result, kth_nearest_by_sorting(k, target, &numbers));
+        }
+
+        /// The same as `prop_kth_nearest_matches_sorting`, over any 10 digit numbers
+        #[test]
+        fn prop_kth_nearest_any_numbers(
+            numbers in prop::collection::vec(1_000_000_000..10_000_000_000_i64, 0..60),
+            target in 1_000_000_000..10_000_000_000_i64,
+            k in 1..70_usize,
+        ) {
+            let result = kth_nearest(k as i64, target, &PhoneCounts::from_numbers(&numbers));
+            prop_assert_eq!(result, kth_nearest_by_sorting(k, target, &numbers));
+        }
+    }
+}
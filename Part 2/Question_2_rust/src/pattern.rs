@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::metric::{DIGITS, to_digits};
+
+/// A phone number pattern made of digits and `?` wildcards.
+/// Patterns shorter than 10 digits match every number that starts with them,
+/// so `732732` matches everything from 7327320000 to 7327329999.
+/// Separators such as dashes, spaces, dots and parentheses are ignored, and a
+/// trailing `*` may be used to make a prefix match explicit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    digits: Vec<Option<u8>>,
+}
+
+impl Pattern {
+    /// Checks whether a phone number matches the pattern
+    /// # Arguments
+    /// * `number` - the phone number to check
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::pattern::Pattern;
+    /// let pattern: Pattern = "732-73?-55??".parse().unwrap();
+    /// assert!(pattern.matches(7327325555));
+    /// assert!(!pattern.matches(7327325455));
+    /// ```
+    pub fn matches(&self, number: i64) -> bool {
+        self.digits
+            .iter()
+            .zip(to_digits(number).iter())
+            .all(|(expected, digit)| expected.is_none_or(|expected| expected == *digit))
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.strip_suffix('*').unwrap_or(s);
+        let mut digits = Vec::new();
+        for c in s.chars() {
+            match c {
+                '0'..='9' => digits.push(c.to_digit(10).map(|digit| digit as u8)),
+                '?' => digits.push(None),
+                '-' | ' ' | '.' | '(' | ')' => {}
+                _ => return Err("Pattern may only contain digits, `?` wildcards and separators"),
+            }
+        }
+        if digits.len() > DIGITS {
+            return Err("Pattern must be at most 10 digits long");
+        }
+        Ok(Pattern { digits })
+    }
+}
+
+impl fmt::Display for Pattern {
+    /// Writes the pattern without separators, ending with `*` if it matches a prefix
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for digit in self.digits.iter() {
+            match digit {
+                Some(digit) => write!(f, "{}", digit)?,
+                None => write!(f, "?")?,
+            }
+        }
+        if self.digits.len() < DIGITS {
+            write!(f, "*")?;
+        }
+        Ok(())
+    }
+}
+
+/// The deduplicated phone numbers kept in sorted order so that a pattern can
+/// be answered by narrowing down ranges with binary searches, in the same way
+/// that a digit trie would be walked, without having to store the trie.
+#[derive(Debug, Clone, Default)]
+pub struct PatternIndex {
+    entries: Vec<(i64, usize)>,
+}
+
+impl PatternIndex {
+    /// Creates the index from the counts of each phone number
+    /// # Arguments
+    /// * `counts` - the number of times each phone number appeared, as built by `count_numbers`
+    pub fn from_counts<S>(counts: &HashMap<i64, usize, S>) -> Self {
+        let mut entries: Vec<(i64, usize)> = counts.iter().map(|(number, count)| (*number, *count)).collect();
+        entries.sort_unstable();
+        PatternIndex { entries }
+    }
+
+    /// Finds every phone number matching the pattern
+    /// # Arguments
+    /// * `pattern` - the pattern to match
+    /// # Returns
+    /// * `Vec<(i64, usize)>` - the matching phone numbers in ascending order, with their counts
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::{count_numbers, pattern::PatternIndex};
+    /// let counts = count_numbers(&[7327325555, 7327325555, 7327329999, 7337325555]);
+    /// let index = PatternIndex::from_counts(&counts);
+    /// assert_eq!(index.find(&"732732".parse().unwrap()), vec![(7327325555, 2), (7327329999, 1)]);
+    /// assert_eq!(index.find(&"73?7325555".parse().unwrap()), vec![(7327325555, 2), (7337325555, 1)]);
+    /// ```
+    pub fn find(&self, pattern: &Pattern) -> Vec<(i64, usize)> {
+        let mut result = Vec::new();
+        self.find_in_range(&self.entries, 0, 0, pattern, &mut result);
+        result
+    }
+
+    /// Narrows down the entries that start with `prefix` one digit of the pattern at a time
+    /// # Arguments
+    /// * `entries` - the sorted entries that start with `prefix`
+    /// * `prefix` - the digits matched so far
+    /// * `depth` - the number of digits matched so far
+    /// * `pattern` - the pattern to match
+    /// * `result` - where the matching entries are collected
+    fn find_in_range(&self, entries: &[(i64, usize)], prefix: i64, depth: usize, pattern: &Pattern, result: &mut Vec<(i64, usize)>) {
+        if entries.is_empty() {
+            return;
+        }
+        if depth == pattern.digits.len() {
+            // every remaining digit is free, so the whole range matches
+            result.extend_from_slice(entries);
+            return;
+        }
+        let candidates = match pattern.digits[depth] {
+            Some(digit) => digit..digit + 1,
+            None => 0..10,
+        };
+        for digit in candidates {
+            let next_prefix = prefix * 10 + digit as i64;
+            let width = 10_i64.pow((DIGITS - depth - 1) as u32);
+            let start = entries.partition_point(|(number, _)| *number < next_prefix * width);
+            let end = entries.partition_point(|(number, _)| *number < (next_prefix + 1) * width);
+            self.find_in_range(&entries[start..end], next_prefix, depth + 1, pattern, result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_numbers;
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+    /// This test checks that patterns are parsed with separators ignored
+    #[test]
+    fn test_pattern_parsing() {
+        let pattern: Pattern = "(732) 73?-55??".parse().unwrap();
+        assert_eq!(pattern, "73273?55??".parse().unwrap());
+        assert_eq!("732732*".parse::<Pattern>(), "732732".parse());
+        assert!("73273255555".parse::<Pattern>().is_err());
+        assert!("732a".parse::<Pattern>().is_err());
+        assert!("".parse::<Pattern>().unwrap().matches(1234567890));
+        assert_eq!(pattern.to_string(), "73273?55??");
+        assert_eq!("732-732".parse::<Pattern>().unwrap().to_string(), "732732*");
+    }
+
+    /// This test checks the pattern index against filtering every number with the pattern
+    #[test]
+    fn test_pattern_index_against_filter() {
+        let patterns = ["732", "7?2", "??????5555", "1?3?5?7?9?", "73273255", "0", "9999999999", ""];
+        for seed in 0..10 {
+            let mut rng = ChaChaRng::seed_from_u64(seed);
+            // keep the numbers in a narrow band so that the patterns match some of them
+            let numbers: Vec<i64> = (0..5000).map(|_| rng.gen_range(7000000000..7400000000) / 11 * 11).collect();
+            let counts = count_numbers(&numbers);
+            let index = PatternIndex::from_counts(&counts);
+            for pattern in patterns {
+                let pattern: Pattern = pattern.parse().unwrap();
+                let mut expected: Vec<(i64, usize)> = counts
+                    .iter()
+                    .filter(|(number, _)| pattern.matches(**number))
+                    .map(|(number, count)| (*number, *count))
+                    .collect();
+                expected.sort();
+                assert_eq!(index.find(&pattern), expected);
+            }
+        }
+    }
+}
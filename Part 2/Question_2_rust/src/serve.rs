@@ -0,0 +1,382 @@
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::binary::{is_binary_file, BinaryDataset};
+use crate::compress;
+use crate::nearest::{kth_nearest, within_radius};
+use crate::records::{read_records, Layout};
+use crate::{phone_number_sanitizer, Neighbour, PhoneCounts};
+
+/// The dataset currently being served, along with what the file looked like when it was loaded.
+/// The numbers are counted in the order they first appeared, so queries are answered the same way as the binary.
+#[derive(Debug)]
+struct Dataset {
+    counts: PhoneCounts,
+    median: Option<(i64, Option<i64>)>,
+    modified: Option<(SystemTime, u64)>,
+}
+
+impl Dataset {
+    /// Reads and indexes the phone numbers in a file, which can be a binary dataset
+    /// # Arguments
+    /// * `path` - the path of the file to read
+    /// * `layout` - how the phone numbers are laid out in the file
+    fn load(path: &Path, layout: &Layout) -> io::Result<Self> {
+        let modified = file_version(path);
+        if is_binary_file(path)? {
+            let dataset = BinaryDataset::open(path)?;
+            return Ok(Dataset { counts: dataset.to_counts(), median: dataset.median(), modified });
+        }
+        let mut phone_numbers = read_records(compress::open(path)?, layout)?.numbers;
+        let counts = PhoneCounts::from_numbers(&phone_numbers);
+        Ok(Dataset { counts, median: median_values(&mut phone_numbers), modified })
+    }
+}
+
+/// Finds the median value(s) of the phone numbers with quick select, the same way as `find_median_values` in Task 1
+/// # Arguments
+/// * `numbers` - the phone numbers, which are reordered
+/// # Returns
+/// * `Option<(i64, Option<i64>)>` - the median, and the upper median if there is an even number
+///   of phone numbers, or None if there are no phone numbers
+fn median_values(numbers: &mut [i64]) -> Option<(i64, Option<i64>)> {
+    let length = numbers.len();
+    match length {
+        0 => None,
+        _ if length % 2 == 0 => {
+            let median = *numbers.select_nth_unstable(length / 2 - 1).1;
+            Some((median, Some(*numbers.select_nth_unstable(length / 2).1)))
+        }
+        _ => Some((*numbers.select_nth_unstable(length / 2).1, None)),
+    }
+}
+
+/// Groups the phone numbers found by a query, with every duplicate, into the neighbours of the target
+/// # Arguments
+/// * `target` - the target of the query
+/// * `numbers` - the phone numbers found, each one repeated as many times as it appears
+fn group_neighbours(target: i64, numbers: &[i64]) -> Vec<Neighbour> {
+    numbers
+        .chunk_by(|a, b| a == b)
+        .map(|group| Neighbour { distance: group[0].abs_diff(target), number: group[0], count: group.len() })
+        .collect()
+}
+
+/// The response to a k-nearest query
+#[derive(Debug, Serialize)]
+struct NearestResponse {
+    target: i64,
+    k: usize,
+    neighbours: Vec<Neighbour>,
+}
+
+/// The response to a radius query
+#[derive(Debug, Serialize)]
+struct RadiusResponse {
+    target: i64,
+    radius: u64,
+    neighbours: Vec<Neighbour>,
+}
+
+/// The response to a median query, which has `median2` set when there is an even number of phone numbers
+#[derive(Debug, Serialize)]
+struct MedianResponse {
+    numbers: usize,
+    median: Option<i64>,
+    median2: Option<i64>,
+}
+
+/// A query service that loads a phone number file once and answers k-nearest, radius
+/// and median queries against it as JSON. The file is reloaded whenever it changes.
+/// Requests are routed by `handle`, which does not need a socket, while `serve`
+/// answers them over HTTP.
+#[derive(Debug)]
+pub struct Service {
+    path: PathBuf,
+    layout: Layout,
+    dataset: RwLock<Dataset>,
+    reloads: RwLock<(usize, Option<String>)>,
+}
+
+impl Service {
+    /// Loads the phone numbers in a file to be served
+    /// # Arguments
+    /// * `path` - the path of the file to serve
+    /// * `layout` - how the phone numbers are laid out in the file
+    /// # Errors
+    /// This function will return an error if the file cannot be read,
+    /// or if it is a table without the column in its header
+    pub fn load<P: AsRef<Path>>(path: P, layout: Layout) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let dataset = Dataset::load(&path, &layout)?;
+        Ok(Service { path, layout, dataset: RwLock::new(dataset), reloads: RwLock::new((0, None)) })
+    }
+
+    /// Answers a request, reloading the file first if it has changed since it was last loaded.
+    /// The endpoints are:
+    /// * `GET /health` - the status of the service and the size of the dataset
+    /// * `GET /nearest?target=<number>&k=<k>` - the k nearest phone numbers to the target
+    /// * `GET /radius?target=<number>&radius=<r>` - every phone number within the radius of the target
+    /// * `GET /median` - the median value(s) of the phone numbers
+    /// # Arguments
+    /// * `method` - the HTTP method of the request
+    /// * `url` - the path and query string of the request
+    /// # Returns
+    /// * `(u16, Value)` - the HTTP status code and the JSON body of the response
+    pub fn handle(&self, method: &str, url: &str) -> (u16, Value) {
+        if method != "GET" {
+            return (405, json!({ "error": "only GET requests are supported" }));
+        }
+        self.reload_if_changed();
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let result = match path {
+            "/health" => Ok(self.health()),
+            "/nearest" => self.nearest(query),
+            "/radius" => self.radius(query),
+            "/median" => Ok(self.median()),
+            _ => return (404, json!({ "error": format!("no endpoint at {}", path) })),
+        };
+        match result {
+            Ok(body) => (200, body),
+            Err(message) => (400, json!({ "error": message })),
+        }
+    }
+
+    /// Reloads the file if its modification time or length has changed.
+    /// If the file cannot be read, the previous dataset is kept and the error is reported by `/health`.
+    fn reload_if_changed(&self) {
+        let version = file_version(&self.path);
+        if version.is_some() && version == self.read_dataset().modified {
+            return;
+        }
+        let mut reloads = self.reloads.write().unwrap_or_else(|e| e.into_inner());
+        match Dataset::load(&self.path, &self.layout) {
+            Ok(dataset) => {
+                *self.dataset.write().unwrap_or_else(|e| e.into_inner()) = dataset;
+                *reloads = (reloads.0 + 1, None);
+            }
+            Err(e) => reloads.1 = Some(e.to_string()),
+        }
+    }
+
+    /// Locks the dataset for reading, recovering it if a previous request panicked
+    fn read_dataset(&self) -> std::sync::RwLockReadGuard<'_, Dataset> {
+        self.dataset.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Answers `/health` with the status of the service and the size of the dataset
+    fn health(&self) -> Value {
+        let dataset = self.read_dataset();
+        let reloads = self.reloads.read().unwrap_or_else(|e| e.into_inner());
+        json!({
+            "status": if reloads.1.is_none() { "ok" } else { "stale" },
+            "file": self.path.display().to_string(),
+            "numbers": dataset.counts.total(),
+            "unique": dataset.counts.len(),
+            "reloads": reloads.0,
+            "error": reloads.1,
+        })
+    }
+
+    /// Answers `/nearest` with the k nearest phone numbers to the target, found by `kth_nearest`
+    /// so that numbers at the same distance are in the order they first appeared, as the binary prints them
+    fn nearest(&self, query: &str) -> Result<Value, String> {
+        let target = phone_number_sanitizer(&query_parameter(query, "target")?)?;
+        let k = query_parameter(query, "k")?.parse::<usize>().map_err(|_| "k must be a positive integer")?;
+        if k == 0 {
+            return Err("k must be greater than 0".to_owned());
+        }
+        let neighbours = group_neighbours(target, &kth_nearest(k as i64, target, &self.read_dataset().counts));
+        Ok(json!(NearestResponse { target, k, neighbours }))
+    }
+
+    /// Answers `/radius` with every phone number within the radius of the target
+    fn radius(&self, query: &str) -> Result<Value, String> {
+        let target = phone_number_sanitizer(&query_parameter(query, "target")?)?;
+        let radius = query_parameter(query, "radius")?.parse::<u64>().map_err(|_| "radius must be a non-negative integer")?;
+        let neighbours = group_neighbours(target, &within_radius(radius, target, &self.read_dataset().counts));
+        Ok(json!(RadiusResponse { target, radius, neighbours }))
+    }
+
+    /// Answers `/median` with the median value(s) of the phone numbers
+    fn median(&self) -> Value {
+        let dataset = self.read_dataset();
+        let median = dataset.median;
+        json!(MedianResponse {
+            numbers: dataset.counts.total(),
+            median: median.map(|(median, _)| median),
+            median2: median.and_then(|(_, median2)| median2),
+        })
+    }
+}
+
+/// Answers requests over HTTP until the process is stopped.
+/// Only loopback addresses are accepted since the service is meant for local tools.
+/// # Arguments
+/// * `service` - the service to answer requests with
+/// * `address` - the local address to bind to, such as `127.0.0.1:8080`
+/// # Errors
+/// This function will return an error if the address is not a loopback address or cannot be bound
+pub fn serve(service: &Service, address: &str) -> io::Result<()> {
+    let address: SocketAddr = address.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if !address.ip().is_loopback() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "the service may only bind to a loopback address"));
+    }
+    let server = tiny_http::Server::http(address).map_err(io::Error::other)?;
+    if let Some(address) = server.server_addr().to_ip() {
+        eprintln!("Serving {} on http://{}", service.path.display(), address);
+    }
+    serve_requests(service, &server);
+    Ok(())
+}
+
+/// Answers every request received by the server
+/// # Arguments
+/// * `service` - the service to answer requests with
+/// * `server` - the server to receive requests from
+fn serve_requests(service: &Service, server: &tiny_http::Server) {
+    for request in server.incoming_requests() {
+        let (status, body) = service.handle(request.method().as_str(), request.url());
+        let header = tiny_http::Header::from_bytes("Content-Type", "application/json").expect("valid header");
+        let response = tiny_http::Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        // the client may have gone away, which should not stop the service
+        let _ = request.respond(response);
+    }
+}
+
+/// Returns the modification time and length of a file, which change whenever it is rewritten
+fn file_version(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Finds the value of a parameter in a query string, decoding any percent-encoded characters
+/// # Arguments
+/// * `query` - the query string, without the leading `?`
+/// * `name` - the name of the parameter
+fn query_parameter(query: &str, name: &str) -> Result<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+        .ok_or_else(|| format!("missing query parameter `{}`", name))
+}
+
+/// Decodes `%XX` escapes and `+` signs in a query string value
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    /// This function writes the contents to a unique file in the temporary directory
+    fn temp_dataset(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}_{}_{}", name, std::process::id(), rand::thread_rng().gen::<u32>()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// This test checks every endpoint of the service without a socket
+    #[test]
+    fn test_service_endpoints() {
+        let path = temp_dataset("test_service_endpoints", "7327325555\n7327325555\n7327325554\n7327325550\n");
+        let service = Service::load(&path, Layout::default()).unwrap();
+        let (status, body) = service.handle("GET", "/health");
+        assert_eq!(status, 200);
+        assert_eq!(body["numbers"], 4);
+        assert_eq!(body["unique"], 3);
+        let (status, body) = service.handle("GET", "/nearest?target=(732)%20732-5555&k=1");
+        assert_eq!(status, 200);
+        assert_eq!(body["neighbours"], json!([{ "distance": 0, "number": 7327325555_i64, "count": 2 }]));
+        let (status, body) = service.handle("GET", "/radius?target=7327325553&radius=1");
+        assert_eq!(status, 200);
+        assert_eq!(body["neighbours"][0]["number"], 7327325554_i64);
+        assert_eq!(body["neighbours"].as_array().unwrap().len(), 1);
+        let (status, body) = service.handle("GET", "/median");
+        assert_eq!(status, 200);
+        assert_eq!(body, json!({ "numbers": 4, "median": 7327325554_i64, "median2": 7327325555_i64 }));
+        assert_eq!(service.handle("GET", "/nearest?target=7327325555").0, 400);
+        assert_eq!(service.handle("GET", "/nearest?target=7327325555&k=0").0, 400);
+        assert_eq!(service.handle("GET", "/nearest?target=12&k=1").0, 400);
+        assert_eq!(service.handle("GET", "/missing").0, 404);
+        assert_eq!(service.handle("POST", "/health").0, 405);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// This test checks that the dataset is reloaded when the file changes,
+    /// and that the previous dataset is kept if the file disappears
+    #[test]
+    fn test_service_reloads_on_change() {
+        let path = temp_dataset("test_service_reloads_on_change", "7327325555\n");
+        let service = Service::load(&path, Layout::default()).unwrap();
+        assert_eq!(service.handle("GET", "/median").1["median"], 7327325555_i64);
+        fs::write(&path, "7327325555\n1234567890\n1234567890\n").unwrap();
+        assert_eq!(service.handle("GET", "/median").1["median"], 1234567890_i64);
+        assert_eq!(service.handle("GET", "/health").1["reloads"], 1);
+        fs::remove_file(&path).unwrap();
+        let (_, body) = service.handle("GET", "/health");
+        assert_eq!(body["status"], "stale");
+        assert_eq!(body["numbers"], 3);
+    }
+
+    /// This test answers a request over a real socket bound to a local port
+    #[test]
+    fn test_service_over_http() {
+        let path = temp_dataset("test_service_over_http", "7327325555\n");
+        let service = Service::load(&path, Layout::default()).unwrap();
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        std::thread::spawn(move || serve_requests(&service, &server));
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET /nearest?target=7327325555&k=1 HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains("application/json"));
+        assert!(response.ends_with(r#"{"target":7327325555,"k":1,"neighbours":[{"distance":0,"number":7327325555,"count":1}]}"#), "{}", response);
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// This test checks that the service refuses to bind to an address other hosts can reach
+    #[test]
+    fn test_serve_rejects_public_address() {
+        let path = temp_dataset("test_serve_rejects_public_address", "7327325555\n");
+        let service = Service::load(&path, Layout::default()).unwrap();
+        assert_eq!(serve(&service, "0.0.0.0:0").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(serve(&service, "not an address").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        fs::remove_file(&path).unwrap();
+    }
+}
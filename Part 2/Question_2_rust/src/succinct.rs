@@ -0,0 +1,915 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Neighbour, PhoneCounts};
+
+/// The number of 64 bit words covered by each rank sample of a `BitVec`
+const BLOCK_WORDS: usize = 8;
+/// The number of bits covered by each rank sample of a `BitVec`
+const BLOCK_BITS: usize = BLOCK_WORDS * 64;
+
+/// A bit vector with rank and select support.
+/// The number of ones before every block of 512 bits is sampled, so rank is a
+/// lookup plus at most 8 popcounts and select is a binary search over the samples.
+#[derive(Debug, Clone, Default)]
+struct BitVec {
+    words: Vec<u64>,
+    len: usize,
+    block_ranks: Vec<u64>,
+}
+
+impl BitVec {
+    /// Creates a bit vector of the given length with every bit unset
+    /// # Arguments
+    /// * `len` - the number of bits
+    fn new(len: usize) -> Self {
+        BitVec { words: vec![0u64; len.div_ceil(64)], len, block_ranks: Vec::new() }
+    }
+
+    /// Sets the bit at a position, which must be done before the ranks are sampled
+    fn set(&mut self, position: usize) {
+        self.words[position / 64] |= 1 << (position % 64);
+    }
+
+    /// Samples the number of set bits before every block, which rank and select need
+    fn sample_ranks(&mut self) {
+        let mut block_ranks = Vec::with_capacity(self.words.len() / BLOCK_WORDS + 1);
+        let mut rank = 0;
+        for block in self.words.chunks(BLOCK_WORDS) {
+            block_ranks.push(rank);
+            rank += block.iter().map(|word| word.count_ones() as u64).sum::<u64>();
+        }
+        self.block_ranks = block_ranks;
+    }
+
+    /// The position of the k-th set bit, indexed from 0
+    fn select1(&self, k: usize) -> usize {
+        let block = self.block_ranks.partition_point(|rank| *rank as usize <= k) - 1;
+        self.select_from_block(block, k - self.block_ranks[block] as usize, |word| word)
+    }
+
+    /// The position of the k-th unset bit, indexed from 0
+    fn select0(&self, k: usize) -> usize {
+        let zeros_before = |block: usize| block * BLOCK_BITS - self.block_ranks[block] as usize;
+        // binary search for the last block with at most k unset bits before it
+        let mut block = 0;
+        let mut right = self.block_ranks.len();
+        while right - block > 1 {
+            let middle = block + (right - block) / 2;
+            if zeros_before(middle) <= k {
+                block = middle;
+            } else {
+                right = middle;
+            }
+        }
+        self.select_from_block(block, k - zeros_before(block), |word| !word)
+    }
+
+    /// Scans the words of a block for the k-th set bit of the words after `transform` is applied
+    fn select_from_block<F: Fn(u64) -> u64>(&self, block: usize, mut k: usize, transform: F) -> usize {
+        for (offset, word) in self.words[block * BLOCK_WORDS..].iter().enumerate() {
+            let mut word = transform(*word);
+            let ones = word.count_ones() as usize;
+            if k < ones {
+                // clear the lowest k set bits, leaving the one we want as the lowest
+                for _ in 0..k {
+                    word &= word - 1;
+                }
+                return (block * BLOCK_WORDS + offset) * 64 + word.trailing_zeros() as usize;
+            }
+            k -= ones;
+        }
+        self.len
+    }
+
+    /// The number of bytes used by the bit vector
+    fn size_in_bytes(&self) -> usize {
+        (self.words.len() + self.block_ranks.len()) * 8
+    }
+}
+
+/// Integers of a fixed number of bits, packed one after the other into 64 bit words
+#[derive(Debug, Clone, Default)]
+struct PackedInts {
+    width: u32,
+    words: Vec<u64>,
+}
+
+impl PackedInts {
+    /// Creates room for `len` integers of `width` bits, all of them 0
+    fn new(len: usize, width: u32) -> Self {
+        PackedInts { width, words: vec![0u64; (len * width as usize).div_ceil(64)] }
+    }
+
+    /// Sets the i-th integer, which must still be 0 and fit in the width
+    fn set(&mut self, i: usize, value: u64) {
+        if self.width == 0 {
+            return;
+        }
+        let position = i * self.width as usize;
+        self.words[position / 64] |= value << (position % 64);
+        if position % 64 + self.width as usize > 64 {
+            // the integer straddles two words
+            self.words[position / 64 + 1] |= value >> (64 - position % 64);
+        }
+    }
+
+    /// Reads the i-th integer
+    fn get(&self, i: usize) -> u64 {
+        if self.width == 0 {
+            return 0;
+        }
+        let position = i * self.width as usize;
+        let mut value = self.words[position / 64] >> (position % 64);
+        if position % 64 + self.width as usize > 64 {
+            value |= self.words[position / 64 + 1] << (64 - position % 64);
+        }
+        value & low_mask(self.width)
+    }
+
+    /// The number of bytes used by the integers
+    fn size_in_bytes(&self) -> usize {
+        self.words.len() * 8
+    }
+}
+
+/// A non-decreasing sequence of integers stored with the Elias-Fano encoding.
+/// Each value is split into `low_width` low bits, which are packed one after the other,
+/// and its remaining high bits, which are stored in unary in a bit vector. This takes
+/// about `2 + log2(universe / n)` bits per value and still supports random access,
+/// rank, predecessor and successor queries without decompressing the sequence.
+#[derive(Debug, Clone, Default)]
+pub struct EliasFano {
+    len: usize,
+    universe: u64,
+    low_width: u32,
+    low_bits: PackedInts,
+    high_bits: BitVec,
+}
+
+impl EliasFano {
+    /// Encodes a sorted sequence of integers
+    /// # Arguments
+    /// * `values` - the values to encode, in non-decreasing order
+    /// # Errors
+    /// This function will return an error if the values are not in non-decreasing order
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::succinct::EliasFano;
+    /// let sequence = EliasFano::new(&[3, 5, 5, 9, 1000]).unwrap();
+    /// assert_eq!(sequence.get(3), 9);
+    /// assert_eq!(sequence.rank(6), 3);
+    /// assert_eq!(sequence.predecessor(8), Some(5));
+    /// assert_eq!(sequence.successor(10), Some(1000));
+    /// assert!(EliasFano::new(&[5, 3]).is_err());
+    /// ```
+    pub fn new(values: &[u64]) -> Result<Self, &'static str> {
+        let universe = values.iter().max().map_or(1, |max| max + 1);
+        let mut builder = EliasFanoBuilder::new(values.len(), universe);
+        values.iter().try_for_each(|value| builder.push(*value))?;
+        builder.finish()
+    }
+
+    /// The number of values in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence has no values in it
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of bytes used by the encoded sequence
+    pub fn size_in_bytes(&self) -> usize {
+        self.low_bits.size_in_bytes() + self.high_bits.size_in_bytes()
+    }
+
+    /// Returns the i-th value of the sequence, indexed from 0
+    /// # Arguments
+    /// * `i` - the index of the value, which must be less than the length
+    pub fn get(&self, i: usize) -> u64 {
+        let high = (self.high_bits.select1(i) - i) as u64;
+        (high << self.low_width) | self.low(i)
+    }
+
+    /// Counts the number of values less than x
+    /// # Arguments
+    /// * `x` - the value to compare against
+    pub fn rank(&self, x: u64) -> usize {
+        if x >= self.universe {
+            return self.len;
+        }
+        let high = x >> self.low_width;
+        let start = self.count_high_below(high);
+        let end = self.count_high_below(high + 1);
+        // the values in [start, end) share the same high bits, so only the low bits need comparing
+        let low = x & low_mask(self.low_width);
+        let mut left = start;
+        let mut right = end;
+        while left < right {
+            let middle = left + (right - left) / 2;
+            if self.low(middle) < low {
+                left = middle + 1;
+            } else {
+                right = middle;
+            }
+        }
+        left
+    }
+
+    /// Returns the largest value less than or equal to x
+    pub fn predecessor(&self, x: u64) -> Option<u64> {
+        match self.rank(x.saturating_add(1)) {
+            0 => None,
+            rank => Some(self.get(rank - 1)),
+        }
+    }
+
+    /// Returns the smallest value greater than or equal to x
+    pub fn successor(&self, x: u64) -> Option<u64> {
+        let rank = self.rank(x);
+        if rank < self.len {
+            Some(self.get(rank))
+        } else {
+            None
+        }
+    }
+
+    /// Reads the low bits of the i-th value
+    fn low(&self, i: usize) -> u64 {
+        self.low_bits.get(i)
+    }
+
+    /// Counts the number of values whose high bits are less than `high`
+    fn count_high_below(&self, high: u64) -> usize {
+        // every high value is terminated by an unset bit, with the values that have it set before it
+        let buckets = self.high_bits.len - self.len;
+        if high == 0 {
+            0
+        } else if high as usize > buckets {
+            self.len
+        } else {
+            self.high_bits.select0(high as usize - 1) - (high as usize - 1)
+        }
+    }
+}
+
+/// Encodes a non-decreasing sequence with the Elias-Fano encoding one value at a time,
+/// so that the values never have to be collected into a list first.
+/// The number of values and an upper bound on them must be known in advance.
+/// # Example
+/// ```rust
+/// use question_2_rust::succinct::EliasFanoBuilder;
+/// let mut builder = EliasFanoBuilder::new(3, 100);
+/// for value in [7, 7, 42] {
+///     builder.push(value).unwrap();
+/// }
+/// assert_eq!(builder.finish().unwrap().get(2), 42);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EliasFanoBuilder {
+    sequence: EliasFano,
+    pushed: usize,
+    last: u64,
+}
+
+impl EliasFanoBuilder {
+    /// Creates a builder for a sequence of the given length
+    /// # Arguments
+    /// * `len` - the number of values that will be pushed
+    /// * `universe` - a number larger than every value
+    pub fn new(len: usize, universe: u64) -> Self {
+        let universe = universe.max(1);
+        let low_width = if len == 0 { 0 } else { (universe / len as u64).max(1).ilog2() };
+        let low_bits = PackedInts::new(len, low_width);
+        let high_bits = BitVec::new(len + ((universe - 1) >> low_width) as usize + 1);
+        EliasFanoBuilder { sequence: EliasFano { len, universe, low_width, low_bits, high_bits }, pushed: 0, last: 0 }
+    }
+
+    /// Adds the next value of the sequence
+    /// # Errors
+    /// This function will return an error if the value is smaller than the one before it or not less than
+    /// the universe, or if every value has already been pushed
+    pub fn push(&mut self, value: u64) -> Result<(), &'static str> {
+        let sequence = &mut self.sequence;
+        if self.pushed == sequence.len {
+            return Err("more values were pushed than the length of the sequence");
+        } else if self.pushed > 0 && value < self.last {
+            return Err("values must be in non-decreasing order");
+        } else if value >= sequence.universe {
+            return Err("values must be less than the universe");
+        }
+        let (i, low_width) = (self.pushed, sequence.low_width);
+        sequence.low_bits.set(i, value & low_mask(low_width));
+        sequence.high_bits.set((value >> low_width) as usize + i);
+        self.pushed += 1;
+        self.last = value;
+        Ok(())
+    }
+
+    /// Finishes the sequence, so that it can be queried
+    /// # Errors
+    /// This function will return an error if fewer values were pushed than the length of the sequence
+    pub fn finish(mut self) -> Result<EliasFano, &'static str> {
+        if self.pushed < self.sequence.len {
+            return Err("fewer values were pushed than the length of the sequence");
+        }
+        self.sequence.high_bits.sample_ranks();
+        Ok(self.sequence)
+    }
+}
+
+/// Returns a mask of the lowest `width` bits
+fn low_mask(width: u32) -> u64 {
+    if width == 0 { 0 } else { u64::MAX >> (64 - width) }
+}
+
+/// A unique phone number, the number of times it appears, and the position it first appeared at
+type Entry = (i64, usize, u64);
+
+/// A compressed nearest neighbour index over deduplicated phone numbers.
+/// The sorted unique numbers and the running total of their counts are both stored
+/// with the Elias-Fano encoding, which takes about `2 + log2(10^10 / n)` bits for each of
+/// n unique phone numbers plus a few bits for each count, and k-nearest and radius queries
+/// are answered with rank queries without decompressing the index.
+/// The position each number first appeared at is packed next to them in `log2(total)` bits,
+/// so that numbers at the same distance are found in the order they first appeared, as `kth_nearest` finds them.
+#[derive(Debug, Clone, Default)]
+pub struct SuccinctIndex {
+    numbers: EliasFano,
+    cumulative_counts: EliasFano,
+    first_seen: PackedInts,
+}
+
+impl SuccinctIndex {
+    /// Creates the index from the counts of each phone number, in the order they first appeared.
+    /// Sanitized phone numbers are never negative, so negative numbers are skipped.
+    /// # Arguments
+    /// * `counts` - the number of times each phone number appeared
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::{succinct::SuccinctIndex, PhoneCounts};
+    /// let index = SuccinctIndex::from_counts(&PhoneCounts::from_numbers(&[7327325555, 7327325555, 7327325554]));
+    /// assert_eq!(index.len(), 2);
+    /// assert_eq!(index.count(7327325555), 2);
+    /// ```
+    pub fn from_counts(counts: &PhoneCounts) -> Self {
+        let mut entries: Vec<Entry> = counts
+            .iter()
+            .enumerate()
+            .filter(|(_, (number, _))| *number >= 0)
+            .map(|(first_seen, (number, count))| (number, count, first_seen as u64))
+            .collect();
+        entries.sort_unstable();
+        let total = entries.iter().map(|(_, count, _)| count).sum();
+        let largest = entries.last().map_or(0, |(number, _, _)| *number);
+        let last_seen = counts.len().saturating_sub(1) as u64;
+        SuccinctIndex::from_entries(entries.len(), total, largest, last_seen, entries.into_iter()).expect("the counts were sorted")
+    }
+
+    /// Creates the index from every phone number in ascending order, with its duplicates next to it,
+    /// which is also the order they first appear in.
+    /// The numbers are encoded as they are read, so building the index only needs as much memory as the
+    /// index itself, unlike counting them in a hash map first.
+    /// Sanitized phone numbers are never negative, so negative numbers are skipped.
+    /// # Arguments
+    /// * `numbers` - the phone numbers, sorted
+    /// # Errors
+    /// This function will return an error if the phone numbers are not sorted
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::succinct::SuccinctIndex;
+    /// let index = SuccinctIndex::from_sorted(&[7327325554, 7327325555, 7327325555]).unwrap();
+    /// assert_eq!(index.count(7327325555), 2);
+    /// assert!(SuccinctIndex::from_sorted(&[7327325555, 7327325554]).is_err());
+    /// ```
+    pub fn from_sorted(numbers: &[i64]) -> Result<Self, &'static str> {
+        let numbers = &numbers[numbers.iter().take_while(|number| **number < 0).count()..];
+        let largest = numbers.last().copied().unwrap_or(0);
+        let last_seen = numbers.len().saturating_sub(1) as u64;
+        let len = entries(numbers.iter().copied().zip(0..)).count();
+        SuccinctIndex::from_entries(len, numbers.len(), largest, last_seen, entries(numbers.iter().copied().zip(0..)))
+    }
+
+    /// Encodes the unique phone numbers, the running total of their counts and where they first appeared in one pass
+    /// # Arguments
+    /// * `len` - the number of unique phone numbers
+    /// * `total` - the number of phone numbers, including duplicates
+    /// * `largest` - the largest phone number, which is the last one if they are sorted
+    /// * `last_seen` - the largest position a phone number first appeared at
+    /// * `entries` - each unique phone number with its count and where it first appeared, in ascending order
+    fn from_entries<I>(len: usize, total: usize, largest: i64, last_seen: u64, entries: I) -> Result<Self, &'static str>
+    where
+        I: Iterator<Item = Entry>,
+    {
+        let mut numbers = EliasFanoBuilder::new(len, largest.max(0) as u64 + 1);
+        let mut cumulative_counts = EliasFanoBuilder::new(len + 1, total as u64 + 1);
+        let mut first_seen = PackedInts::new(len, u64::BITS - last_seen.leading_zeros());
+        let mut cumulative = 0;
+        cumulative_counts.push(cumulative)?;
+        for (i, (number, count, seen)) in entries.enumerate() {
+            if number < 0 {
+                return Err("values must be in non-decreasing order");
+            }
+            numbers.push(number as u64)?;
+            cumulative += count as u64;
+            cumulative_counts.push(cumulative)?;
+            if seen > last_seen {
+                return Err("positions must be no later than the last position");
+            }
+            first_seen.set(i, seen);
+        }
+        Ok(SuccinctIndex { numbers: numbers.finish()?, cumulative_counts: cumulative_counts.finish()?, first_seen })
+    }
+
+    /// The number of unique phone numbers in the index
+    pub fn len(&self) -> usize {
+        self.numbers.len()
+    }
+
+    /// Whether the index has no phone numbers in it
+    pub fn is_empty(&self) -> bool {
+        self.numbers.is_empty()
+    }
+
+    /// The number of bytes used by the index
+    pub fn size_in_bytes(&self) -> usize {
+        self.numbers.size_in_bytes() + self.cumulative_counts.size_in_bytes() + self.first_seen.size_in_bytes()
+    }
+
+    /// The number of times a phone number appears in the index
+    pub fn count(&self, number: i64) -> usize {
+        if number < 0 {
+            return 0;
+        }
+        let rank = self.numbers.rank(number as u64);
+        if rank < self.len() && self.numbers.get(rank) == number as u64 {
+            self.count_at(rank)
+        } else {
+            0
+        }
+    }
+
+    /// Finds the k nearest unique phone numbers to the target by walking outwards from
+    /// the rank of the target in both directions. In the case of a tie at the k-th distance,
+    /// every number at that distance is returned, the same way `kth_nearest` does.
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `k` - the number of unique phone numbers to find
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance, and numbers at the same distance
+    ///   in the order they first appeared, the same order as `kth_nearest`
+    /// # Example
+    /// ```rust
+    /// use question_2_rust::{succinct::SuccinctIndex, PhoneCounts};
+    /// let index = SuccinctIndex::from_counts(&PhoneCounts::from_numbers(&[12, 10, 12, 8, 20]));
+    /// let found: Vec<(i64, usize)> = index.nearest(11, 1).iter().map(|n| (n.number, n.count)).collect();
+    /// assert_eq!(found, vec![(12, 2), (10, 1)]);
+    /// ```
+    pub fn nearest(&self, target: i64, k: usize) -> Vec<Neighbour> {
+        let rank = self.numbers.rank(target.max(0) as u64);
+        // the next index to look at below and above the target
+        let mut below = rank;
+        let mut above = rank;
+        let mut result: Vec<Neighbour> = Vec::new();
+        loop {
+            let below_distance = (below > 0).then(|| (self.numbers.get(below - 1) as i64).abs_diff(target));
+            let above_distance = (above < self.len()).then(|| (self.numbers.get(above) as i64).abs_diff(target));
+            // take whichever side is closer, preferring the number that appeared first on a tie,
+            // as only the numbers on either side of the target can be at the same distance
+            let index = match (below_distance, above_distance) {
+                (Some(b), Some(a)) if b < a => below - 1,
+                (Some(b), Some(a)) if b == a && self.first_seen.get(below - 1) < self.first_seen.get(above) => below - 1,
+                (Some(_), None) => below - 1,
+                (_, Some(_)) => above,
+                (None, None) => break,
+            };
+            let found = self.neighbour_at(index, target);
+            if result.len() >= k && result.last().map(|last| last.distance) != Some(found.distance) {
+                // we have enough numbers, and this one is not tied with the last one
+                break;
+            }
+            if index < rank {
+                below -= 1;
+            } else {
+                above += 1;
+            }
+            result.push(found);
+        }
+        result
+    }
+
+    /// Finds every phone number within the radius of the target
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `radius` - the maximum absolute difference from the target, inclusive
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance then by number
+    pub fn within(&self, target: i64, radius: u64) -> Vec<Neighbour> {
+        // the ends can be outside the range of an i64 when the radius is larger than i64::MAX
+        let (first, last) = (target as i128 - radius as i128, target as i128 + radius as i128);
+        let start = self.numbers.rank(first.max(0) as u64);
+        let end = match last {
+            last if last < 0 => 0,
+            last if last < i64::MAX as i128 => self.numbers.rank(last as u64 + 1),
+            _ => self.len(),
+        };
+        let mut result: Vec<Neighbour> = (start..end.max(start)).map(|index| self.neighbour_at(index, target)).collect();
+        result.sort_by_key(|found| (found.distance, found.number));
+        result
+    }
+
+    /// The count of the i-th smallest phone number
+    fn count_at(&self, i: usize) -> usize {
+        (self.cumulative_counts.get(i + 1) - self.cumulative_counts.get(i)) as usize
+    }
+
+    /// The i-th smallest phone number as a neighbour of the target
+    fn neighbour_at(&self, i: usize, target: i64) -> Neighbour {
+        let number = self.numbers.get(i) as i64;
+        Neighbour { distance: number.abs_diff(target), number, count: self.count_at(i) }
+    }
+}
+
+/// The number of phone numbers a `SuccinctIndexBuilder` sorts in memory at a time by default,
+/// which takes 16 MB along with the position each appeared at
+pub const DEFAULT_BUFFER: usize = 1 << 20;
+
+/// Builds a `SuccinctIndex` from phone numbers in the order they are read, such as while a file is streamed,
+/// without collecting them into a list or counting them in a hash map.
+/// The phone numbers are sorted a buffer at a time, each sorted buffer is written to a temporary file
+/// as a run of counted numbers, and the runs are merged into the index at the end. Building the index
+/// needs about as much memory as the buffer and the index itself, however many phone numbers are read.
+/// # Example
+/// ```rust
+/// use question_2_rust::succinct::SuccinctIndexBuilder;
+/// let mut builder = SuccinctIndexBuilder::with_buffer(2);
+/// for number in [7327325557, 7327325553, 7327325557, 7327325554] {
+///     builder.push(number);
+/// }
+/// let index = builder.finish().unwrap();
+/// assert_eq!(index.count(7327325557), 2);
+/// let found: Vec<i64> = index.nearest(7327325555, 1).iter().map(|n| n.number).collect();
+/// assert_eq!(found, vec![7327325554]);
+/// ```
+#[derive(Debug)]
+pub struct SuccinctIndexBuilder {
+    buffer: Vec<(i64, u64)>,
+    capacity: usize,
+    runs: Vec<Run>,
+    pushed: u64,
+    total: usize,
+    error: Option<io::Error>,
+}
+
+impl Default for SuccinctIndexBuilder {
+    fn default() -> Self {
+        SuccinctIndexBuilder::with_buffer(DEFAULT_BUFFER)
+    }
+}
+
+impl SuccinctIndexBuilder {
+    /// Creates a builder that sorts the given number of phone numbers in memory at a time
+    /// # Arguments
+    /// * `capacity` - the number of phone numbers to sort before they are written to a run, at least 1
+    pub fn with_buffer(capacity: usize) -> Self {
+        SuccinctIndexBuilder { buffer: Vec::new(), capacity: capacity.max(1), runs: Vec::new(), pushed: 0, total: 0, error: None }
+    }
+
+    /// Adds the next phone number that was read.
+    /// Sanitized phone numbers are never negative, so negative numbers are skipped.
+    /// A run that cannot be written is reported by `finish`, and every number after it is ignored.
+    pub fn push(&mut self, number: i64) {
+        let position = self.pushed;
+        self.pushed += 1;
+        if number < 0 || self.error.is_some() {
+            return;
+        }
+        self.buffer.push((number, position));
+        self.total += 1;
+        if self.buffer.len() >= self.capacity {
+            if let Err(error) = self.spill() {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    /// Sorts the buffer and writes it to a new run
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort_unstable();
+        self.runs.push(Run::write(entries(self.buffer.iter().copied()))?);
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Builds the index from every phone number pushed, then removes the runs
+    /// # Errors
+    /// This function will return an error if a run could not be written or read back
+    pub fn finish(mut self) -> io::Result<SuccinctIndex> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        let last_seen = self.pushed.saturating_sub(1);
+        if self.runs.is_empty() {
+            // every phone number fit in the buffer, so it is sorted in memory without writing a run
+            self.buffer.sort_unstable();
+            let len = entries(self.buffer.iter().copied()).count();
+            let largest = self.buffer.last().map_or(0, |(number, _)| *number);
+            let index = SuccinctIndex::from_entries(len, self.total, largest, last_seen, entries(self.buffer.iter().copied()));
+            return Ok(index.expect("the buffer was sorted"));
+        }
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        self.buffer = Vec::new();
+        // the first merge finds the size of the index, and the second encodes it
+        let (mut len, mut largest) = (0, 0);
+        for entry in Merge::new(&self.runs)? {
+            (len, largest) = (len + 1, entry?.0);
+        }
+        let mut error = None;
+        let merged = Merge::new(&self.runs)?.map_while(|entry| entry.map_err(|e| error = Some(e)).ok());
+        let index = SuccinctIndex::from_entries(len, self.total, largest, last_seen, merged);
+        match error {
+            Some(error) => Err(error),
+            None => index.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
+
+/// Counts the runs written, so that every run of every builder has its own file
+static RUNS_WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+/// A sorted run of counted phone numbers in a temporary file, which is removed when the run is dropped.
+/// Each unique phone number is stored as three little-endian `u64`s: the number, its count and where it first appeared.
+#[derive(Debug)]
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    /// Writes counted phone numbers in ascending order to a new temporary file
+    fn write(entries: impl Iterator<Item = Entry>) -> io::Result<Run> {
+        let name = format!("succinct_run_{}_{}", std::process::id(), RUNS_WRITTEN.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        let mut writer = BufWriter::new(File::create_new(&path)?);
+        // the run owns the file from here, so it is removed even if writing it fails
+        let run = Run { path };
+        for (number, count, first_seen) in entries {
+            writer.write_all(&number.to_le_bytes())?;
+            writer.write_all(&(count as u64).to_le_bytes())?;
+            writer.write_all(&first_seen.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(run)
+    }
+
+    /// Reads the counted phone numbers back in ascending order
+    fn read(&self) -> io::Result<RunReader> {
+        Ok(RunReader(BufReader::new(File::open(&self.path)?)))
+    }
+}
+
+/// Reads the counted phone numbers of a run in ascending order
+struct RunReader(BufReader<File>);
+
+impl Iterator for RunReader {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0u8; 24];
+        match self.0.read_exact(&mut bytes) {
+            Ok(()) => {
+                let value = |i: usize| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap());
+                Some(Ok((value(0) as i64, value(1) as usize, value(2))))
+            }
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        // a temporary file that cannot be removed is left for the system to clean up
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Merges sorted runs into one sequence of counted phone numbers in ascending order,
+/// adding up the counts of a number found in several runs and keeping the earliest position it appeared at
+struct Merge {
+    runs: Vec<RunReader>,
+    // the next entry of each run, smallest first: the number, where it first appeared, its count and the run
+    heap: BinaryHeap<Reverse<(i64, u64, usize, usize)>>,
+}
+
+impl Merge {
+    /// Opens every run to be merged
+    fn new(runs: &[Run]) -> io::Result<Self> {
+        let mut merge = Merge { runs: Vec::with_capacity(runs.len()), heap: BinaryHeap::with_capacity(runs.len()) };
+        for run in runs {
+            merge.runs.push(run.read()?);
+            merge.advance(merge.runs.len() - 1)?;
+        }
+        Ok(merge)
+    }
+
+    /// Reads the next entry of a run onto the heap
+    fn advance(&mut self, run: usize) -> io::Result<()> {
+        if let Some(entry) = self.runs[run].next() {
+            let (number, count, first_seen) = entry?;
+            self.heap.push(Reverse((number, first_seen, count, run)));
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for Merge {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the heap is ordered by where a number first appeared after the number, so the first one popped appeared earliest
+        let Reverse((number, first_seen, mut count, run)) = self.heap.pop()?;
+        let mut result = self.advance(run);
+        while let Some(Reverse((next, _, more, run))) = self.heap.peek().copied() {
+            if next != number {
+                break;
+            }
+            self.heap.pop();
+            count += more;
+            result = result.and(self.advance(run));
+        }
+        Some(result.map(|()| (number, count, first_seen)))
+    }
+}
+
+/// Groups phone numbers, sorted along with the positions they appeared at, into each unique number,
+/// the number of times it is repeated and the first position it appeared at
+fn entries<I: Iterator<Item = (i64, u64)>>(sorted: I) -> impl Iterator<Item = Entry> {
+    let mut sorted = sorted.peekable();
+    std::iter::from_fn(move || {
+        let (number, first_seen) = sorted.next()?;
+        let mut count = 1;
+        while sorted.next_if(|(next, _)| *next == number).is_some() {
+            count += 1;
+        }
+        Some((number, count, first_seen))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binary::{write_binary, BinaryDataset};
+    use crate::expand_neighbours;
+    use crate::nearest::{kth_nearest, within_radius};
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+    /// This test checks every Elias-Fano query against a plain sorted vector
+    #[test]
+    fn test_elias_fano_against_sorted_vec() {
+        for seed in 0..20 {
+            let mut rng = ChaChaRng::seed_from_u64(seed);
+            let len = rng.gen_range(0..3000);
+            let max_value = [10, 1000, 10000000000][seed as usize % 3];
+            let mut values: Vec<u64> = (0..len).map(|_| rng.gen_range(0..max_value)).collect();
+            values.sort();
+            let sequence = EliasFano::new(&values).unwrap();
+            assert_eq!(sequence.len(), values.len());
+            for (i, value) in values.iter().enumerate() {
+                assert_eq!(sequence.get(i), *value);
+            }
+            for _ in 0..1000 {
+                let x = rng.gen_range(0..max_value + 10);
+                let rank = values.partition_point(|value| *value < x);
+                assert_eq!(sequence.rank(x), rank);
+                assert_eq!(sequence.successor(x), values.get(rank).copied());
+                let predecessor = values.iter().rev().find(|value| **value <= x).copied();
+                assert_eq!(sequence.predecessor(x), predecessor);
+            }
+        }
+    }
+
+    /// This test checks that sequences that cannot be encoded are errors rather than panics
+    #[test]
+    fn test_elias_fano_errors() {
+        assert_eq!(EliasFano::new(&[1, 3, 2]).unwrap_err(), "values must be in non-decreasing order");
+        assert!(EliasFano::new(&[]).unwrap().is_empty());
+        let mut builder = EliasFanoBuilder::new(2, 10);
+        assert_eq!(builder.push(10), Err("values must be less than the universe"));
+        builder.push(4).unwrap();
+        assert_eq!(builder.clone().finish().unwrap_err(), "fewer values were pushed than the length of the sequence");
+        builder.push(4).unwrap();
+        assert_eq!(builder.push(5), Err("more values were pushed than the length of the sequence"));
+        assert_eq!(builder.finish().unwrap().get(1), 4);
+        assert!(SuccinctIndex::from_sorted(&[3, 1, 2]).is_err());
+        assert!(SuccinctIndex::from_sorted(&[1, 2, 2, 1]).is_err());
+        assert!(SuccinctIndex::from_sorted(&[5, -1]).is_err());
+        assert_eq!(SuccinctIndex::from_sorted(&[-5, -1, 0, 0]).unwrap().count(0), 2);
+    }
+
+    /// This test checks that the succinct index answers queries the same way as `kth_nearest` and `within_radius`,
+    /// with tied numbers in the order they first appeared, whether it is built from counts, from sorted numbers
+    /// or by the builder from many runs
+    #[test]
+    fn test_succinct_index_against_kth_nearest() {
+        for seed in 0..20 {
+            let mut rng = ChaChaRng::seed_from_u64(seed);
+            let numbers: Vec<i64> = (0..2000).map(|_| rng.gen_range(1000000000..1000100000)).collect();
+            let counts = PhoneCounts::from_numbers(&numbers);
+            let index = SuccinctIndex::from_counts(&counts);
+            // a buffer much smaller than the numbers, so that they are merged from many runs
+            let mut builder = SuccinctIndexBuilder::with_buffer(rng.gen_range(1..300));
+            numbers.iter().for_each(|number| builder.push(*number));
+            let built = builder.finish().unwrap();
+            let mut sorted_numbers = numbers.clone();
+            sorted_numbers.sort_unstable();
+            let from_sorted = SuccinctIndex::from_sorted(&sorted_numbers).unwrap();
+            let sorted_counts = PhoneCounts::from_numbers(&sorted_numbers);
+            assert_eq!(index.len(), counts.len());
+            assert_eq!(built.len(), counts.len());
+            assert_eq!(from_sorted.len(), counts.len());
+            for _ in 0..100 {
+                let target = rng.gen_range(999990000..1000110000);
+                let k = rng.gen_range(0..50);
+                let radius = rng.gen_range(0..200);
+                let expected = kth_nearest(k as i64, target, &counts);
+                assert_eq!(expand_neighbours(&index.nearest(target, k)), expected, "seed {}", seed);
+                assert_eq!(expand_neighbours(&built.nearest(target, k)), expected, "seed {}", seed);
+                assert_eq!(expand_neighbours(&from_sorted.nearest(target, k)), kth_nearest(k as i64, target, &sorted_counts));
+                let expected = within_radius(radius, target, &counts);
+                assert_eq!(expand_neighbours(&index.within(target, radius)), expected);
+                assert_eq!(expand_neighbours(&built.within(target, radius)), expected);
+                assert_eq!(expand_neighbours(&from_sorted.within(target, radius)), expected);
+                assert_eq!(index.count(target), counts.count(target));
+                assert_eq!(built.count(target), counts.count(target));
+            }
+        }
+    }
+
+    /// This test checks that the builder indexes no numbers, negative numbers and numbers that fit in its buffer,
+    /// and removes every run it writes
+    #[test]
+    fn test_succinct_index_builder() {
+        assert!(SuccinctIndexBuilder::default().finish().unwrap().is_empty());
+        let mut builder = SuccinctIndexBuilder::with_buffer(2);
+        [7327325557, -1, 7327325553, 7327325557, 7327325554, 7327325553].iter().for_each(|number| builder.push(*number));
+        assert_eq!(builder.runs.len(), 2);
+        let paths: Vec<PathBuf> = builder.runs.iter().map(|run| run.path.clone()).collect();
+        let index = builder.finish().unwrap();
+        assert!(paths.iter().all(|path| !path.exists()));
+        assert_eq!((index.len(), index.count(7327325553), index.count(-1)), (3, 2, 0));
+        let found: Vec<i64> = index.nearest(7327325555, 2).iter().map(|found| found.number).collect();
+        assert_eq!(found, vec![7327325554, 7327325557, 7327325553]);
+        let mut builder = SuccinctIndexBuilder::default();
+        [7327325557, 7327325553].iter().for_each(|number| builder.push(*number));
+        let index = builder.finish().unwrap();
+        let found: Vec<i64> = index.nearest(7327325555, 1).iter().map(|found| found.number).collect();
+        assert_eq!(found, vec![7327325557, 7327325553]);
+    }
+
+    /// This test checks that the succinct index answers queries the same way as the binary format,
+    /// which is searched in place, on random phone numbers with duplicates
+    #[test]
+    fn test_succinct_index_against_binary() {
+        let mut rng = ChaChaRng::seed_from_u64(41);
+        for _ in 0..50 {
+            let numbers: Vec<i64> = (0..rng.gen_range(0..300)).map(|_| rng.gen_range(7327325000..7327326000)).collect();
+            let counts = PhoneCounts::from_numbers(&numbers);
+            let mut bytes = Vec::new();
+            write_binary(&mut bytes, &counts).unwrap();
+            let dataset = BinaryDataset::from_bytes(bytes).unwrap();
+            let index = SuccinctIndex::from_counts(&counts);
+            for _ in 0..20 {
+                let target = rng.gen_range(7327324900..7327326100);
+                let k = rng.gen_range(1..10);
+                let radius = rng.gen_range(0..50);
+                assert_eq!(index.nearest(target, k), dataset.nearest(target, k));
+                assert_eq!(index.within(target, radius), dataset.within(target, radius));
+                assert_eq!(index.count(target), dataset.count(target));
+            }
+            // a radius too large for an i64 still finds every number
+            assert_eq!(index.within(i64::MIN, u64::MAX).len(), dataset.len());
+        }
+    }
+
+    /// This test checks that the index is much smaller than storing every number as an i64
+    #[test]
+    fn test_succinct_index_is_compact() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let numbers: Vec<i64> = (0..100000).map(|_| rng.gen_range(1000000000..=9999999999)).collect();
+        let index = SuccinctIndex::from_counts(&PhoneCounts::from_numbers(&numbers));
+        // 2 + log2(10^10 / 10^5) bits for each number, plus a few bits for each count
+        // and log2(10^5) bits for where it first appeared
+        assert!(index.size_in_bytes() < numbers.len() * 5, "{} bytes", index.size_in_bytes());
+        assert!(index.within(-5, 3).is_empty());
+        assert!(index.nearest(i64::MIN, 0).is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+//! Tests that run the compiled binary and check its output and exit codes.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use question_2_rust::records::Layout;
+use question_2_rust::serve::Service;
+use serde_json::Value;
+
+/// This function runs the binary from the crate root with the given arguments
+fn run(args: &[&str]) -> Output {
+    run_with_stdin(args, "")
+}
+
+/// This function runs the binary from the crate root with the given arguments and stdin
+fn run_with_stdin(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_Question_2_rust"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// This test checks that the help lists the subcommands and exit codes
+#[test]
+fn test_cli_help() {
+    let output = run(&["--help"]);
+    assert_eq!(output.status.code(), Some(0));
+    for expected in ["nearest", "radius", "pattern", "interactive", "serve", "convert", "Exit codes:"] {
+        assert!(stdout(&output).contains(expected), "{}", stdout(&output));
+    }
+}
+
+/// This test checks that a query without a subcommand finds the nearest numbers
+#[test]
+fn test_cli_nearest_without_subcommand() {
+    let output = run(&["phonescraped", "732-732-5555", "1"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325555\n".repeat(5));
+    assert_eq!(stderr(&output), "");
+    let subcommand = run(&["nearest", "phonescraped", "732-732-5555", "1"]);
+    assert_eq!(subcommand.stdout, output.stdout);
+}
+
+/// This test checks that k defaults to 1, the same way as `task2.py`
+#[test]
+fn test_cli_default_k() {
+    let output = run(&["phonescraped", "7327325555"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, run(&["phonescraped", "7327325555", "1"]).stdout);
+    let output = run(&["nearest", "phonescraped", "7327325555"]);
+    assert_eq!(output.stdout, run(&["phonescraped", "7327325555", "1"]).stdout);
+}
+
+/// This test checks that each record is parsed as one phone number, even with spaces in it,
+/// and that the lines that are not phone numbers are reported on stderr
+#[test]
+fn test_cli_delimiter() {
+    let path = std::env::temp_dir().join(format!("cli_delimiter_{}", std::process::id()));
+    std::fs::write(&path, "(732) 732-5555\n12345\n732 732 5556\n").unwrap();
+    let output = run(&[path.to_str().unwrap(), "7327325555", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325555\n7327325556\n");
+    assert_eq!(stderr(&output), format!("warning: skipped records in {} that are not phone numbers, on line 2\n", path.display()));
+    std::fs::write(&path, "732-732-5555,732-732-5554\n7327325553,phone\n").unwrap();
+    let output = run(&["--delimiter", "comma", path.to_str().unwrap(), "7327325555", "3"]);
+    assert_eq!(stdout(&output), "7327325555\n7327325554\n7327325553\n");
+    assert!(stderr(&output).ends_with("on line 2\n"), "{}", stderr(&output));
+    let output = run(&["pattern", path.to_str().unwrap(), "732", "--delimiter", "tab"]);
+    assert_eq!(stdout(&output), "7327325553 1\n");
+    assert!(stderr(&output).ends_with("on line 1\n"), "{}", stderr(&output));
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that a large file gives the same results whatever the number of threads it is read with
+#[test]
+fn test_cli_threads() {
+    let path = std::env::temp_dir().join(format!("cli_threads_{}", std::process::id()));
+    let contents: String = (0..200_000).map(|i| format!("({}) 555-{:04}\n", 200 + i % 7, i * 7919 % 10_000)).collect();
+    std::fs::write(&path, contents + "12345\n").unwrap();
+    let expected = run(&["--threads", "1", path.to_str().unwrap(), "2035555000", "5"]);
+    assert_eq!(expected.status.code(), Some(0));
+    assert!(stderr(&expected).ends_with("on line 200001\n"), "{}", stderr(&expected));
+    for threads in ["2", "7"] {
+        let output = run(&["--threads", threads, path.to_str().unwrap(), "2035555000", "5"]);
+        assert_eq!(output.stdout, expected.stdout);
+        assert_eq!(output.stderr, expected.stderr);
+    }
+    let output = run(&["--threads", "0", path.to_str().unwrap(), "2035555000"]);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that lines that are not valid UTF-8 are still read, with a warning on stderr
+#[test]
+fn test_cli_invalid_utf8() {
+    let path = std::env::temp_dir().join(format!("cli_invalid_utf8_{}", std::process::id()));
+    std::fs::write(&path, b"7327325555\n\xff(732) 732-5554\nn\xe9o\n").unwrap();
+    let output = run(&[path.to_str().unwrap(), "7327325555", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325555\n7327325554\n");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "warning: read lines in {0} that are not valid UTF-8, on lines 2, 3\n\
+             warning: skipped records in {0} that are not phone numbers, on line 3\n",
+            path.display()
+        )
+    );
+    let output = run(&["pattern", path.to_str().unwrap(), "732", "--output", "json"]);
+    assert!(stdout(&output).starts_with("{\"input\":{\"parsed\":2,\"rejected\":1}"), "{}", stdout(&output));
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that gzip and zstd files are decompressed as they are read by every subcommand
+#[test]
+fn test_cli_compressed() {
+    let contents = std::fs::read("phonescraped").unwrap();
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&contents).unwrap();
+    let gzip_path = std::env::temp_dir().join(format!("cli_compressed_{}.gz", std::process::id()));
+    std::fs::write(&gzip_path, gzip.finish().unwrap()).unwrap();
+    let zstd_path = std::env::temp_dir().join(format!("cli_compressed_{}", std::process::id()));
+    std::fs::write(&zstd_path, zstd::encode_all(&contents[..], 0).unwrap()).unwrap();
+    let expected = run(&["phonescraped", "7327325555", "2"]).stdout;
+    for path in [&gzip_path, &zstd_path] {
+        let output = run(&[path.to_str().unwrap(), "7327325555", "2"]);
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(output.stdout, expected);
+        let output = run(&["pattern", path.to_str().unwrap(), "732-73?-555?"]);
+        assert_eq!(output.stdout, run(&["pattern", "phonescraped", "732-73?-555?"]).stdout);
+    }
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(b"Call (732) 732-5555").unwrap();
+    std::fs::write(&gzip_path, gzip.finish().unwrap()).unwrap();
+    let output = run(&["extract", gzip_path.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "5\t7327325555\t(732) 732-5555\n");
+    std::fs::write(&gzip_path, "7327325555\n").unwrap();
+    let output = run(&[gzip_path.to_str().unwrap(), "7327325555"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("error: could not read"), "{}", stderr(&output));
+    std::fs::remove_file(&gzip_path).unwrap();
+    std::fs::remove_file(&zstd_path).unwrap();
+}
+
+/// This test checks that a file converted to a binary dataset answers the same queries,
+/// with tied numbers in the order they first appear as for text, and converts back to text that does too
+#[test]
+fn test_cli_convert() {
+    let binary_path = std::env::temp_dir().join(format!("cli_convert_{}.bin", std::process::id()));
+    let text_path = std::env::temp_dir().join(format!("cli_convert_{}.txt", std::process::id()));
+    let (binary, text) = (binary_path.to_str().unwrap(), text_path.to_str().unwrap());
+    let output = run(&["convert", "phonescraped", binary]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    let output = run(&[binary, "7327325550", "3"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325551\n7327325552\n7327325552\n7327325552\n7327325548\n7327325548\n7327325548\n");
+    for target in ["7327325550", "7327325553", "7327325545", "7327325560"] {
+        for k in ["1", "2", "5"] {
+            assert_eq!(run(&[binary, target, k]).stdout, run(&["phonescraped", target, k]).stdout, "{} {}", target, k);
+        }
+    }
+    let expected = run(&["radius", "phonescraped", "7327325550", "3"]).stdout;
+    assert_eq!(run(&["radius", binary, "7327325550", "3"]).stdout, expected);
+    let expected = run(&["pattern", "phonescraped", "732-73?-555?"]).stdout;
+    assert_eq!(run(&["pattern", binary, "732-73?-555?"]).stdout, expected);
+    let output = run(&["radius", binary, "7327325550", "1", "--output", "json"]);
+    assert_eq!(stdout(&output), "{\"input\":{\"parsed\":14,\"rejected\":0},\"target\":7327325550,\"radius\":1,\"metric\":\"absolute\",\"neighbours\":[{\"distance\":1,\"number\":7327325551,\"count\":1}]}\n");
+    assert_eq!(run(&["convert", binary, text]).status.code(), Some(0));
+    let mut expected: Vec<i64> = std::fs::read_to_string("phonescraped").unwrap().lines().map(|line| line.trim().parse().unwrap()).collect();
+    let mut converted: Vec<i64> = std::fs::read_to_string(&text_path).unwrap().lines().map(|line| line.parse().unwrap()).collect();
+    assert_eq!(run(&[text, "7327325550", "3"]).stdout, run(&["phonescraped", "7327325550", "3"]).stdout);
+    expected.sort_unstable();
+    converted.sort_unstable();
+    assert_eq!(converted, expected);
+    let mut corrupt = std::fs::read(&binary_path).unwrap();
+    *corrupt.last_mut().unwrap() ^= 1;
+    std::fs::write(&binary_path, corrupt).unwrap();
+    let output = run(&["convert", binary, text]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("checksum"), "{}", stderr(&output));
+    std::fs::remove_file(&binary_path).unwrap();
+    std::fs::remove_file(&text_path).unwrap();
+}
+
+/// This test checks that phone numbers are extracted from free text with their offsets,
+/// and that the other subcommands can search the numbers extracted from a file
+#[test]
+fn test_cli_extract() {
+    let path = std::env::temp_dir().join(format!("cli_extract_{}", std::process::id()));
+    std::fs::write(&path, "<p>Call (732) 732-5555 or +1 732.732.5554</p>\nOrder #7327325553 on 2023-10-19\n").unwrap();
+    let output = run(&["extract", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "8\t7327325555\t(732) 732-5555\n26\t7327325554\t+1 732.732.5554\n");
+    let output = run(&["--extract", path.to_str().unwrap(), "7327325553", "2"]);
+    assert_eq!(stdout(&output), "7327325554\n7327325555\n");
+    assert_eq!(stderr(&output), "");
+    let output = run(&["--extract", "--delimiter", "comma", path.to_str().unwrap(), "7327325553"]);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::write(&path, "Order #7327325553 on 2023-10-19\n").unwrap();
+    let output = run(&["extract", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that phone numbers are read from a column of a table,
+/// and that the results are printed with the rest of the row they were found in
+#[test]
+fn test_cli_table() {
+    let path = std::env::temp_dir().join(format!("cli_table_{}", std::process::id()));
+    std::fs::write(&path, "name,phone,source\n\"Smith, Jo\",(732) 732-5555,web\nAl,732-732-5554,shop\nKim,7327325555,api\n").unwrap();
+    let output = run(&["--format", "csv", "--column", "phone", path.to_str().unwrap(), "7327325555", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "7327325555\t\"Smith, Jo\",web\n7327325555\tKim,api\n7327325554\tAl,shop\n");
+    let output = run(&["radius", path.to_str().unwrap(), "7327325554", "0", "--format", "csv", "--field", "2"]);
+    assert_eq!(stdout(&output), "7327325554\tAl,shop\n");
+    let output = run(&["pattern", path.to_str().unwrap(), "732", "--format", "csv", "--column", "phone"]);
+    assert_eq!(stdout(&output), "7327325554 1\n7327325555 2\n");
+    let output = run(&["--format", "csv", "--column", "mobile", path.to_str().unwrap(), "7327325555"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("there is no column mobile in the header, which has name, phone, source"), "{}", stderr(&output));
+    let output = run(&["--format", "csv", path.to_str().unwrap(), "7327325555"]);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::write(&path, "{\"name\":\"Jo\",\"phone\":\"732-732-5555\"}\n").unwrap();
+    let output = run(&["--format", "jsonl", "--field", "phone", path.to_str().unwrap(), "7327325555"]);
+    assert_eq!(stdout(&output), "7327325555\t{\"name\":\"Jo\"}\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that results are printed as JSON, NDJSON and CSV with their distances and counts,
+/// along with how many records were read and rejected
+#[test]
+fn test_cli_output() {
+    let output = run(&["--output", "json", "phonescraped", "7327325555", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "{\"input\":{\"parsed\":14,\"rejected\":0},\"target\":7327325555,\"k\":2,\"metric\":\"absolute\",\"neighbours\":[\
+         {\"distance\":0,\"number\":7327325555,\"count\":5},{\"distance\":1,\"number\":7327325554,\"count\":1}]}\n"
+    );
+    let output = run(&["radius", "phonescraped", "7327325550", "1", "--output", "csv"]);
+    assert_eq!(stdout(&output), "distance,number,count\n1,7327325551,1\n");
+    let output = run(&["pattern", "phonescraped", "73273255?4", "--output", "ndjson"]);
+    assert_eq!(
+        stdout(&output),
+        "{\"type\":\"input\",\"parsed\":14,\"rejected\":0}\n{\"type\":\"query\",\"pattern\":\"73273255?4\"}\n{\"type\":\"match\",\"number\":7327325554,\"count\":1}\n"
+    );
+    let path = std::env::temp_dir().join(format!("cli_output_{}", std::process::id()));
+    std::fs::write(&path, "name,phone\nJo,732-732-5555\nAl,12345\nKim,7327325555\n").unwrap();
+    let output = run(&["--format", "csv", "--column", "phone", "--output", "ndjson", path.to_str().unwrap(), "7327325555"]);
+    assert_eq!(
+        stdout(&output),
+        "{\"type\":\"input\",\"parsed\":2,\"rejected\":1}\n{\"type\":\"query\",\"target\":7327325555,\"k\":1,\"metric\":\"absolute\"}\n\
+         {\"type\":\"neighbour\",\"distance\":0,\"number\":7327325555,\"count\":2,\"rows\":[\"Jo\",\"Kim\"]}\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+    let output = run(&["radius", "phonescraped", "1111111111", "5", "--output", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).ends_with("\"neighbours\":[]}\n"), "{}", stdout(&output));
+    let output = run(&["--output", "xml", "phonescraped", "7327325555"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+/// This test checks that invalid arguments are usage errors with the reason on stderr
+#[test]
+fn test_cli_usage_errors() {
+    let cases: [(&[&str], &str); 6] = [
+        (&[], "required arguments were not provided"),
+        (&["phonescraped"], "<TARGET_NUMBER>"),
+        (&["phonescraped", "7327325555", "two"], "invalid value 'two' for '[K]'"),
+        (&["phonescraped", "7327325555", "0"], "invalid value '0' for '[K]'"),
+        (&["phonescraped", "12345", "1"], "Phone number must be at least 10 digits long"),
+        (&["radius", "phonescraped", "7327325555", "1", "--metric", "euclidean"], "Metric must be one of"),
+    ];
+    for (args, expected) in cases {
+        let output = run(args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).contains(expected), "{:?}: {}", args, stderr(&output));
+        assert!(!stderr(&output).contains("panicked"), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stdout(&output), "");
+    }
+}
+
+/// This test checks that a missing file is an I/O error
+#[test]
+fn test_cli_missing_file() {
+    let output = run(&["does_not_exist", "7327325555", "1"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).starts_with("error: could not read does_not_exist"), "{}", stderr(&output));
+    let output = run(&["pattern", "does_not_exist", "732"]);
+    assert_eq!(output.status.code(), Some(3));
+}
+
+/// This test checks that queries that find nothing exit with the no result code
+#[test]
+fn test_cli_no_result() {
+    let output = run(&["radius", "phonescraped", "1111111111", "5"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    let output = run(&["pattern", "phonescraped", "999"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+/// This test checks the radius and pattern subcommands
+#[test]
+fn test_cli_radius_and_pattern() {
+    let output = run(&["radius", "phonescraped", "7327325550", "1", "--metric", "hamming"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = stdout(&output);
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.dedup();
+    assert_eq!(lines, vec!["7327325551", "7327325552", "7327325553", "7327325554", "7327325555"]);
+    let output = run(&["pattern", "phonescraped", "732-73?-555?"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "7327325551 1\n7327325552 3\n7327325553 1\n7327325554 1\n7327325555 5\n");
+}
+
+/// This test checks that the compressed index answers the same as counting the phone numbers,
+/// along with the records read and the rows of a table, for queries without tied distances
+#[test]
+fn test_cli_succinct() {
+    let path = std::env::temp_dir().join(format!("cli_succinct_{}", std::process::id()));
+    std::fs::write(&path, "name,phone\nJo,(732) 732-5555\nAl,732-732-5554\nKim,7327325555\n").unwrap();
+    let queries: [&[&str]; 4] = [
+        &["nearest", "phonescraped", "7327325555", "3", "--output", "json"],
+        &["radius", "phonescraped", "7327325555", "3", "--output", "json"],
+        &["nearest", path.to_str().unwrap(), "7327325555", "2", "--format", "csv", "--column", "phone"],
+        &["radius", path.to_str().unwrap(), "7327325554", "1", "--format", "csv", "--column", "phone"],
+    ];
+    for args in queries {
+        let expected = run(args);
+        assert_eq!(expected.status.code(), Some(0), "{:?}: {}", args, stderr(&expected));
+        let output = run(&[args, &["--succinct"]].concat());
+        assert_eq!(output.status.code(), Some(0), "{:?}: {}", args, stderr(&output));
+        assert_eq!(stdout(&output), stdout(&expected), "{:?}", args);
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks the interactive subcommand reads commands from stdin
+#[test]
+fn test_cli_interactive() {
+    let output = run_with_stdin(&["interactive", "phonescraped"], "insert 7327325556\nnearest 7327325556 1\nbogus\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "1\n7327325556\nerror: commands are insert, remove, decrement, nearest and radius\n");
+}
+
+/// This test checks that the service answers k-nearest and radius queries with the same numbers, in the same order,
+/// as the binary prints them, on a file where numbers at the same distance first appear in no particular order
+#[test]
+fn test_cli_matches_service() {
+    let path = std::env::temp_dir().join(format!("cli_matches_service_{}", std::process::id()));
+    std::fs::write(&path, "7327325557\n7327325553\n7327325557\n7327325556\n7327325550\n7327325554\n7327325560\n").unwrap();
+    let service = Service::load(&path, Layout::default()).unwrap();
+    for target in 7327325548_i64..7327325563 {
+        for k in 1..5 {
+            let output = run(&["nearest", path.to_str().unwrap(), &target.to_string(), &k.to_string(), "--output", "json"]);
+            let expected: Value = serde_json::from_slice(&output.stdout).unwrap();
+            let (status, body) = service.handle("GET", &format!("/nearest?target={}&k={}", target, k));
+            assert_eq!(status, 200);
+            assert_eq!(body["neighbours"], expected["neighbours"], "target {} k {}", target, k);
+            let output = run(&["radius", path.to_str().unwrap(), &target.to_string(), &k.to_string(), "--output", "json"]);
+            let expected: Value = serde_json::from_slice(&output.stdout).unwrap();
+            let (_, body) = service.handle("GET", &format!("/radius?target={}&radius={}", target, k));
+            assert_eq!(body["neighbours"], expected["neighbours"], "target {} radius {}", target, k);
+        }
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that the service refuses to bind to a public address
+#[test]
+fn test_cli_serve_public_address() {
+    let output = run(&["serve", "phonescraped", "--address", "0.0.0.0:8080"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("loopback"), "{}", stderr(&output));
+}
@@ -0,0 +1,253 @@
+//! A differential test runner that runs both the Rust binary and the Python `task2.py` on the same
+//! generated files and checks that they print exactly the same numbers in the same order, whether the Rust
+//! binary reads the file as text, as the binary dataset `convert` writes from it, or with `--succinct`.
+//! When they differ, the file and k are minimized into a small reproducer, which is written
+//! with a line by line diff of the outputs. The tests are skipped when no Python interpreter can be found;
+//! set `PYTHON` to choose one, and `CONFORMANCE_FIXTURES` to run more than 20 generated files.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use rand::Rng;
+use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+use golden::diff_lines;
+use question_2_rust::generate::{AreaCodes, Config, Generator};
+
+/// This function finds a Python interpreter that can run `task2.py`,
+/// resolving it to its executable so that shims such as pyenv are only run once
+fn find_python() -> Option<String> {
+    let candidates = match std::env::var("PYTHON") {
+        Ok(python) => vec![python],
+        Err(_) => vec!["python3".to_owned(), "python".to_owned()],
+    };
+    candidates.into_iter().find_map(|python| {
+        let output = Command::new(python)
+            .args(["-c", "import sys; assert sys.version_info >= (3, 7); print(sys.executable)"])
+            .output()
+            .ok()?;
+        let executable = String::from_utf8(output.stdout).ok()?;
+        output.status.success().then(|| executable.trim().to_owned())
+    })
+}
+
+/// This function returns the path to the Python implementation
+fn task2_py() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("Question 2").join("task2.py")
+}
+
+/// This function runs a command and returns its stdout, failing the test if it could not be run
+fn stdout(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The ways the Rust binary reads the same file, which must all print the same as `task2.py`
+const PATHS: [&str; 3] = ["text", "binary", "succinct"];
+
+/// This function runs the Rust binary on the file in the first argument in every way it can read it
+/// # Arguments
+/// * `args` - the file, target and k that `task2.py` is run with
+/// # Returns
+/// * `Vec<String>` - what the Rust binary printed for each of `PATHS`
+fn run_rust(args: &[String]) -> Vec<String> {
+    let binary = format!("{}.bin", args[0]);
+    let converted = Command::new(env!("CARGO_BIN_EXE_Question_2_rust")).args(["convert", &args[0], &binary]).output().unwrap();
+    assert!(converted.status.success(), "could not convert {}: {}", args[0], String::from_utf8_lossy(&converted.stderr));
+    let outputs = vec![
+        stdout(Command::new(env!("CARGO_BIN_EXE_Question_2_rust")).args(args)),
+        stdout(Command::new(env!("CARGO_BIN_EXE_Question_2_rust")).arg(&binary).args(&args[1..])),
+        stdout(Command::new(env!("CARGO_BIN_EXE_Question_2_rust")).args(args).arg("--succinct")),
+    ];
+    fs::remove_file(&binary).unwrap();
+    outputs
+}
+
+/// This function finds the first way of reading the file on which the Rust binary printed something else
+/// than `task2.py`
+/// # Returns
+/// * `Option<(&str, &String)>` - the way it was read and what the Rust binary printed, or None if they all match
+fn first_difference<'a>(expected: &str, actual: &'a [String]) -> Option<(&'static str, &'a String)> {
+    PATHS.iter().zip(actual).find(|(_, actual)| *actual != expected).map(|(path, actual)| (*path, actual))
+}
+
+/// One run of both implementations
+/// * `lines` - the lines of the file, without their line endings
+/// * `line_ending` - what every line ends with
+/// * `target` - the target phone number
+/// * `k` - the number of unique nearest numbers, or None to leave it out
+#[derive(Debug, Clone, PartialEq)]
+struct Case {
+    lines: Vec<String>,
+    line_ending: &'static str,
+    target: i64,
+    k: Option<u64>,
+}
+
+impl Case {
+    /// This function writes the file of the case to the given path and returns the arguments
+    /// that both implementations are run with
+    fn write(&self, path: &Path) -> Vec<String> {
+        let contents: String = self.lines.iter().map(|line| format!("{}{}", line, self.line_ending)).collect();
+        fs::write(path, contents).unwrap();
+        let mut args = vec![path.to_str().unwrap().to_owned(), self.target.to_string()];
+        args.extend(self.k.map(|k| k.to_string()));
+        args
+    }
+
+    /// This function runs both implementations on the case, writing its file to the given path
+    /// # Returns
+    /// * `(String, Vec<String>)` - what `task2.py` printed, and what the Rust binary printed for each of `PATHS`
+    fn run(&self, python: &str, path: &Path) -> (String, Vec<String>) {
+        let args = self.write(path);
+        let expected = stdout(Command::new(python).arg(task2_py()).args(&args));
+        (expected, run_rust(&args))
+    }
+}
+
+/// This function generates a case whose file has phone numbers drawn from a small pool,
+/// so that there are plenty of duplicates and numbers at the same distance from a target
+fn generate_pool_case(rng: &mut ChaChaRng) -> Case {
+    let base: i64 = rng.gen_range(2000000000..9000000000);
+    let spread = rng.gen_range(1..200);
+    let pool: Vec<i64> = (0..rng.gen_range(1..30)).map(|_| base + rng.gen_range(-spread..=spread)).collect();
+    let lines = (0..rng.gen_range(1..200)).map(|_| pool[rng.gen_range(0..pool.len())].to_string()).collect();
+    Case { lines, line_ending: if rng.gen_bool(0.5) { "\n" } else { "\r\n" }, target: 0, k: None }
+}
+
+/// This function generates a case whose file is a synthetic dataset like `phonescraped`, with every number
+/// sharing an area code and exchange and some of them repeated
+fn generate_dataset_case(rng: &mut ChaChaRng) -> Case {
+    let prefix = rng.gen_range(200..1000) * 1000 + rng.gen_range(200..1000);
+    let config = Config {
+        duplicate_rate: rng.gen_range(0.0..0.6),
+        area_codes: AreaCodes::Prefixes(vec![prefix]),
+        ..Config::default()
+    };
+    let lines = Generator::new(config, rng.gen()).take(rng.gen_range(1..500)).map(|line| line.text).collect();
+    Case { lines, line_ending: if rng.gen_bool(0.5) { "\n" } else { "\r\n" }, target: 0, k: None }
+}
+
+/// This function shrinks a case on which the implementations differ, first by removing
+/// runs of lines from the file, halving their length whenever none can be removed, and then by lowering k
+/// # Arguments
+/// * `case` - the case to shrink
+/// * `differs` - whether the implementations still differ on a smaller case
+/// # Returns
+/// * `Case` - the smallest case found, which still has at least one line
+fn minimize(mut case: Case, mut differs: impl FnMut(&Case) -> bool) -> Case {
+    let mut chunk = case.lines.len() / 2;
+    while chunk > 0 {
+        let mut removed = false;
+        let mut start = 0;
+        while start < case.lines.len() {
+            let mut smaller = case.clone();
+            smaller.lines.drain(start..(start + chunk).min(case.lines.len()));
+            if !smaller.lines.is_empty() && differs(&smaller) {
+                case = smaller;
+                removed = true;
+            } else {
+                start += chunk;
+            }
+        }
+        if !removed {
+            chunk /= 2;
+        }
+    }
+    if let Some(k) = case.k {
+        if let Some(smaller) = (1..k).map(|k| Case { k: Some(k), ..case.clone() }).find(|smaller| differs(smaller)) {
+            case = smaller;
+        }
+    }
+    case
+}
+
+/// This test runs both implementations on generated files with targets that are in the file,
+/// halfway between numbers in the file and far away from them, with and without k,
+/// and checks that their outputs are identical however the Rust binary reads the file.
+/// Any difference is minimized into a reproducer
+/// in the `differential` directory of the Cargo target directory.
+#[test]
+fn test_conformance_with_task2_py() {
+    let Some(python) = find_python() else {
+        eprintln!("skipping: no Python 3.7+ interpreter found, set PYTHON to run the conformance tests");
+        return;
+    };
+    let fixtures: u64 = std::env::var("CONFORMANCE_FIXTURES").map_or(20, |fixtures| fixtures.parse().unwrap());
+    let directory = std::env::temp_dir().join(format!("conformance_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    let mut rng = ChaChaRng::seed_from_u64(32);
+    for fixture in 0..fixtures {
+        let case = match fixture % 2 {
+            0 => generate_pool_case(&mut rng),
+            _ => generate_dataset_case(&mut rng),
+        };
+        let path = directory.join(format!("fixture_{}", fixture));
+        let first: i64 = case.lines[rng.gen_range(0..case.lines.len())].parse().unwrap();
+        let second: i64 = case.lines[rng.gen_range(0..case.lines.len())].parse().unwrap();
+        for target in [first, (first + second) / 2, first - 1000, first + 1000] {
+            let k = rng.gen_range(1..12);
+            for k in [None, Some(k)] {
+                let case = Case { target, k, ..case.clone() };
+                let (expected, actual) = case.run(&python, &path);
+                assert!(!expected.is_empty(), "task2.py printed nothing for {:?}", case);
+                if first_difference(&expected, &actual).is_none() {
+                    continue;
+                }
+                let smallest = minimize(case, |smaller| {
+                    let (expected, actual) = smaller.run(&python, &path);
+                    first_difference(&expected, &actual).is_some()
+                });
+                let reproducer = Path::new(env!("CARGO_TARGET_TMPDIR")).join("differential");
+                fs::create_dir_all(&reproducer).unwrap();
+                let args = smallest.write(&reproducer.join(format!("fixture_{}", fixture)));
+                let (expected, actual) = smallest.run(&python, &path);
+                let (read_as, actual) = first_difference(&expected, &actual).unwrap();
+                panic!(
+                    "outputs differ for the arguments {:?} with the file read as {}, reduced to a file of {} lines:\n{}\n\
+                     the lines task2.py printed are marked -, and those the Rust binary printed +:\n{}",
+                    args,
+                    read_as,
+                    smallest.lines.len(),
+                    smallest.lines.join("\n"),
+                    diff_lines(&expected, actual).unwrap_or_default(),
+                );
+            }
+        }
+    }
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+/// This test checks that a difference is shrunk to the lines and k that cause it, without needing Python
+#[test]
+fn test_minimize() {
+    let lines: Vec<String> = (0..100).map(|number| (7327320000_i64 + number).to_string()).collect();
+    let case = Case { lines, line_ending: "\n", target: 7327320000, k: Some(8) };
+    // pretend the implementations differ whenever both of two numbers are in the file and k is at least 3
+    let smallest = minimize(case, |case| {
+        let has = |number: &str| case.lines.iter().any(|line| line == number);
+        has("7327320017") && has("7327320071") && case.k >= Some(3)
+    });
+    assert_eq!(smallest.lines, vec!["7327320017", "7327320071"]);
+    assert_eq!(smallest.k, Some(3));
+}
+
+/// This test runs both implementations on the provided dataset, reading it every way the Rust binary can
+#[test]
+fn test_conformance_on_phonescraped() {
+    let Some(python) = find_python() else {
+        eprintln!("skipping: no Python 3.7+ interpreter found, set PYTHON to run the conformance tests");
+        return;
+    };
+    // the binary dataset is written next to a copy, since the dataset is checked in
+    let path = std::env::temp_dir().join(format!("conformance_phonescraped_{}", std::process::id()));
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("Question 2").join("phonescraped"), &path).unwrap();
+    for (target, k) in [("7327325555", None), ("7327325550", Some("3")), ("1000000000", Some("2")), ("9999999999", Some("10"))] {
+        let mut args = vec![path.to_str().unwrap().to_owned(), target.to_owned()];
+        args.extend(k.map(str::to_owned));
+        let expected = stdout(Command::new(&python).arg(task2_py()).args(&args));
+        for (read_as, actual) in PATHS.iter().zip(run_rust(&args)) {
+            assert_eq!(actual, expected, "outputs differ for {:?} with the file read as {}", args, read_as);
+        }
+    }
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,75 @@
+//! Tests that run the dataset generator and read what it writes with the binary.
+
+use std::process::{Command, Output};
+
+/// This function runs the dataset generator with the given arguments
+fn run_gen(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gen")).args(args).output().unwrap()
+}
+
+/// This test checks that the same seed always writes the same lines, and that the styles and prefixes are followed
+#[test]
+fn test_gen_is_reproducible() {
+    let args = ["--size", "50", "--seed", "3", "--area-codes", "732732", "--styles", "dashes"];
+    let output = run_gen(&args);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stderr, b"");
+    let lines = String::from_utf8(output.stdout.clone()).unwrap();
+    assert_eq!(lines.lines().count(), 50);
+    assert!(lines.lines().all(|line| line.len() == 12 && line.starts_with("732-732-")), "{}", lines);
+    assert_eq!(run_gen(&args).stdout, output.stdout);
+    assert_ne!(run_gen(&["--size", "50", "--seed", "4", "--area-codes", "732732", "--styles", "dashes"]).stdout, output.stdout);
+}
+
+/// This test checks that the binary reads every valid line the generator writes and rejects every malformed one
+#[test]
+fn test_gen_malformed_lines_are_rejected() {
+    let path = std::env::temp_dir().join(format!("gen_{}.txt", std::process::id()));
+    let output = run_gen(&[
+        "--size", "1000", "--seed", "5", "--malformed", "0.2", "--duplicate-rate", "0.5", "--crlf",
+        "--styles", "plain,dashes,dots,spaces,parentheses,international,country-code",
+        "--output", path.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let malformed: usize = stderr
+        .strip_prefix("wrote 1000 lines, ")
+        .and_then(|rest| rest.split_once(' '))
+        .map(|(malformed, _)| malformed.parse().unwrap())
+        .unwrap_or_else(|| panic!("{}", stderr));
+    assert!((150..250).contains(&malformed), "{}", stderr);
+    assert!(std::fs::read_to_string(&path).unwrap().ends_with("\r\n"));
+    let output = Command::new(env!("CARGO_BIN_EXE_Question_2_rust"))
+        .args([path.to_str().unwrap(), "5000000000", "1", "--output", "json"])
+        .output()
+        .unwrap();
+    let result: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["input"]["parsed"], 1000 - malformed);
+    assert_eq!(result["input"]["rejected"], malformed);
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// This test checks that invalid settings are usage errors
+#[test]
+fn test_gen_usage_errors() {
+    for args in [
+        vec!["--malformed", "1.5"],
+        vec!["--duplicate-rate", "-0.1"],
+        vec!["--area-codes", "012"],
+        vec!["--area-codes", "zipf:x"],
+        vec!["--styles", "hyphens"],
+    ] {
+        let output = run_gen(&args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).starts_with("error:"), "{:?}", args);
+    }
+}
+
+/// This test checks that a file that cannot be written is an I/O error
+#[test]
+fn test_gen_write_error() {
+    let path = std::env::temp_dir().join(format!("gen_missing_{}", std::process::id())).join("dataset.txt");
+    let output = run_gen(&["--output", path.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: could not write the dataset"));
+}
@@ -0,0 +1,14 @@
+//! Golden file tests that run the compiled binary on every case in `tests/golden` and compare its stdout,
+//! stderr and exit status with the expected ones checked in next to the case, with the harness in the `golden` crate.
+//! After changing the output on purpose, run `BLESS=1 cargo test --test golden` to write the expected files again
+//! and review them with `git diff`. A new case only needs its `args` and inputs before it is blessed.
+
+use std::path::Path;
+
+/// This test runs every golden case and checks that the output is exactly the expected output,
+/// or writes the expected files from the output when `BLESS` is set
+#[test]
+fn test_golden() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+    golden::check_cases(Path::new(env!("CARGO_BIN_EXE_Question_2_rust")), &root);
+}
@@ -0,0 +1,105 @@
+//! Tests of how much memory is allocated at the peak while the phone numbers are indexed,
+//! measured by an allocator that counts the bytes of every allocation of this test binary.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use question_2_rust::succinct::{SuccinctIndex, SuccinctIndexBuilder};
+use question_2_rust::PhoneCounts;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+/// The system allocator, keeping count of the bytes allocated now and at the peak
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if !pointer.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// This function runs a closure and measures the most memory it allocated at once
+/// # Returns
+/// * `(T, usize)` - what the closure returned, and the bytes allocated at the peak beyond those allocated before it ran
+fn peak_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = CURRENT.load(Ordering::SeqCst);
+    PEAK.store(before, Ordering::SeqCst);
+    let result = f();
+    (result, PEAK.load(Ordering::SeqCst) - before)
+}
+
+/// This function generates the same random phone numbers for every call, a few of them repeated
+fn phone_numbers(count: usize) -> impl Iterator<Item = i64> {
+    let mut rng = ChaChaRng::seed_from_u64(29);
+    (0..count).map(move |_| rng.gen_range(2_000_000_000..10_000_000_000))
+}
+
+/// This test checks that building the compressed index from the phone numbers as they are read, as `--succinct` does,
+/// needs less than half the memory at its peak of counting the same phone numbers in a hash map and less than
+/// a list of them, allocating little more than the index and the builder's buffer, and that the index itself
+/// is built from sorted numbers without allocating much more than its own size
+#[test]
+fn test_succinct_index_peak_memory() {
+    const COUNT: usize = 1_000_000;
+    const BUFFER: usize = 1 << 16;
+    let (counts, counting_peak) = peak_during(|| {
+        let mut counts = PhoneCounts::default();
+        phone_numbers(COUNT).for_each(|number| counts.add(number));
+        counts
+    });
+    let unique = counts.len();
+    drop(counts);
+
+    let (index, builder_peak) = peak_during(|| {
+        // pushed one at a time, as the phone numbers of a file are read
+        let mut builder = SuccinctIndexBuilder::with_buffer(BUFFER);
+        phone_numbers(COUNT).for_each(|number| builder.push(number));
+        builder.finish().unwrap()
+    });
+    assert_eq!(index.len(), unique);
+    assert!(
+        builder_peak < counting_peak / 2,
+        "the succinct index needed {} bytes at its peak and the hash map {}",
+        builder_peak,
+        counting_peak
+    );
+    assert!(
+        builder_peak < COUNT * std::mem::size_of::<i64>(),
+        "the succinct index needed {} bytes at its peak, more than a list of the phone numbers",
+        builder_peak
+    );
+    // each number in the buffer is kept with its position, and the index is built while the runs are merged
+    let expected = index.size_in_bytes() + BUFFER * 16;
+    assert!(
+        builder_peak <= expected + expected / 10,
+        "building an index of {} bytes allocated {} bytes at its peak",
+        index.size_in_bytes(),
+        builder_peak
+    );
+
+    let mut numbers: Vec<i64> = phone_numbers(COUNT).collect();
+    numbers.sort_unstable();
+    let (index, index_peak) = peak_during(|| SuccinctIndex::from_sorted(&numbers).unwrap());
+    assert!(
+        index_peak <= index.size_in_bytes() + index.size_in_bytes() / 10,
+        "building an index of {} bytes allocated {} bytes at its peak",
+        index.size_in_bytes(),
+        index_peak
+    );
+}
@@ -0,0 +1,156 @@
+//! Golden file tests shared by both tasks, which run a compiled binary on every case in a directory and
+//! compare its stdout, stderr and exit status with the expected ones checked in next to the case.
+//! Each case is a directory with an `args` file of the arguments, one per line, an optional `stdin` file,
+//! and any input files the arguments name, which are found relative to the directory.
+//! After changing the output on purpose, run `BLESS=1 cargo test --test golden` to write the expected files again
+//! and review them with `git diff`. A new case only needs its `args` and inputs before it is blessed.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// The files with the expected stdout, stderr and exit status of a case
+const EXPECTED: [&str; 3] = ["expected.stdout", "expected.stderr", "expected.status"];
+
+/// This function runs the binary on a case from its directory
+/// # Arguments
+/// * `binary` - the path of the binary, such as `env!("CARGO_BIN_EXE_qn_1")`
+/// * `case` - the directory of the case
+/// # Returns
+/// * `[String; 3]` - what it printed to stdout and stderr and its exit status, in the order of `EXPECTED`
+fn run_case(binary: &Path, case: &Path) -> [String; 3] {
+    let args = fs::read_to_string(case.join("args")).unwrap();
+    let stdin = fs::read(case.join("stdin")).unwrap_or_default();
+    let mut child = Command::new(binary)
+        .args(args.lines())
+        .current_dir(case)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // stdin is written from another thread while the output is read, as `Command::output` does, so that
+    // a binary that fills its stdout before reading all of stdin does not wait on the test forever
+    let mut child_stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || match child_stdin.write_all(&stdin) {
+        // a binary may exit without reading all of stdin, such as on a usage error
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    });
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    let status = output.status.code().map_or("killed by a signal".to_owned(), |code| code.to_string());
+    [
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        format!("{}\n", status),
+    ]
+}
+
+/// This function lists the lines that differ between the expected and actual output,
+/// with `-` before the expected line and `+` before the actual one. Lines are compared and shown
+/// with their line endings, so that a line ending in `\r\n` rather than `\n` is a difference too.
+/// # Returns
+/// * `Option<String>` - every line that differs, or None if the outputs are the same
+/// # Example
+/// ```
+/// assert_eq!(golden::diff_lines("1\n2\n", "1\n2\n"), None);
+/// assert_eq!(golden::diff_lines("1\n2\n", "1\n"), Some("    2 - \"2\\n\"".to_owned()));
+/// ```
+pub fn diff_lines(expected: &str, actual: &str) -> Option<String> {
+    let expected: Vec<&str> = expected.split_inclusive('\n').collect();
+    let actual: Vec<&str> = actual.split_inclusive('\n').collect();
+    let differences: Vec<String> = (0..expected.len().max(actual.len()))
+        .filter(|line| expected.get(*line) != actual.get(*line))
+        .flat_map(|line| {
+            let removed = expected.get(line).map(|text| format!("  {:>3} - {:?}", line + 1, text));
+            let added = actual.get(line).map(|text| format!("  {:>3} + {:?}", line + 1, text));
+            removed.into_iter().chain(added)
+        })
+        .collect();
+    (!differences.is_empty()).then(|| differences.join("\n"))
+}
+
+/// This function runs the binary on every case in a directory and panics with every difference from
+/// the expected output, or writes the expected files from the output when `BLESS` is set
+/// # Arguments
+/// * `binary` - the path of the binary, such as `env!("CARGO_BIN_EXE_qn_1")`
+/// * `root` - the directory with a directory for each case
+/// # Panics
+/// If the output of any case is not exactly its expected output, or there are no cases
+pub fn check_cases(binary: &Path, root: &Path) {
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut cases: Vec<PathBuf> = fs::read_dir(root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no cases in {}", root.display());
+    let mut failures = Vec::new();
+    for case in &cases {
+        let name = case.file_name().unwrap().to_string_lossy();
+        for (file, actual) in EXPECTED.iter().zip(run_case(binary, case)) {
+            let path = case.join(file);
+            if bless {
+                fs::write(&path, actual).unwrap();
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(expected) => {
+                    if let Some(differences) = diff_lines(&expected, &actual) {
+                        failures.push(format!("{}/{} differs:\n{}", name, file, differences));
+                    }
+                }
+                Err(_) => failures.push(format!("{}/{} is missing", name, file)),
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\n\nif the new output is right, run `BLESS=1 cargo test --test golden` to update the expected files",
+        failures.join("\n\n")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// This test checks that outputs are compared line by line, with their line endings
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(diff_lines("1\n2\n", "1\n2\n"), None);
+        assert_eq!(
+            diff_lines("1\n2\n3\n", "1\n4\n3\r\n"),
+            Some("    2 - \"2\\n\"\n    2 + \"4\\n\"\n    3 - \"3\\n\"\n    3 + \"3\\r\\n\"".to_owned())
+        );
+        assert_eq!(diff_lines("1\n", ""), Some("    1 - \"1\\n\"".to_owned()));
+        assert_eq!(diff_lines("", "1"), Some("    1 + \"1\"".to_owned()));
+    }
+
+    /// This function writes a case with the given arguments and stdin to a new directory
+    fn temp_case(name: &str, args: &str, stdin: &[u8]) -> PathBuf {
+        let case = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        fs::create_dir_all(&case).unwrap();
+        fs::write(case.join("args"), args).unwrap();
+        fs::write(case.join("stdin"), stdin).unwrap();
+        case
+    }
+
+    /// This test checks that a case whose stdin and stdout are both larger than a pipe holds is run
+    /// without waiting forever, and that a binary which exits without reading stdin is not an error
+    #[cfg(unix)]
+    #[test]
+    fn test_run_case_with_large_stdin() {
+        let stdin = "7327325555\n".repeat(1 << 16);
+        let case = temp_case("golden_large_stdin", "", stdin.as_bytes());
+        let [stdout, stderr, status] = run_case(Path::new("cat"), &case);
+        assert_eq!((stdout == stdin, stderr.as_str(), status.as_str()), (true, "", "0\n"));
+        let [stdout, _, status] = run_case(Path::new("true"), &case);
+        assert_eq!((stdout.as_str(), status.as_str()), ("", "0\n"));
+        fs::remove_dir_all(&case).unwrap();
+    }
+}
@@ -0,0 +1,496 @@
+//! A compact binary format for counted phone numbers, which can be queried through a memory map
+//! without parsing any text.
+//!
+//! Every value is a little-endian `u64`, apart from the magic bytes and the version:
+//!
+//! | Offset     | Size   | Field                                                              |
+//! |------------|--------|--------------------------------------------------------------------|
+//! | 0          | 4      | the magic bytes `PHNB`                                             |
+//! | 4          | 4      | the version of the format, as a little-endian `u32`                |
+//! | 8          | 8      | the number of unique phone numbers `n`                             |
+//! | 16         | 8      | the number of phone numbers, including duplicates                  |
+//! | 24         | 8      | the lower median, the phone number at index `(total - 1) / 2`      |
+//! | 32         | 8      | the upper median, the phone number at index `total / 2`            |
+//! | 40         | 8      | the FNV-1a checksum of every other byte of the file                |
+//! | 48         | 8n     | the unique phone numbers, in ascending order                       |
+//! | 48 + 8n    | 8n     | the number of times each phone number appears                      |
+//! | 48 + 16n   | 8n     | the order each phone number first appeared in, from 0              |
+//!
+//! The order of first appearance is kept so that numbers at the same distance from a target are
+//! found in the order they appeared in the text the dataset was converted from, as `kth_nearest` finds them.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::{Neighbour, PhoneCounts};
+
+/// The magic bytes every binary dataset starts with
+pub const MAGIC: [u8; 4] = *b"PHNB";
+/// The version of the format written by `write_binary`
+pub const VERSION: u32 = 2;
+/// The size of the header, which is followed by the phone numbers, their counts and the order they first appeared in
+const HEADER_SIZE: usize = 48;
+/// The size of each unique phone number with its count and the order it first appeared in
+const ENTRY_SIZE: usize = 24;
+/// Where the checksum is stored in the header
+const CHECKSUM_OFFSET: usize = 40;
+
+/// Writes counted phone numbers in the binary format
+/// # Arguments
+/// * `writer` - where to write the dataset
+/// * `counts` - the phone numbers to write, with the number of times each appears
+/// # Errors
+/// This function will return an error if the dataset cannot be written,
+/// or with the `InvalidInput` kind if any phone number is negative
+/// # Example
+/// ```rust
+/// use phone_io::{binary::{write_binary, BinaryDataset}, PhoneCounts};
+/// let mut bytes = Vec::new();
+/// write_binary(&mut bytes, &PhoneCounts::from_numbers(&[7327325555, 7327325554, 7327325555])).unwrap();
+/// let dataset = BinaryDataset::from_bytes(bytes).unwrap();
+/// assert_eq!(dataset.iter().collect::<Vec<_>>(), vec![(7327325554, 1), (7327325555, 2)]);
+/// assert_eq!(dataset.to_counts().iter().collect::<Vec<_>>(), vec![(7327325555, 2), (7327325554, 1)]);
+/// assert_eq!(dataset.median(), Some((7327325555, None)));
+/// ```
+pub fn write_binary<W: Write>(writer: W, counts: &PhoneCounts) -> io::Result<()> {
+    let mut entries: Vec<(u64, u64, u64)> = Vec::with_capacity(counts.len());
+    for (first_seen, (number, count)) in counts.iter().enumerate() {
+        let number = u64::try_from(number)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a phone number", number)))?;
+        entries.push((number, count as u64, first_seen as u64));
+    }
+    entries.sort_unstable();
+    let total = counts.total() as u64;
+    let (low, high) = match total {
+        0 => (0, 0),
+        _ => (nth_number(&entries, (total - 1) / 2), nth_number(&entries, total / 2)),
+    };
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    for value in [entries.len() as u64, total, low, high, 0] {
+        header.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut checksum = Checksum::default();
+    checksum.update(&header[..CHECKSUM_OFFSET]);
+    entries.iter().for_each(|(number, _, _)| checksum.update(&number.to_le_bytes()));
+    entries.iter().for_each(|(_, count, _)| checksum.update(&count.to_le_bytes()));
+    entries.iter().for_each(|(_, _, first_seen)| checksum.update(&first_seen.to_le_bytes()));
+    header[CHECKSUM_OFFSET..].copy_from_slice(&checksum.0.to_le_bytes());
+    let mut writer = BufWriter::new(writer);
+    writer.write_all(&header)?;
+    entries.iter().try_for_each(|(number, _, _)| writer.write_all(&number.to_le_bytes()))?;
+    entries.iter().try_for_each(|(_, count, _)| writer.write_all(&count.to_le_bytes()))?;
+    entries.iter().try_for_each(|(_, _, first_seen)| writer.write_all(&first_seen.to_le_bytes()))?;
+    writer.flush()
+}
+
+/// Finds the phone number at an index of the sorted list with every duplicate repeated
+fn nth_number(entries: &[(u64, u64, u64)], index: u64) -> u64 {
+    let mut seen = 0;
+    for (number, count, _) in entries {
+        seen += count;
+        if index < seen {
+            return *number;
+        }
+    }
+    0
+}
+
+/// Whether a file is a binary dataset, judging by its magic bytes
+/// # Errors
+/// This function will return an error if the file cannot be opened or read
+pub fn is_binary_file(path: impl AsRef<Path>) -> io::Result<bool> {
+    let mut magic = Vec::with_capacity(MAGIC.len());
+    File::open(path)?.take(MAGIC.len() as u64).read_to_end(&mut magic)?;
+    Ok(magic == MAGIC)
+}
+
+/// The bytes of a binary dataset, either mapped from a file or held in memory
+#[derive(Debug)]
+enum Bytes {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Deref for Bytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bytes::Mapped(map) => map,
+            Bytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// A binary dataset of counted phone numbers, which is read in place.
+/// Opening it only checks its header and size, so every query only touches the pages it needs:
+/// the median is read from the header, and the nearest numbers are found with a binary search.
+#[derive(Debug)]
+pub struct BinaryDataset {
+    bytes: Bytes,
+    len: usize,
+}
+
+impl BinaryDataset {
+    /// Maps a binary dataset from a file
+    /// # Arguments
+    /// * `path` - the path of the file
+    /// # Errors
+    /// This function will return an error if the file cannot be mapped,
+    /// or with the `InvalidData` kind if it is not a binary dataset of a supported version
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read, and the format is checked before any of it is used.
+        // Like any memory map, it relies on the file not being truncated while it is open.
+        let map = unsafe { Mmap::map(&file)? };
+        BinaryDataset::new(Bytes::Mapped(map))
+    }
+
+    /// Reads a binary dataset from bytes held in memory
+    /// # Errors
+    /// This function will return an error with the `InvalidData` kind if the bytes are not
+    /// a binary dataset of a supported version
+    pub fn from_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        BinaryDataset::new(Bytes::Owned(bytes))
+    }
+
+    /// Checks the header of a binary dataset and that it is the size the header says
+    fn new(bytes: Bytes) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < HEADER_SIZE || bytes[..4] != MAGIC {
+            return Err(invalid("not a binary phone number dataset".to_owned()));
+        }
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        if version != VERSION {
+            return Err(invalid(format!("version {} of the binary format is not supported, only version {}", version, VERSION)));
+        }
+        let len = read_u64(&bytes, 8) as usize;
+        let expected = len.checked_mul(ENTRY_SIZE).and_then(|size| size.checked_add(HEADER_SIZE));
+        if expected != Some(bytes.len()) {
+            return Err(invalid(format!("the file should hold {} phone numbers, but it is {} bytes long", len, bytes.len())));
+        }
+        Ok(BinaryDataset { bytes, len })
+    }
+
+    /// Checks the checksum of the whole dataset, which reads every page of it
+    /// # Errors
+    /// This function will return an error with the `InvalidData` kind if the checksum does not match
+    pub fn verify(&self) -> io::Result<()> {
+        let mut checksum = Checksum::default();
+        checksum.update(&self.bytes[..CHECKSUM_OFFSET]);
+        checksum.update(&self.bytes[HEADER_SIZE..]);
+        match checksum.0 == read_u64(&self.bytes, CHECKSUM_OFFSET) {
+            true => Ok(()),
+            false => Err(io::Error::new(io::ErrorKind::InvalidData, "the checksum does not match, so the file is corrupt")),
+        }
+    }
+
+    /// The number of unique phone numbers
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether there are no phone numbers
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of phone numbers, including duplicates
+    pub fn total(&self) -> usize {
+        read_u64(&self.bytes, 16) as usize
+    }
+
+    /// The median of the phone numbers with every duplicate repeated, read from the header.
+    /// This is the same as `find_median_values` in Task 1.
+    /// # Returns
+    /// * `Option<(i64, Option<i64>)>` - the median, and the upper median if there is an even number
+    ///   of phone numbers, or None if there are no phone numbers
+    pub fn median(&self) -> Option<(i64, Option<i64>)> {
+        let (low, high) = (read_u64(&self.bytes, 24) as i64, read_u64(&self.bytes, 32) as i64);
+        match self.total() {
+            0 => None,
+            total if total % 2 == 0 => Some((low, Some(high))),
+            _ => Some((low, None)),
+        }
+    }
+
+    /// The i-th smallest unique phone number
+    pub fn number(&self, i: usize) -> i64 {
+        read_u64(&self.bytes, HEADER_SIZE + 8 * i) as i64
+    }
+
+    /// The number of times the i-th smallest unique phone number appears
+    pub fn count_at(&self, i: usize) -> usize {
+        read_u64(&self.bytes, HEADER_SIZE + 8 * (self.len + i)) as usize
+    }
+
+    /// The number of times a phone number appears, found with a binary search
+    pub fn count(&self, number: i64) -> usize {
+        let rank = self.rank(number);
+        match rank < self.len && self.number(rank) == number {
+            true => self.count_at(rank),
+            false => 0,
+        }
+    }
+
+    /// Where the i-th smallest unique phone number first appeared among the unique phone numbers
+    pub fn first_seen_at(&self, i: usize) -> u64 {
+        read_u64(&self.bytes, HEADER_SIZE + 8 * (2 * self.len + i))
+    }
+
+    /// Each unique phone number with the number of times it appears, in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = (i64, usize)> + '_ {
+        (0..self.len).map(|i| (self.number(i), self.count_at(i)))
+    }
+
+    /// The indexes of the unique phone numbers in the order they first appeared
+    fn first_seen_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.len).collect();
+        // a corrupt dataset can repeat an order, so ties are broken by the number to stay deterministic
+        order.sort_unstable_by_key(|i| (self.first_seen_at(*i), *i));
+        order
+    }
+
+    /// Every phone number with the number of times it appears, counted in the order they first appeared,
+    /// so that it is the same as the counts the dataset was written from
+    pub fn to_counts(&self) -> PhoneCounts {
+        let mut counts = PhoneCounts::default();
+        self.first_seen_order().into_iter().for_each(|i| counts.insert(self.number(i), self.count_at(i)));
+        counts
+    }
+
+    /// Every phone number in ascending order, with every duplicate repeated
+    pub fn numbers(&self) -> Vec<i64> {
+        let mut numbers = Vec::with_capacity(self.total());
+        self.iter().for_each(|(number, count)| numbers.extend(std::iter::repeat_n(number, count)));
+        numbers
+    }
+
+    /// Writes every phone number as text, one per line in the order they first appeared,
+    /// with every duplicate repeated after it so that the file can be read like any other
+    /// and answers the same queries as the text the dataset was converted from
+    /// # Errors
+    /// This function will return an error if the text cannot be written
+    pub fn write_text<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = BufWriter::new(writer);
+        for i in self.first_seen_order() {
+            let number = self.number(i);
+            for _ in 0..self.count_at(i) {
+                writeln!(writer, "{}", number)?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// The number of unique phone numbers smaller than a number
+    fn rank(&self, number: i64) -> usize {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.number(middle) < number {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low
+    }
+
+    /// The i-th smallest phone number as a neighbour of the target
+    fn neighbour_at(&self, i: usize, target: i64) -> Neighbour {
+        let number = self.number(i);
+        Neighbour { distance: number.abs_diff(target), number, count: self.count_at(i) }
+    }
+
+    /// Finds the k nearest unique phone numbers to the target with a binary search, then by
+    /// walking outwards from it in both directions. In the case of a tie at the k-th distance,
+    /// every number at that distance is returned, the same way `kth_nearest` does.
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `k` - the number of unique phone numbers to find
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance, and numbers at the same distance
+    ///   in the order they first appeared, the same order as `kth_nearest`
+    /// # Example
+    /// ```rust
+    /// use phone_io::{binary::{write_binary, BinaryDataset}, PhoneCounts};
+    /// let mut bytes = Vec::new();
+    /// write_binary(&mut bytes, &PhoneCounts::from_numbers(&[12, 10, 12, 8, 20])).unwrap();
+    /// let dataset = BinaryDataset::from_bytes(bytes).unwrap();
+    /// let found: Vec<(i64, usize)> = dataset.nearest(11, 1).iter().map(|n| (n.number, n.count)).collect();
+    /// assert_eq!(found, vec![(12, 2), (10, 1)]);
+    /// ```
+    pub fn nearest(&self, target: i64, k: usize) -> Vec<Neighbour> {
+        let rank = self.rank(target);
+        // the next index to look at below and above the target
+        let mut below = rank;
+        let mut above = rank;
+        let mut result: Vec<Neighbour> = Vec::new();
+        loop {
+            let below_distance = (below > 0).then(|| self.number(below - 1).abs_diff(target));
+            let above_distance = (above < self.len).then(|| self.number(above).abs_diff(target));
+            // take whichever side is closer, preferring the number that appeared first on a tie,
+            // as only the numbers on either side of the target can be at the same distance
+            let index = match (below_distance, above_distance) {
+                (Some(b), Some(a)) if b < a => below - 1,
+                (Some(b), Some(a)) if b == a && self.first_seen_at(below - 1) < self.first_seen_at(above) => below - 1,
+                (Some(_), None) => below - 1,
+                (_, Some(_)) => above,
+                (None, None) => break,
+            };
+            let found = self.neighbour_at(index, target);
+            if result.len() >= k && result.last().map(|last| last.distance) != Some(found.distance) {
+                // we have enough numbers, and this one is not tied with the last one
+                break;
+            }
+            if index < rank {
+                below -= 1;
+            } else {
+                above += 1;
+            }
+            result.push(found);
+        }
+        result
+    }
+
+    /// Finds every phone number within the radius of the target with a binary search for each end
+    /// # Arguments
+    /// * `target` - the target phone number
+    /// * `radius` - the maximum absolute difference from the target, inclusive
+    /// # Returns
+    /// * `Vec<Neighbour>` - the phone numbers found, sorted by distance then by number
+    pub fn within(&self, target: i64, radius: u64) -> Vec<Neighbour> {
+        // the ends can be outside the range of an i64 when the radius is larger than i64::MAX
+        let (first, last) = (target as i128 - radius as i128, target as i128 + radius as i128);
+        let start = self.rank(first.max(i64::MIN as i128) as i64);
+        let end = match last < i64::MAX as i128 {
+            true => self.rank(last as i64 + 1),
+            false => self.len,
+        };
+        let mut result: Vec<Neighbour> = (start..end.max(start)).map(|index| self.neighbour_at(index, target)).collect();
+        result.sort_by_key(|found| (found.distance, found.number));
+        result
+    }
+}
+
+/// Reads a little-endian `u64` at an offset
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
+
+/// The 64 bit FNV-1a hash, used as the checksum of a dataset
+struct Checksum(u64);
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Checksum(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Checksum {
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand_chacha::{ChaChaRng, rand_core::SeedableRng};
+
+    /// This function writes counted phone numbers and reads them back from memory
+    fn dataset(numbers: &[i64]) -> BinaryDataset {
+        let mut bytes = Vec::new();
+        write_binary(&mut bytes, &PhoneCounts::from_numbers(numbers)).unwrap();
+        BinaryDataset::from_bytes(bytes).unwrap()
+    }
+
+    /// Every unique phone number as found from the target, sorted by distance then by the order they first appeared,
+    /// which `sort_by_key` keeps as it is stable
+    fn neighbours_by_sorting(numbers: &[i64], target: i64) -> Vec<Neighbour> {
+        let counts = PhoneCounts::from_numbers(numbers);
+        let mut neighbours: Vec<Neighbour> = counts
+            .iter()
+            .map(|(number, count)| Neighbour { distance: number.abs_diff(target), number, count })
+            .collect();
+        neighbours.sort_by_key(|found| found.distance);
+        neighbours
+    }
+
+    /// This test checks the median and the queries against sorting every number,
+    /// on random phone numbers with duplicates
+    #[test]
+    fn test_binary_against_sorted_numbers() {
+        let mut rng = ChaChaRng::seed_from_u64(41);
+        for _ in 0..50 {
+            let numbers: Vec<i64> = (0..rng.gen_range(0..300)).map(|_| rng.gen_range(7327325000..7327326000)).collect();
+            let dataset = dataset(&numbers);
+            dataset.verify().unwrap();
+            let mut sorted = numbers.clone();
+            sorted.sort_unstable();
+            let expected = match sorted.len() {
+                0 => None,
+                n if n % 2 == 0 => Some((sorted[n / 2 - 1], Some(sorted[n / 2]))),
+                n => Some((sorted[n / 2], None)),
+            };
+            assert_eq!(dataset.median(), expected);
+            assert_eq!(dataset.total(), numbers.len());
+            assert_eq!(dataset.numbers(), sorted);
+            assert_eq!(dataset.to_counts(), PhoneCounts::from_numbers(&numbers));
+            for _ in 0..20 {
+                let target = rng.gen_range(7327324900..7327326100);
+                let k = rng.gen_range(1..10);
+                let radius = rng.gen_range(0..50);
+                let neighbours = neighbours_by_sorting(&numbers, target);
+                // the k nearest, along with every other number at the distance of the k-th
+                let taken = match neighbours.get(k - 1) {
+                    Some(kth) => neighbours.iter().take_while(|found| found.distance <= kth.distance).count(),
+                    None => neighbours.len(),
+                };
+                assert_eq!(dataset.nearest(target, k), neighbours[..taken]);
+                let mut within: Vec<Neighbour> = neighbours.iter().filter(|found| found.distance <= radius).copied().collect();
+                within.sort_by_key(|found| (found.distance, found.number));
+                assert_eq!(dataset.within(target, radius), within);
+                assert_eq!(dataset.count(target), numbers.iter().filter(|n| **n == target).count());
+            }
+            // a radius too large for an i64 still finds every number
+            assert_eq!(dataset.within(i64::MIN, u64::MAX).len(), dataset.len());
+        }
+    }
+
+    /// This test checks that files that are truncated, corrupt, of another version or not datasets
+    /// at all are rejected
+    #[test]
+    fn test_binary_rejects_invalid_files() {
+        let mut bytes = Vec::new();
+        write_binary(&mut bytes, &PhoneCounts::from_numbers(&[7327325555, 7327325554])).unwrap();
+        assert_eq!(bytes.len(), HEADER_SIZE + 2 * ENTRY_SIZE);
+        let truncated = bytes[..bytes.len() - 1].to_vec();
+        assert_eq!(BinaryDataset::from_bytes(truncated).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert!(BinaryDataset::from_bytes(corrupt).unwrap().verify().is_err());
+        let mut version = bytes.clone();
+        version[4] = 1;
+        assert!(BinaryDataset::from_bytes(version).unwrap_err().to_string().contains("version 1"));
+        assert!(BinaryDataset::from_bytes(b"7327325555\n".to_vec()).is_err());
+        let mut negative = Vec::new();
+        assert!(write_binary(&mut negative, &PhoneCounts::from_numbers(&[-1])).is_err());
+        let mut text = Vec::new();
+        BinaryDataset::from_bytes(bytes).unwrap().write_text(&mut text).unwrap();
+        assert_eq!(text, b"7327325555\n7327325554\n");
+        assert_eq!(dataset(&[]).median(), None);
+        assert!(dataset(&[]).nearest(7327325555, 1).is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use flate2::bufread::MultiGzDecoder;
+
+/// The magic bytes every gzip stream starts with
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// The magic bytes every zstd frame starts with
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a file of phone numbers is compressed
+/// * `None` - the file is plain text
+/// * `Gzip` - the file is compressed with gzip, such as by `gzip` or `pigz`
+/// * `Zstd` - the file is compressed with zstd
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects how a file is compressed from its first bytes, or from its extension
+    /// if they are not the magic bytes of a known format
+    /// # Arguments
+    /// * `path` - the path of the file, if it has one
+    /// * `header` - the first bytes of the file
+    /// # Returns
+    /// * `Compression` - how the file is compressed
+    /// # Example
+    /// ```rust
+    /// use std::path::Path;
+    /// use phone_io::compress::Compression;
+    /// assert_eq!(Compression::detect(None, &[0x1f, 0x8b, 0x08]), Compression::Gzip);
+    /// assert_eq!(Compression::detect(Some(Path::new("dump.zst")), b""), Compression::Zstd);
+    /// assert_eq!(Compression::detect(Some(Path::new("phonescraped")), b"7327325555\n"), Compression::None);
+    /// ```
+    pub fn detect(path: Option<&Path>, header: &[u8]) -> Self {
+        if header.starts_with(&GZIP_MAGIC) {
+            return Compression::Gzip;
+        }
+        if header.starts_with(&ZSTD_MAGIC) {
+            return Compression::Zstd;
+        }
+        let extension = path.and_then(|path| path.extension()).and_then(|extension| extension.to_str());
+        match extension.map(|extension| extension.to_ascii_lowercase()).as_deref() {
+            Some("gz" | "gzip") => Compression::Gzip,
+            Some("zst" | "zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Wraps a reader so that a gzip or zstd stream is decompressed as it is read,
+/// without holding the whole decompressed text in memory. Plain text is passed through.
+/// Gzip streams made of several members, such as from `pigz` or concatenated files, are read to the end.
+/// # Arguments
+/// * `reader` - where to read the possibly compressed bytes from
+/// * `path` - the path of the file, used to detect the compression from its extension
+/// # Errors
+/// This function will return an error if the first bytes cannot be read,
+/// or if the zstd decoder cannot be created
+/// # Example
+/// ```rust
+/// use std::io::{BufRead, Write};
+/// use flate2::{write::GzEncoder, Compression};
+/// use phone_io::compress::decompress;
+/// let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+/// encoder.write_all(b"7327325555\n7327325554\n").unwrap();
+/// let compressed = encoder.finish().unwrap();
+/// let lines: Vec<String> = decompress(&compressed[..], None).unwrap().lines().map(Result::unwrap).collect();
+/// assert_eq!(lines, vec!["7327325555", "7327325554"]);
+/// ```
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R, path: Option<&Path>) -> io::Result<Box<dyn BufRead + 'a>> {
+    let compression = Compression::detect(path, reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(BufReader::new(Decoder(MultiGzDecoder::new(reader)))),
+        Compression::Zstd => Box::new(BufReader::new(Decoder(zstd::stream::read::Decoder::with_buffer(reader).map_err(corrupt)?))),
+    })
+}
+
+/// A decoder whose errors on corrupt input have the `InvalidData` kind,
+/// so that they are not mistaken for invalid arguments, which use `InvalidInput`
+struct Decoder<R>(R);
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(corrupt)
+    }
+}
+
+/// Gives an error from a decoder the `InvalidData` kind if it has the `InvalidInput` kind
+fn corrupt(error: io::Error) -> io::Error {
+    match error.kind() {
+        io::ErrorKind::InvalidInput => io::Error::new(io::ErrorKind::InvalidData, error),
+        _ => error,
+    }
+}
+
+/// Opens a file of phone numbers, decompressing it as it is read if it is compressed
+/// # Arguments
+/// * `path` - the path of the file
+/// # Errors
+/// This function will return an error if the file cannot be opened or its first bytes cannot be read
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    let path = path.as_ref();
+    decompress(BufReader::new(File::open(path)?), Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    const CONTENTS: &str = "7327325555\n(732) 732-5554\n12345\n";
+
+    /// This function reads everything from a reader as text
+    fn read_all(mut reader: Box<dyn BufRead + '_>) -> String {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        text
+    }
+
+    /// This test checks that gzip and zstd streams are decompressed whatever their extension,
+    /// that concatenated gzip members are all read, and that plain text is passed through
+    #[test]
+    fn test_decompress_formats() {
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(CONTENTS.as_bytes()).unwrap();
+        let gzip = gzip.finish().unwrap();
+        let zstd = zstd::encode_all(CONTENTS.as_bytes(), 0).unwrap();
+        assert_eq!(read_all(decompress(&gzip[..], None).unwrap()), CONTENTS);
+        assert_eq!(read_all(decompress(&zstd[..], Some(Path::new("numbers.txt"))).unwrap()), CONTENTS);
+        assert_eq!(read_all(decompress(CONTENTS.as_bytes(), None).unwrap()), CONTENTS);
+        let twice = [gzip.clone(), gzip].concat();
+        assert_eq!(read_all(decompress(&twice[..], None).unwrap()), CONTENTS.repeat(2));
+    }
+
+    /// This test checks that a file named as compressed but holding something else is an error
+    /// rather than being read as text
+    #[test]
+    fn test_decompress_by_extension() {
+        assert_eq!(Compression::detect(Some(Path::new("DUMP.GZ")), b"plain"), Compression::Gzip);
+        let mut reader = decompress(CONTENTS.as_bytes(), Some(Path::new("numbers.gz"))).unwrap();
+        let error = reader.read_to_string(&mut String::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(decompress(&b""[..], Some(Path::new("empty"))).is_ok());
+    }
+}
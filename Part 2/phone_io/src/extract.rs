@@ -0,0 +1,311 @@
+use serde::Serialize;
+
+/// A phone number found in free text
+/// * `start` - the byte offset in the text where the phone number starts
+/// * `end` - the byte offset in the text just after the phone number
+/// * `number` - the sanitized phone number
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PhoneMatch {
+    pub start: usize,
+    pub end: usize,
+    pub number: i64,
+}
+
+/// The words that mark a plain run of 10 or 11 digits as an identifier rather than a phone number
+const ID_KEYWORDS: [&str; 19] = [
+    "order", "invoice", "id", "ref", "reference", "tracking", "account", "acct", "sku", "item", "transaction",
+    "confirmation", "serial", "isbn", "ticket", "case", "timestamp", "epoch", "po",
+];
+
+/// The words that can sit between an identifier keyword and its digits, as in "order number"
+const NUMBER_WORDS: [&str; 3] = ["no", "number", "num"];
+
+/// The separators allowed between the groups of digits in a phone number
+const SEPARATORS: [&[u8]; 5] = [b" ", b"-", b".", "\u{a0}".as_bytes(), b"&nbsp;"];
+
+/// Finds every phone number in a piece of free text or HTML, such as a scraped page.
+/// A phone number is 10 digits, grouped as `(AAA) EEE-LLLL`, `AAA-EEE-LLLL`, `AAA.EEE.LLLL`,
+/// `AAA EEE LLLL` or written without separators, optionally starting with a `+1` or `1`
+/// country code.
+/// To avoid false positives:
+/// * a number must not be part of a longer run of digits, letters or groups, so dates such as
+///   `2023-10-19`, IP addresses and card numbers are never matched
+/// * a plain run of digits is skipped when it follows `#` or a word such as `order`, `invoice`,
+///   `id` or `timestamp`, since it is then most likely an identifier
+/// * HTML tags, along with scripts and styles, are skipped, so only the visible text is searched
+/// # Arguments
+/// * `text` - the text to search
+/// # Returns
+/// * `Vec<PhoneMatch>` - the phone numbers found, with their byte offsets, in the order they appear
+/// # Example
+/// ```rust
+/// use phone_io::extract::extract_phone_numbers;
+/// let text = "<p>Call (732) 732-5555 or +1 732.732.5554 before 2023-10-19, order #7327325553</p>";
+/// let found = extract_phone_numbers(text);
+/// let numbers: Vec<i64> = found.iter().map(|m| m.number).collect();
+/// assert_eq!(numbers, vec![7327325555, 7327325554]);
+/// assert_eq!(&text[found[0].start..found[0].end], "(732) 732-5555");
+/// ```
+pub fn extract_phone_numbers(text: &str) -> Vec<PhoneMatch> {
+    let bytes = text.as_bytes();
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'<' {
+            i = skip_tag(bytes, i);
+            continue;
+        }
+        if matches!(bytes[i], b'+' | b'(' | b'0'..=b'9') && starts_cleanly(bytes, i) {
+            if let Some((end, number, formatted)) = parse_phone_number(bytes, i) {
+                if ends_cleanly(bytes, end) && (formatted || !follows_id_keyword(bytes, i)) {
+                    found.push(PhoneMatch { start: i, end, number });
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    found
+}
+
+/// Parses a phone number starting at the given offset
+/// # Returns
+/// * `Option<(usize, i64, bool)>` - the offset just after the phone number, the phone number,
+///   and whether it had any separators or a `+`, or None if there is no phone number there
+fn parse_phone_number(bytes: &[u8], start: usize) -> Option<(usize, i64, bool)> {
+    let mut i = start;
+    let mut formatted = false;
+    // the country code, which is either +1 or a 1 followed by a separator
+    if bytes[i] == b'+' {
+        if bytes.get(i + 1) != Some(&b'1') {
+            return None;
+        }
+        i += 2;
+        i = skip_separator(bytes, i).unwrap_or(i);
+        formatted = true;
+    } else if bytes[i] == b'1' {
+        if let Some(next) = skip_separator(bytes, i + 1) {
+            if matches!(bytes.get(next), Some(b'(' | b'0'..=b'9')) {
+                i = next;
+                formatted = true;
+            }
+        }
+    }
+    let mut digits = String::new();
+    if bytes.get(i) == Some(&b'(') {
+        // (AAA) EEE-LLLL, where the separator after the area code is optional
+        let area = take_digits(bytes, i + 1, 3)?;
+        if bytes.get(area) != Some(&b')') {
+            return None;
+        }
+        digits.push_str(std::str::from_utf8(&bytes[i + 1..area]).ok()?);
+        i = skip_separator(bytes, area + 1).unwrap_or(area + 1);
+        formatted = true;
+    } else {
+        let end = (i..bytes.len()).find(|j| !bytes[*j].is_ascii_digit()).unwrap_or(bytes.len());
+        if end - i == 3 {
+            // AAA-EEE-LLLL
+            digits.push_str(std::str::from_utf8(&bytes[i..end]).ok()?);
+            i = skip_separator(bytes, end)?;
+            formatted = true;
+        } else {
+            // a plain run of 10 digits, or 11 starting with the country code
+            let run = match end - i {
+                10 => &bytes[i..end],
+                11 if bytes[i] == b'1' && i == start => &bytes[i + 1..end],
+                _ => return None,
+            };
+            if run[0] == b'0' {
+                return None;
+            }
+            let number = std::str::from_utf8(run).ok()?.parse().ok()?;
+            return Some((end, number, formatted || bytes[start] == b'+'));
+        }
+    }
+    let exchange = take_digits(bytes, i, 3)?;
+    let after = skip_separator(bytes, exchange)?;
+    let line = take_digits(bytes, after, 4)?;
+    digits.push_str(std::str::from_utf8(&bytes[i..exchange]).ok()?);
+    digits.push_str(std::str::from_utf8(&bytes[after..line]).ok()?);
+    if digits.starts_with('0') {
+        return None;
+    }
+    Some((line, digits.parse().ok()?, formatted))
+}
+
+/// Returns the offset after exactly `count` digits starting at `start`,
+/// or None if there are fewer or more digits there
+fn take_digits(bytes: &[u8], start: usize, count: usize) -> Option<usize> {
+    let end = start + count;
+    let is_digits = bytes.get(start..end)?.iter().all(|b| b.is_ascii_digit());
+    let is_followed_by_digit = bytes.get(end).is_some_and(|b| b.is_ascii_digit());
+    (is_digits && !is_followed_by_digit).then_some(end)
+}
+
+/// Returns the offset after a separator starting at `start`, or None if there is no separator there
+fn skip_separator(bytes: &[u8], start: usize) -> Option<usize> {
+    let rest = bytes.get(start..)?;
+    SEPARATORS
+        .iter()
+        .find(|separator| rest.starts_with(separator))
+        .map(|separator| start + separator.len())
+}
+
+/// Whether a phone number can start at the offset, which is not the case when it would
+/// continue a word, a path, a `#` identifier or a longer group of digits
+fn starts_cleanly(bytes: &[u8], start: usize) -> bool {
+    let Some(previous) = start.checked_sub(1).map(|i| bytes[i]) else {
+        return true;
+    };
+    if previous.is_ascii_alphanumeric() || matches!(previous, b'_' | b'/' | b'#' | b'$' | b'@' | b'+' | b'(') {
+        return false;
+    }
+    // a separator between this and an earlier group of digits, such as in 5-732-732-5555
+    let is_separator = matches!(previous, b'-' | b'.' | b' ');
+    !(is_separator && start >= 2 && bytes[start - 2].is_ascii_digit())
+}
+
+/// Whether a phone number can end at the offset, which is not the case when it
+/// is followed by a word or a further group of digits
+fn ends_cleanly(bytes: &[u8], end: usize) -> bool {
+    let Some(next) = bytes.get(end) else {
+        return true;
+    };
+    if next.is_ascii_alphanumeric() || *next == b'_' {
+        return false;
+    }
+    let is_separator = matches!(next, b'-' | b'.' | b'/' | b',');
+    !(is_separator && bytes.get(end + 1).is_some_and(|b| b.is_ascii_digit()))
+}
+
+/// Whether the digits at the offset follow a word marking them as an identifier,
+/// such as `Order #`, `Invoice: ` or `order number `
+fn follows_id_keyword(bytes: &[u8], start: usize) -> bool {
+    let before = &bytes[start.saturating_sub(40)..start];
+    let before = String::from_utf8_lossy(before).to_ascii_lowercase();
+    let mut words = before
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .rev()
+        .filter(|word| !word.is_empty());
+    // nothing but punctuation between the keyword and the digits, apart from `#`
+    let gap = before.trim_end_matches(|c: char| !c.is_ascii_alphanumeric());
+    if before[gap.len()..].contains('#') {
+        return true;
+    }
+    match words.next() {
+        Some(word) if NUMBER_WORDS.contains(&word) => words.next().is_some_and(|word| ID_KEYWORDS.contains(&word)),
+        Some(word) => ID_KEYWORDS.contains(&word),
+        None => false,
+    }
+}
+
+/// Returns the offset after an HTML tag starting at `start`, skipping the contents of
+/// scripts and styles as well. Anything that is not a tag, such as `a < b`, is skipped one byte at a time.
+fn skip_tag(bytes: &[u8], start: usize) -> usize {
+    let is_tag = bytes.get(start + 1).is_some_and(|b| b.is_ascii_alphabetic() || matches!(b, b'/' | b'!'));
+    if !is_tag {
+        return start + 1;
+    }
+    let end = match bytes[start..].iter().position(|b| *b == b'>') {
+        Some(position) => start + position + 1,
+        None => return bytes.len(),
+    };
+    let name: String = bytes[start + 1..end]
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric())
+        .map(|b| b.to_ascii_lowercase() as char)
+        .collect();
+    if name != "script" && name != "style" {
+        return end;
+    }
+    let closing = format!("</{}", name);
+    let rest = String::from_utf8_lossy(&bytes[end..]).to_ascii_lowercase();
+    match rest.find(&closing) {
+        Some(position) => end + position,
+        None => bytes.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// This function extracts the phone numbers along with the text they were found in
+    fn extract(text: &str) -> Vec<(&str, i64)> {
+        extract_phone_numbers(text).iter().map(|m| (&text[m.start..m.end], m.number)).collect()
+    }
+
+    /// This test checks every supported way of writing a phone number
+    #[test]
+    fn test_extract_formats() {
+        let cases = [
+            ("(323) 456-7890", 3234567890),
+            ("(323)456-7890", 3234567890),
+            ("323-456-7890", 3234567890),
+            ("323.456.7890", 3234567890),
+            ("323 456 7890", 3234567890),
+            ("3234567890", 3234567890),
+            ("13234567890", 3234567890),
+            ("+13234567890", 3234567890),
+            ("+1 323-456-7890", 3234567890),
+            ("+1 (323) 456-7890", 3234567890),
+            ("1-323-456-7890", 3234567890),
+            ("1 (323) 456-7890", 3234567890),
+            ("323\u{a0}456\u{a0}7890", 3234567890),
+            ("323&nbsp;456&nbsp;7890", 3234567890),
+        ];
+        for (text, number) in cases {
+            let sentence = format!("Call us at {}, today.", text);
+            assert_eq!(extract(&sentence), vec![(text, number)], "{}", text);
+        }
+    }
+
+    /// This test checks that dates, identifiers and other runs of digits are not taken for phone numbers
+    #[test]
+    fn test_extract_false_positives() {
+        let cases = [
+            "Posted on 2023-10-19 at 10:30",
+            "Posted on 10/19/2023",
+            "Order #3234567890 has shipped",
+            "Order number: 3234567890",
+            "Invoice 3234567890",
+            "Tracking ID: 13234567890",
+            "\"timestamp\": 1697731200",
+            "Card 4111 1111 1111 1111",
+            "Server 192.168.100.200",
+            "Reference 323-456-7890-12",
+            "Part 5-323-456-7890",
+            "SKU ABC3234567890",
+            "https://example.com/items/3234567890",
+            "Too long 323456789012",
+            "Too short 323-456-789",
+            "Leading zero (023) 456-7890",
+        ];
+        for text in cases {
+            assert_eq!(extract(text), vec![], "{}", text);
+        }
+    }
+
+    /// This test checks that only the visible text of an HTML page is searched
+    #[test]
+    fn test_extract_html() {
+        let html = "<html><head><style>.phone { width: 3234567890px }</style>\
+            <script>var id = 3234567891;</script></head>\
+            <body><a href=\"tel:+13234567892\" data-id=\"3234567893\">(323) 456-7894</a>\
+            <p>Fax:&nbsp;323&nbsp;456&nbsp;7895</p><p>5 < 6 and 323-456-7896</p></body></html>";
+        let numbers: Vec<i64> = extract(html).iter().map(|(_, number)| *number).collect();
+        assert_eq!(numbers, vec![3234567894, 3234567895, 3234567896]);
+    }
+
+    /// This test checks that the offsets are byte offsets into the original text,
+    /// including after multibyte characters
+    #[test]
+    fn test_extract_offsets() {
+        let text = "Café ☕: 323-456-7890\nBäckerei: (323) 456-7891";
+        let found = extract_phone_numbers(text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], PhoneMatch { start: 11, end: 23, number: 3234567890 });
+        assert_eq!(&text[found[1].start..found[1].end], "(323) 456-7891");
+    }
+}
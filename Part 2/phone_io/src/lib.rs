@@ -0,0 +1,143 @@
+//! Reading phone numbers from files and writing the results found from them, shared by both tasks.
+//! The files can hold one phone number per record, free text or tables, compressed with gzip or zstd,
+//! or be binary datasets, and the results are written as text, JSON, NDJSON or CSV.
+
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use serde::Serialize;
+
+pub mod binary;
+pub mod compress;
+pub mod extract;
+pub mod output;
+pub mod records;
+pub mod table;
+
+/// A phone number found by a nearest neighbour or radius query
+/// * `distance` - the distance from the queried target
+/// * `number` - the phone number
+/// * `count` - the number of times the phone number appeared in the dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Neighbour {
+    pub distance: u64,
+    pub number: i64,
+    pub count: usize,
+}
+
+/// A hasher for phone numbers, which are already spread evenly enough that a multiply and shift
+/// hashes them well, and much faster than the default hasher when counting millions of them.
+/// It is not resistant to collisions chosen on purpose, so it is only used for counting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumberHasher(u64);
+
+impl Hasher for NumberHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|byte| self.write_u64(*byte as u64));
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        let hash = (self.0 ^ value).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        self.0 = hash ^ (hash >> 32);
+    }
+
+    fn write_i64(&mut self, value: i64) {
+        self.write_u64(value as u64);
+    }
+}
+
+/// The number of times each phone number appears, along with the order the numbers first appeared in,
+/// so that numbers can be counted as a file is read without keeping every number in memory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhoneCounts {
+    counts: HashMap<i64, usize, BuildHasherDefault<NumberHasher>>,
+    order: Vec<i64>,
+    total: usize,
+}
+
+impl PhoneCounts {
+    /// Counts a list of phone numbers
+    /// # Example
+    /// ```rust
+    /// use phone_io::PhoneCounts;
+    /// let counts = PhoneCounts::from_numbers(&[7327325555, 7327325554, 7327325555]);
+    /// assert_eq!(counts.count(7327325555), 2);
+    /// assert_eq!(counts.iter().collect::<Vec<_>>(), vec![(7327325555, 2), (7327325554, 1)]);
+    /// assert_eq!((counts.len(), counts.total()), (2, 3));
+    /// ```
+    pub fn from_numbers(numbers: &[i64]) -> Self {
+        let mut counts = PhoneCounts::default();
+        numbers.iter().for_each(|number| counts.add(*number));
+        counts
+    }
+
+    /// Adds one occurrence of a phone number
+    pub fn add(&mut self, number: i64) {
+        self.insert(number, 1);
+    }
+
+    /// Adds a number of occurrences of a phone number
+    pub fn insert(&mut self, number: i64, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let current = self.counts.entry(number).or_insert(0);
+        if *current == 0 {
+            self.order.push(number);
+        }
+        *current += count;
+        self.total += count;
+    }
+
+    /// Adds the counts of phone numbers read after these ones, such as from the next chunk of a file,
+    /// keeping the order the numbers first appeared in
+    /// # Example
+    /// ```rust
+    /// use phone_io::PhoneCounts;
+    /// let mut counts = PhoneCounts::from_numbers(&[5, 3]);
+    /// counts.merge(PhoneCounts::from_numbers(&[4, 5, 4]));
+    /// assert_eq!(counts, PhoneCounts::from_numbers(&[5, 3, 4, 5, 4]));
+    /// ```
+    pub fn merge(&mut self, other: PhoneCounts) {
+        if self.is_empty() {
+            *self = other;
+            return;
+        }
+        self.counts.reserve(other.len());
+        other.iter().for_each(|(number, count)| self.insert(number, count));
+    }
+
+    /// The number of times a phone number appears
+    pub fn count(&self, number: i64) -> usize {
+        self.counts.get(&number).copied().unwrap_or(0)
+    }
+
+    /// The number of unique phone numbers
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Whether no phone numbers have been counted
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// The number of phone numbers counted, including duplicates
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Each unique phone number with the number of times it appears, in the order they first appeared
+    pub fn iter(&self) -> impl Iterator<Item = (i64, usize)> + '_ {
+        self.order.iter().map(|number| (*number, self.counts[number]))
+    }
+
+    /// Each unique phone number with the number of times it appears, as returned by `count_numbers`
+    pub fn counts(&self) -> &HashMap<i64, usize, BuildHasherDefault<NumberHasher>> {
+        &self.counts
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The formats results can be written in
+/// * `Text` - the plain numbers, one per line, which each binary writes itself
+/// * `Json` - a single JSON object with the input statistics, the query and the results
+/// * `Csv` - a header and one row per result
+/// * `Ndjson` - one JSON object per line: the input statistics, the query, then each result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            _ => Err("Output must be one of json, csv, ndjson or text"),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+        }
+    }
+}
+
+/// How many phone numbers were read from the input
+/// * `parsed` - the number of phone numbers read
+/// * `rejected` - the number of lines or arguments that were not phone numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct InputStats {
+    pub parsed: usize,
+    pub rejected: usize,
+}
+
+/// The results of a query, ready to be written in a structured format
+/// * `input` - how many phone numbers were read
+/// * `query` - the parameters of the query, such as the target and k, as a JSON object
+/// * `name` - what the results are called in JSON, such as `neighbours`
+/// * `kind` - what each result is called in NDJSON, such as `neighbour`
+/// * `results` - the results, which should each serialize to a JSON object
+#[derive(Debug, Clone)]
+pub struct Report<T> {
+    pub input: InputStats,
+    pub query: Value,
+    pub name: &'static str,
+    pub kind: &'static str,
+    pub results: Vec<T>,
+}
+
+/// Writes a report in a structured format.
+/// In CSV, nested values such as lists are written as JSON, and the input statistics are left out.
+/// # Arguments
+/// * `writer` - where to write the report
+/// * `format` - the format to write it in, which must not be `Text`
+/// * `report` - the report to write
+/// # Errors
+/// This function will return an error if the report cannot be written,
+/// or with the `InvalidInput` kind if the format is `Text`
+/// # Example
+/// ```rust
+/// use phone_io::{Neighbour, output::{write_report, InputStats, OutputFormat, Report}};
+/// use serde_json::json;
+/// let report = Report {
+///     input: InputStats { parsed: 3, rejected: 1 },
+///     query: json!({ "target": 7327325555_i64, "k": 1 }),
+///     name: "neighbours",
+///     kind: "neighbour",
+///     results: vec![Neighbour { distance: 0, number: 7327325555, count: 2 }],
+/// };
+/// let mut output = Vec::new();
+/// write_report(&mut output, OutputFormat::Csv, &report).unwrap();
+/// assert_eq!(String::from_utf8(output).unwrap(), "distance,number,count\n0,7327325555,2\n");
+/// ```
+pub fn write_report<W: Write, T: Serialize>(mut writer: W, format: OutputFormat, report: &Report<T>) -> io::Result<()> {
+    let results = report
+        .results
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<Value>, _>>()?;
+    match format {
+        OutputFormat::Text => Err(io::Error::new(io::ErrorKind::InvalidInput, "text output is written by each binary")),
+        OutputFormat::Json => {
+            let mut object = Map::new();
+            object.insert("input".to_owned(), serde_json::to_value(report.input)?);
+            if let Value::Object(query) = &report.query {
+                object.extend(query.clone());
+            }
+            object.insert(report.name.to_owned(), Value::Array(results));
+            writeln!(writer, "{}", Value::Object(object))
+        }
+        OutputFormat::Ndjson => {
+            let mut input = Map::new();
+            input.insert("type".to_owned(), Value::from("input"));
+            input.extend(as_object(serde_json::to_value(report.input)?));
+            writeln!(writer, "{}", Value::Object(input))?;
+            if let Value::Object(query) = &report.query {
+                let mut line = Map::new();
+                line.insert("type".to_owned(), Value::from("query"));
+                line.extend(query.clone());
+                writeln!(writer, "{}", Value::Object(line))?;
+            }
+            for result in results {
+                let mut line = Map::new();
+                line.insert("type".to_owned(), Value::from(report.kind));
+                line.extend(as_object(result));
+                writeln!(writer, "{}", Value::Object(line))?;
+            }
+            Ok(())
+        }
+        OutputFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(writer);
+            let header: Vec<String> = match results.first() {
+                Some(Value::Object(first)) => first.keys().cloned().collect(),
+                _ => Vec::new(),
+            };
+            if !header.is_empty() {
+                csv.write_record(&header)?;
+            }
+            for result in results {
+                let object = as_object(result);
+                let fields: Vec<String> = header.iter().map(|key| csv_field(object.get(key))).collect();
+                csv.write_record(&fields)?;
+            }
+            csv.flush()
+        }
+    }
+}
+
+/// Returns the fields of a JSON object, or puts any other value under `value`
+fn as_object(value: Value) -> Map<String, Value> {
+    match value {
+        Value::Object(object) => object,
+        value => Map::from_iter([("value".to_owned(), value)]),
+    }
+}
+
+/// Writes a JSON value as a CSV field: strings as they are, nothing for null,
+/// and lists and objects as JSON
+fn csv_field(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// This function writes a report of two results with a nested list in one of them
+    fn write(format: OutputFormat) -> String {
+        let report = Report {
+            input: InputStats { parsed: 5, rejected: 2 },
+            query: json!({ "target": 7327325555_i64 }),
+            name: "neighbours",
+            kind: "neighbour",
+            results: vec![
+                json!({ "distance": 0, "number": 7327325555_i64, "rows": ["Jo,web", "Al,\"shop\""] }),
+                json!({ "distance": 1, "number": 7327325554_i64, "rows": null }),
+            ],
+        };
+        let mut output = Vec::new();
+        write_report(&mut output, format, &report).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// This test checks every structured format, keeping the fields in the order they were declared
+    #[test]
+    fn test_output_formats() {
+        assert_eq!(
+            write(OutputFormat::Json),
+            "{\"input\":{\"parsed\":5,\"rejected\":2},\"target\":7327325555,\"neighbours\":[\
+             {\"distance\":0,\"number\":7327325555,\"rows\":[\"Jo,web\",\"Al,\\\"shop\\\"\"]},\
+             {\"distance\":1,\"number\":7327325554,\"rows\":null}]}\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Ndjson),
+            "{\"type\":\"input\",\"parsed\":5,\"rejected\":2}\n\
+             {\"type\":\"query\",\"target\":7327325555}\n\
+             {\"type\":\"neighbour\",\"distance\":0,\"number\":7327325555,\"rows\":[\"Jo,web\",\"Al,\\\"shop\\\"\"]}\n\
+             {\"type\":\"neighbour\",\"distance\":1,\"number\":7327325554,\"rows\":null}\n"
+        );
+        assert_eq!(
+            write(OutputFormat::Csv),
+            "distance,number,rows\n0,7327325555,\"[\"\"Jo,web\"\",\"\"Al,\\\"\"shop\\\"\"\"\"]\"\n1,7327325554,\n"
+        );
+        let mut output = Vec::new();
+        let report = Report { input: InputStats::default(), query: Value::Null, name: "neighbours", kind: "neighbour", results: Vec::<Value>::new() };
+        assert!(write_report(&mut output, OutputFormat::Text, &report).is_err());
+        write_report(&mut output, OutputFormat::Json, &report).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "{\"input\":{\"parsed\":0,\"rejected\":0},\"neighbours\":[]}\n");
+    }
+}
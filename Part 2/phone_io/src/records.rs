@@ -0,0 +1,372 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use crate::extract::extract_phone_numbers;
+use crate::table::{read_table, Table};
+
+/// The character that separates the phone number records in a file.
+/// Records are always split on newlines as well, so a comma or tab separated file
+/// can have more than one line of records.
+/// * `Newline` - one phone number per line, which can contain spaces such as `(323) 456-7890`
+/// * `Comma` - phone numbers separated by commas
+/// * `Tab` - phone numbers separated by tabs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delimiter {
+    #[default]
+    Newline,
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    /// The character records are split on within a line, if any
+    pub fn separator(&self) -> Option<char> {
+        match self {
+            Delimiter::Newline => None,
+            Delimiter::Comma => Some(','),
+            Delimiter::Tab => Some('\t'),
+        }
+    }
+}
+
+impl FromStr for Delimiter {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "newline" => Ok(Delimiter::Newline),
+            "comma" | "," => Ok(Delimiter::Comma),
+            "tab" | "\t" => Ok(Delimiter::Tab),
+            _ => Err("Delimiter must be one of newline, comma or tab"),
+        }
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Delimiter::Newline => write!(f, "newline"),
+            Delimiter::Comma => write!(f, "comma"),
+            Delimiter::Tab => write!(f, "tab"),
+        }
+    }
+}
+
+/// How the phone numbers are laid out in a file
+/// * `Records` - one phone number per record, separated by the delimiter
+/// * `Text` - free text or HTML, such as a scraped page, that phone numbers are extracted from
+/// * `Table` - a column of a CSV, TSV or JSON lines table, along with the rest of each row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layout {
+    Records(Delimiter),
+    Text,
+    Table(Table),
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Records(Delimiter::Newline)
+    }
+}
+
+/// The phone numbers parsed from the records of a file
+/// * `numbers` - the sanitized phone numbers, in the order they appear
+/// * `rejected` - the line numbers, counted from 1, of every line with a record
+///   that is not a phone number, in order
+/// * `rows` - the rest of the row each phone number was found in, when reading a table,
+///   in the same order as `numbers`. This is empty for any other layout
+/// * `invalid_utf8` - the line numbers of every line that is not valid UTF-8, in order.
+///   The invalid bytes of records and free text are replaced, so their digits are still read,
+///   while rows of a table with invalid bytes are rejected
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Records {
+    pub numbers: Vec<i64>,
+    pub rejected: Vec<usize>,
+    pub rows: Vec<String>,
+    pub invalid_utf8: Vec<usize>,
+}
+
+/// Parses every record in the contents of a file as one phone number.
+/// Spaces around a record are ignored, and blank records are skipped.
+/// # Arguments
+/// * `contents` - The contents of the file
+/// * `delimiter` - The character that separates the records
+/// # Returns
+/// * `Records` - The phone numbers found, and the lines of the records that are not phone numbers
+/// # Example
+/// ```rust
+/// use phone_io::records::{parse_records, Delimiter};
+/// let records = parse_records("(732) 732-5555\n12345\n\n+1 732 732 5554\n", Delimiter::Newline);
+/// assert_eq!(records.numbers, vec![7327325555, 7327325554]);
+/// assert_eq!(records.rejected, vec![2]);
+/// let records = parse_records("732-732-5555,732-732-5554\n732-732-5553\n", Delimiter::Comma);
+/// assert_eq!(records.numbers, vec![7327325555, 7327325554, 7327325553]);
+/// ```
+pub fn parse_records(contents: &str, delimiter: Delimiter) -> Records {
+    let mut records = Records::default();
+    for (index, line) in contents.lines().enumerate() {
+        parse_line(line, index + 1, delimiter, &mut records.rejected, |number| records.numbers.push(number));
+    }
+    records
+}
+
+/// Parses the records on one line, handing the phone numbers found to `sink`
+/// and adding the line to the rejected lines if any record on it is not a phone number
+fn parse_line<F: FnMut(i64)>(line: &str, line_number: usize, delimiter: Delimiter, rejected: &mut Vec<usize>, mut sink: F) {
+    let fields: Vec<&str> = match delimiter.separator() {
+        Some(separator) => line.split(separator).collect(),
+        None => vec![line],
+    };
+    for field in fields.iter().map(|field| field.trim()).filter(|field| !field.is_empty()) {
+        match parse_record(field) {
+            Some(number) => sink(number),
+            None if rejected.last() != Some(&line_number) => rejected.push(line_number),
+            None => {}
+        }
+    }
+}
+
+/// Parses the phone numbers in the contents of a file laid out in any supported way.
+/// Nothing is rejected from free text, since anything that is not a phone number is skipped.
+/// # Arguments
+/// * `contents` - The contents of the file
+/// * `layout` - How the phone numbers are laid out in the file
+/// # Returns
+/// * `Records` - The phone numbers found, and the lines of the records that are not phone numbers
+/// # Errors
+/// This function will return an error if the layout is a table without the column in its header
+/// # Example
+/// ```rust
+/// use phone_io::records::{parse_contents, Layout};
+/// let records = parse_contents("<p>Call (732) 732-5555 or 732.732.5554</p>", &Layout::Text).unwrap();
+/// assert_eq!(records.numbers, vec![7327325555, 7327325554]);
+/// ```
+pub fn parse_contents(contents: &str, layout: &Layout) -> Result<Records, String> {
+    // reading from a string can only fail on a missing column
+    read_records(contents.as_bytes(), layout).map_err(|e| e.to_string())
+}
+
+/// Reads the phone numbers from a reader laid out in any supported way, such as a file
+/// opened with `compress::open`. Records and tables are parsed as they are read,
+/// so only the phone numbers are kept in memory. Free text is read whole,
+/// since HTML tags can span lines.
+/// # Arguments
+/// * `reader` - Where to read the contents from
+/// * `layout` - How the phone numbers are laid out in the contents
+/// # Returns
+/// * `Records` - The phone numbers found, and the lines of the records that are not phone numbers
+/// # Errors
+/// This function will return an error if the contents cannot be read,
+/// or with the `InvalidInput` kind if the layout is a table without the column in its header
+/// # Example
+/// ```rust
+/// use phone_io::records::{read_records, Delimiter, Layout};
+/// let records = read_records(&b"(732) 732-5555\n12345\n"[..], &Layout::Records(Delimiter::Newline)).unwrap();
+/// assert_eq!(records.numbers, vec![7327325555]);
+/// assert_eq!(records.rejected, vec![2]);
+/// ```
+pub fn read_records<R: BufRead>(mut reader: R, layout: &Layout) -> io::Result<Records> {
+    match layout {
+        Layout::Records(delimiter) => {
+            let mut numbers = Vec::new();
+            let records = stream_records(reader, *delimiter, |number| numbers.push(number))?;
+            Ok(Records { numbers, ..records })
+        }
+        Layout::Text => {
+            let mut contents = Vec::new();
+            reader.read_to_end(&mut contents)?;
+            let invalid_utf8 = match std::str::from_utf8(&contents) {
+                Ok(_) => Vec::new(),
+                Err(_) => invalid_lines(&contents),
+            };
+            let contents = String::from_utf8_lossy(&contents);
+            Ok(Records {
+                numbers: extract_phone_numbers(&contents).iter().map(|found| found.number).collect(),
+                invalid_utf8,
+                ..Records::default()
+            })
+        }
+        Layout::Table(table) => read_table(reader, table),
+    }
+}
+
+/// Streams the records of a reader one line at a time, handing each phone number to `sink`
+/// as soon as it is parsed, so that they can be counted without being collected first.
+/// Lines are read as bytes into one reused buffer. A line that is not valid UTF-8 has its
+/// invalid bytes replaced and is still parsed, since only its digits are needed.
+/// # Arguments
+/// * `reader` - Where to read the records from
+/// * `delimiter` - The character that separates the records
+/// * `sink` - Called with every phone number, in the order they appear
+/// # Returns
+/// * `Records` - The lines that were rejected or are not valid UTF-8.
+///   Its `numbers` are left empty, since every phone number is handed to `sink`
+/// # Errors
+/// This function will return an error if the reader cannot be read
+/// # Example
+/// ```rust
+/// use phone_io::{records::{stream_records, Delimiter}, PhoneCounts};
+/// let mut counts = PhoneCounts::default();
+/// let records = stream_records(&b"7327325555\n732\xff732 5555\n12345\n"[..], Delimiter::Newline, |n| counts.add(n)).unwrap();
+/// assert_eq!(counts.count(7327325555), 2);
+/// assert_eq!(records.rejected, vec![3]);
+/// assert_eq!(records.invalid_utf8, vec![2]);
+/// ```
+pub fn stream_records<R: BufRead, F: FnMut(i64)>(mut reader: R, delimiter: Delimiter, mut sink: F) -> io::Result<Records> {
+    let mut records = Records::default();
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        match std::str::from_utf8(&line) {
+            Ok(text) => parse_line(text, line_number, delimiter, &mut records.rejected, &mut sink),
+            Err(_) => {
+                records.invalid_utf8.push(line_number);
+                parse_line(&String::from_utf8_lossy(&line), line_number, delimiter, &mut records.rejected, &mut sink);
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Finds the line numbers, counted from 1, of the lines that are not valid UTF-8
+fn invalid_lines(contents: &[u8]) -> Vec<usize> {
+    contents
+        .split(|byte| *byte == b'\n')
+        .enumerate()
+        .filter(|(_, line)| std::str::from_utf8(line).is_err())
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Cleans a single record into a phone number, keeping only its digits.
+/// A record with 11 digits is taken to start with a country code, which is dropped.
+/// # Arguments
+/// * `record` - the record to clean
+/// # Returns
+/// * `Option<i64>` - the phone number, or None if the record is too short or too long
+///   to be a phone number
+pub fn parse_record(record: &str) -> Option<i64> {
+    parse_record_bytes(record.as_bytes())
+}
+
+/// Cleans a single record into a phone number the same way as `parse_record`,
+/// reading its digits straight from its bytes without allocating
+pub fn parse_record_bytes(record: &[u8]) -> Option<i64> {
+    let mut digits = 0;
+    let mut number: i64 = 0;
+    for byte in record.iter().filter(|byte| byte.is_ascii_digit()) {
+        digits += 1;
+        if digits > 11 {
+            return None;
+        }
+        number = number * 10 + (byte - b'0') as i64;
+    }
+    if digits < 10 {
+        return None;
+    }
+    // take the last 10 digits, dropping the country code
+    Some(number % 10_000_000_000).filter(|number| *number >= 1000000000)
+}
+
+/// Describes the lines that had a record that is not a phone number, listing at most
+/// the first 10 of them
+/// # Arguments
+/// * `rejected` - the line numbers, as reported in `Records`
+/// # Returns
+/// * `Option<String>` - the description, or None if no lines were rejected
+/// # Example
+/// ```rust
+/// use phone_io::records::describe_rejected;
+/// assert_eq!(describe_rejected(&[2, 5]), Some("lines 2, 5".to_owned()));
+/// assert_eq!(describe_rejected(&(1..=12).collect::<Vec<_>>()), Some("lines 1, 2, 3, 4, 5, 6, 7, 8, 9, 10 and 2 more".to_owned()));
+/// assert_eq!(describe_rejected(&[]), None);
+/// ```
+pub fn describe_rejected(rejected: &[usize]) -> Option<String> {
+    const SHOWN: usize = 10;
+    let (first, rest) = rejected.split_at(rejected.len().min(SHOWN));
+    let lines = first.iter().map(|line| line.to_string()).collect::<Vec<_>>().join(", ");
+    match (first.len(), rest.len()) {
+        (0, _) => None,
+        (1, _) => Some(format!("line {}", lines)),
+        (_, 0) => Some(format!("lines {}", lines)),
+        (_, more) => Some(format!("lines {} and {} more", lines, more)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// This test checks that a phone number with spaces in it is parsed as a single record,
+    /// which splitting on whitespace used to break into two rejected halves
+    #[test]
+    fn test_records_keep_spaces() {
+        let records = parse_records("(323) 456-7890\r\n+1 323 456 7891\n  323.456.7892  \n", Delimiter::Newline);
+        assert_eq!(records.numbers, vec![3234567890, 3234567891, 3234567892]);
+        assert!(records.rejected.is_empty());
+    }
+
+    /// This test checks that records are cleaned up to their 10 digits, dropping the country code,
+    /// the same way for the arguments of Task 1 as for the lines of a file
+    #[test]
+    fn test_parse_record() {
+        assert_eq!(parse_record("123-456-7890"), Some(1234567890));
+        assert_eq!(parse_record("(323) 456-7890"), Some(3234567890));
+        assert_eq!(parse_record("+1 223-456-7890"), Some(2234567890));
+        assert_eq!(parse_record("1-322-345-7890"), Some(3223457890));
+        assert_eq!(parse_record("12345"), None);
+        assert_eq!(parse_record("123-456-78901-2"), None);
+        assert_eq!(parse_record("012-345-6789"), None);
+    }
+
+    /// This test checks that the rejected lines are reported for every delimiter,
+    /// once per line even if it has more than one bad record
+    #[test]
+    fn test_records_rejected_lines() {
+        let contents = "7327325555,12345,phone\n\n7327325554,,7327325553\n0123456789\n";
+        let records = parse_records(contents, Delimiter::Comma);
+        assert_eq!(records.numbers, vec![7327325555, 7327325554, 7327325553]);
+        assert_eq!(records.rejected, vec![1, 4]);
+        let records = parse_records(&contents.replace(',', "\t"), Delimiter::Tab);
+        assert_eq!(records.numbers, vec![7327325555, 7327325554, 7327325553]);
+        assert_eq!(records.rejected, vec![1, 4]);
+        let records = parse_records(contents, Delimiter::Newline);
+        assert!(records.numbers.is_empty());
+        assert_eq!(records.rejected, vec![1, 3, 4]);
+    }
+
+    /// This test checks that lines that are not valid UTF-8 are still parsed and reported,
+    /// with or without a final newline and with Windows line endings
+    #[test]
+    fn test_stream_invalid_utf8() {
+        let contents = b"7327325555\r\n\xff\xfe732-732-5554\n\nphone \xe9\n7327325553";
+        let mut numbers = Vec::new();
+        let records = stream_records(&contents[..], Delimiter::Newline, |number| numbers.push(number)).unwrap();
+        assert_eq!(numbers, vec![7327325555, 7327325554, 7327325553]);
+        assert_eq!(records.rejected, vec![4]);
+        assert_eq!(records.invalid_utf8, vec![2, 4]);
+        assert!(records.numbers.is_empty());
+        let lossy = String::from_utf8_lossy(contents);
+        let expected = parse_records(&lossy, Delimiter::Newline);
+        let records = read_records(&contents[..], &Layout::Records(Delimiter::Newline)).unwrap();
+        assert_eq!((records.numbers, records.rejected), (expected.numbers, expected.rejected));
+        let records = read_records(&b"<p>\xffCall (732) 732-5555</p>\nok"[..], &Layout::Text).unwrap();
+        assert_eq!(records.numbers, vec![7327325555]);
+        assert_eq!(records.invalid_utf8, vec![1]);
+    }
+
+    /// This test checks that the delimiters are parsed from their names
+    #[test]
+    fn test_delimiter_from_str() {
+        for delimiter in [Delimiter::Newline, Delimiter::Comma, Delimiter::Tab] {
+            assert_eq!(delimiter.to_string().parse::<Delimiter>(), Ok(delimiter));
+        }
+        assert!("semicolon".parse::<Delimiter>().is_err());
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::records::{parse_record, Records};
+
+/// The formats of exported tables that phone numbers can be read from
+/// * `Csv` - comma separated values, with quoting
+/// * `Tsv` - tab separated values, with quoting
+/// * `JsonLines` - one JSON object per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    Tsv,
+    JsonLines,
+}
+
+impl FromStr for TableFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(TableFormat::Csv),
+            "tsv" => Ok(TableFormat::Tsv),
+            "jsonl" | "ndjson" | "json-lines" => Ok(TableFormat::JsonLines),
+            _ => Err("Format must be one of csv, tsv or jsonl"),
+        }
+    }
+}
+
+impl fmt::Display for TableFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableFormat::Csv => write!(f, "csv"),
+            TableFormat::Tsv => write!(f, "tsv"),
+            TableFormat::JsonLines => write!(f, "jsonl"),
+        }
+    }
+}
+
+/// A table of phone numbers along with the other columns of each row
+/// * `format` - the format of the table
+/// * `column` - the column with the phone numbers. For CSV and TSV, this is the name of a column
+///   in the header, or its position counted from 1. For JSON lines, this is the field, which can be
+///   nested with dots such as `contact.phone`
+/// * `header` - whether the first row of a CSV or TSV table is a header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub format: TableFormat,
+    pub column: String,
+    pub header: bool,
+}
+
+/// Parses the phone numbers in a table, keeping the rest of each row so that it can be shown
+/// alongside the phone number. The rest of a CSV or TSV row is written back in the same format,
+/// and the rest of a JSON object is written as compact JSON.
+/// Rows that cannot be parsed, or where the column is not a phone number, are rejected.
+/// # Arguments
+/// * `contents` - The contents of the file
+/// * `table` - The format of the table and the column with the phone numbers
+/// # Returns
+/// * `Records` - The phone numbers with the rest of their rows, and the lines of the rejected rows
+/// # Errors
+/// This function will return an error if the column is not in the header
+/// # Example
+/// ```rust
+/// use phone_io::table::{parse_table, Table, TableFormat};
+/// let contents = "name,phone,source\n\"Smith, Jo\",(732) 732-5555,web\nAl,12345,web\n";
+/// let table = Table { format: TableFormat::Csv, column: "phone".to_owned(), header: true };
+/// let records = parse_table(contents, &table).unwrap();
+/// assert_eq!(records.numbers, vec![7327325555]);
+/// assert_eq!(records.rows, vec!["\"Smith, Jo\",web"]);
+/// assert_eq!(records.rejected, vec![3]);
+/// ```
+pub fn parse_table(contents: &str, table: &Table) -> Result<Records, String> {
+    // reading from a string can only fail on a missing column
+    read_table(contents.as_bytes(), table).map_err(|e| e.to_string())
+}
+
+/// Reads a table from a reader, parsing each row as it is read
+/// # Errors
+/// This function will return an error if the table cannot be read,
+/// or with the `InvalidInput` kind if the column is not in the header
+pub(crate) fn read_table<R: BufRead>(reader: R, table: &Table) -> io::Result<Records> {
+    match table.format {
+        TableFormat::Csv => read_delimited(reader, table, b','),
+        TableFormat::Tsv => read_delimited(reader, table, b'\t'),
+        TableFormat::JsonLines => read_json_lines(reader, &table.column),
+    }
+}
+
+/// Reads a CSV or TSV table with the given delimiter
+fn read_delimited<R: BufRead>(reader: R, table: &Table, delimiter: u8) -> io::Result<Records> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(table.header)
+        .flexible(true)
+        .from_reader(reader);
+    let column = if table.header {
+        let header = reader.headers()?.clone();
+        find_column(&header, &table.column)
+    } else {
+        parse_position(&table.column)
+    }
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut records = Records::default();
+    let mut record = csv::StringRecord::new();
+    loop {
+        let line = reader.position().line() as usize;
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) if record.iter().all(|field| field.trim().is_empty()) => {}
+            Ok(true) => match record.get(column).and_then(parse_record) {
+                Some(number) => {
+                    records.numbers.push(number);
+                    records.rows.push(write_row(&record, column, delimiter));
+                }
+                None => records.rejected.push(line),
+            },
+            Err(error) if error.is_io_error() => return Err(error.into()),
+            // a malformed row, such as one with an unterminated quote
+            Err(_) => records.rejected.push(line),
+        }
+    }
+    Ok(records)
+}
+
+/// Finds a column in the header by its name, ignoring case and spaces, or by its position counted from 1
+fn find_column(header: &csv::StringRecord, column: &str) -> Result<usize, String> {
+    header
+        .iter()
+        .position(|name| name.trim().eq_ignore_ascii_case(column.trim()))
+        .map_or_else(|| parse_position(column), Ok)
+        .map_err(|_| {
+            let names: Vec<&str> = header.iter().collect();
+            format!("there is no column {} in the header, which has {}", column, names.join(", "))
+        })
+}
+
+/// Parses the position of a column counted from 1, returning its index
+fn parse_position(column: &str) -> Result<usize, String> {
+    match column.trim().parse::<usize>() {
+        Ok(position) if position > 0 => Ok(position - 1),
+        _ => Err(format!("the column must be a position counted from 1 when there is no header, not {}", column)),
+    }
+}
+
+/// Writes every field of a row apart from the phone number back in the same format
+fn write_row(record: &csv::StringRecord, column: usize, delimiter: u8) -> String {
+    let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(Vec::new());
+    let rest: Vec<&str> = record.iter().enumerate().filter(|(i, _)| *i != column).map(|(_, field)| field).collect();
+    // writing to a vector cannot fail
+    writer.write_record(&rest).unwrap_or_default();
+    let bytes = writer.into_inner().unwrap_or_default();
+    String::from_utf8_lossy(&bytes).trim_end_matches(['\r', '\n']).to_owned()
+}
+
+/// Reads one JSON object per line, taking the phone number from a field that can be nested
+/// with dots. The field can hold a string or a number.
+fn read_json_lines<R: BufRead>(mut reader: R, field: &str) -> io::Result<Records> {
+    let mut records = Records::default();
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        // a line that is not valid UTF-8 is not valid JSON either, so it is rejected
+        if std::str::from_utf8(&line).is_err() {
+            records.invalid_utf8.push(line_number);
+        }
+        let mut object: Value = match serde_json::from_slice(&line) {
+            Ok(object) => object,
+            Err(_) => {
+                records.rejected.push(line_number);
+                continue;
+            }
+        };
+        let number = match take_field(&mut object, field) {
+            Some(Value::String(text)) => parse_record(&text),
+            Some(Value::Number(number)) => parse_record(&number.to_string()),
+            _ => None,
+        };
+        match number {
+            Some(number) => {
+                records.numbers.push(number);
+                records.rows.push(object.to_string());
+            }
+            None => records.rejected.push(line_number),
+        }
+    }
+    Ok(records)
+}
+
+/// Removes a field, which can be nested with dots, from a JSON object and returns it
+fn take_field(object: &mut Value, field: &str) -> Option<Value> {
+    match field.split_once('.') {
+        Some((parent, rest)) => take_field(object.get_mut(parent)?, rest),
+        None => object.as_object_mut()?.remove(field),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(format: TableFormat, column: &str, header: bool) -> Table {
+        Table { format, column: column.to_owned(), header }
+    }
+
+    /// This test checks that quoted fields with delimiters, quotes and newlines in them are read correctly
+    #[test]
+    fn test_table_csv_quoting() {
+        let contents = "name,phone,notes\r\n\"Smith, Jo\",\"(732) 732-5555\",\"said \"\"hi\"\"\"\r\nLee,732-732-5554,\"two\nlines\"\r\n\r\nKim,7327325553\r\n";
+        let records = parse_table(contents, &table(TableFormat::Csv, "Phone", true)).unwrap();
+        assert_eq!(records.numbers, vec![7327325555, 7327325554, 7327325553]);
+        assert_eq!(records.rows, vec!["\"Smith, Jo\",\"said \"\"hi\"\"\"", "Lee,\"two\nlines\"", "Kim"]);
+        assert!(records.rejected.is_empty());
+    }
+
+    /// This test checks that columns are found by name or by position, with and without a header,
+    /// and that a missing column is an error
+    #[test]
+    fn test_table_columns() {
+        let contents = "name\tphone\nJo\t732 732 5555\nAl\tunknown\n";
+        let records = parse_table(contents, &table(TableFormat::Tsv, "2", true)).unwrap();
+        assert_eq!(records.numbers, vec![7327325555]);
+        assert_eq!(records.rows, vec!["Jo"]);
+        assert_eq!(records.rejected, vec![3]);
+        let records = parse_table(contents, &table(TableFormat::Tsv, "2", false)).unwrap();
+        assert_eq!(records.numbers, vec![7327325555]);
+        assert_eq!(records.rejected, vec![1, 3]);
+        assert!(parse_table(contents, &table(TableFormat::Tsv, "mobile", true)).is_err());
+        assert!(parse_table(contents, &table(TableFormat::Tsv, "phone", false)).is_err());
+        assert!(parse_table(contents, &table(TableFormat::Tsv, "0", false)).is_err());
+    }
+
+    /// This test checks that JSON lines are read from nested fields holding strings or numbers,
+    /// and that the rest of each object is kept
+    #[test]
+    fn test_table_json_lines() {
+        let contents = "{\"name\":\"Jo\",\"contact\":{\"phone\":\"+1 (732) 732-5555\",\"ext\":12}}\n\
+            {\"name\":\"Al\",\"contact\":{\"phone\":7327325554}}\n\
+            not json\n\
+            {\"name\":\"Kim\"}\n\n";
+        let records = parse_table(contents, &table(TableFormat::JsonLines, "contact.phone", true)).unwrap();
+        assert_eq!(records.numbers, vec![7327325555, 7327325554]);
+        assert_eq!(records.rows, vec!["{\"name\":\"Jo\",\"contact\":{\"ext\":12}}", "{\"name\":\"Al\",\"contact\":{}}"]);
+        assert_eq!(records.rejected, vec![3, 4]);
+    }
+}